@@ -0,0 +1,45 @@
+// A short run of the timer on a tokio runtime, pausing part way through.
+// Run with `cargo run --example async_timer --features tokio`.
+use std::time::Duration;
+
+use pomodoro_timer::{
+    async_timer,
+    timer::{PomodoroConfig, TimerCommand, TimerEvent},
+};
+use tokio::{sync::mpsc, time::sleep};
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let config = PomodoroConfig::builder()
+        .work(Duration::from_secs(3))
+        .break_(Duration::from_secs(2))
+        .cycles(Some(1))
+        .build()
+        .unwrap_or_else(|err| panic!("invalid config: {}", err));
+
+    let (commands, command_receiver) = mpsc::channel(8);
+    let (event_sender, mut events) = mpsc::channel(64);
+    let timer = tokio::spawn(async_timer::run(config, command_receiver, event_sender));
+
+    // The timer quits once every command sender is gone, so this task only
+    // gets a clone and `commands` lives until the end
+    let pauser = commands.clone();
+    tokio::spawn(async move {
+        sleep(Duration::from_millis(1500)).await;
+        let _ = pauser.send(TimerCommand::Pause).await;
+        sleep(Duration::from_secs(1)).await;
+        let _ = pauser.send(TimerCommand::Resume).await;
+    });
+
+    while let Some(event) = events.recv().await {
+        match event {
+            TimerEvent::Tick { remaining } => println!("  {}s left", remaining.as_secs()),
+            event => println!("{:?}", event),
+        }
+    }
+    match timer.await {
+        Ok(counts) => println!("Done: {} pomodoros", counts.completed_pomodoros),
+        Err(err) => eprintln!("error: the timer task failed: {}", err),
+    }
+    drop(commands);
+}
@@ -0,0 +1,182 @@
+use std::{
+    mem,
+    sync::{Arc, Mutex},
+};
+
+use chrono::Local;
+use tokio::{
+    sync::mpsc,
+    time::{Instant, sleep_until},
+};
+
+use crate::timer::{Counts, Phase, PomodoroConfig, PomodoroTimer, TimerCommand, TimerEvent};
+
+// Run a timer on the current async task, taking commands from `commands`
+// and sending everything it does to `events`. Closing `commands` quits, and
+// the totals are returned once the timer finishes.
+pub async fn run(
+    config: PomodoroConfig,
+    mut commands: mpsc::Receiver<TimerCommand>,
+    events: mpsc::Sender<TimerEvent>,
+) -> Counts {
+    let mut timer = PomodoroTimer::new(config);
+    // Listeners can't wait, so events are collected here and sent on after
+    // every step
+    let pending = Arc::new(Mutex::new(Vec::new()));
+    let collected = pending.clone();
+    timer.subscribe(move |event| {
+        if let Ok(mut pending) = collected.lock() {
+            pending.push(*event);
+        }
+    });
+
+    // Time since this was last taken counts towards the running session, or
+    // the time spent paused or idle
+    let mut last_counted = Instant::now();
+    while timer.phase() != Phase::Finished {
+        match timer.phase() {
+            Phase::Ready => timer.begin(Local::now().naive_local()),
+            // A paused timer still ticks, to count the time spent paused,
+            // and so does a snoozed one until the snooze is over
+            Phase::Running | Phase::Paused | Phase::Snoozed => {
+                let deadline = last_counted + timer.until_next_tick();
+                let command = tokio::select! {
+                    command = commands.recv() => Some(command.unwrap_or(TimerCommand::Quit)),
+                    _ = sleep_until(deadline) => None,
+                };
+                let now = Instant::now();
+                // A command that comes in just as the session ends applies
+                // to whatever comes next
+                timer.advance(now - last_counted);
+                last_counted = now;
+                if let Some(command) = command {
+                    timer.handle(command);
+                }
+            }
+            // Wakes up for reminders that the next session is waiting
+            Phase::Waiting => {
+                let command = match timer.until_reminder() {
+                    Some(wait) => tokio::select! {
+                        command = commands.recv() => Some(command.unwrap_or(TimerCommand::Quit)),
+                        _ = sleep_until(Instant::now() + wait) => None,
+                    },
+                    None => Some(commands.recv().await.unwrap_or(TimerCommand::Quit)),
+                };
+                // Only adds to the time spent idle, which mustn't be counted
+                // again if the command snoozes or undoes a skip
+                let now = Instant::now();
+                timer.advance(now - last_counted);
+                last_counted = now;
+                if let Some(command) = command {
+                    timer.handle(command);
+                }
+            }
+            Phase::Finished => {}
+        }
+        if !matches!(
+            timer.phase(),
+            Phase::Running | Phase::Paused | Phase::Snoozed
+        ) {
+            last_counted = Instant::now();
+        }
+
+        let ready = pending.lock().map(|mut pending| mem::take(&mut *pending));
+        for event in ready.unwrap_or_default() {
+            // Nobody listening isn't a reason to stop the timer
+            let _ = events.send(event).await;
+        }
+    }
+    timer.counts()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::sleep;
+
+    use super::*;
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    // Wait for the first event `wanted` matches, returning when it came
+    async fn until(
+        events: &mut mpsc::Receiver<TimerEvent>,
+        wanted: impl Fn(&TimerEvent) -> bool,
+    ) -> Instant {
+        while let Some(event) = events.recv().await {
+            if wanted(&event) {
+                return Instant::now();
+            }
+        }
+        panic!("the timer finished first");
+    }
+
+    // The timer's channels, for a timer that only starts sessions when told
+    fn spawn() -> (
+        mpsc::Sender<TimerCommand>,
+        mpsc::Receiver<TimerEvent>,
+        tokio::task::JoinHandle<Counts>,
+    ) {
+        let config = PomodoroConfig::builder()
+            .work(minutes(25))
+            .break_(minutes(5))
+            .auto_start(false)
+            .build()
+            .unwrap();
+        let (commands, command_receiver) = mpsc::channel(8);
+        let (event_sender, events) = mpsc::channel(64);
+        let timer = tokio::spawn(run(config, command_receiver, event_sender));
+        (commands, events, timer)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn snoozing_after_a_wait_snoozes_for_as_long_as_asked() {
+        let (commands, mut events, timer) = spawn();
+        until(&mut events, |event| {
+            matches!(event, TimerEvent::SessionCompleted { .. })
+        })
+        .await;
+        sleep(minutes(10)).await;
+        commands
+            .send(TimerCommand::Snooze(minutes(5)))
+            .await
+            .unwrap();
+        let snoozed = Instant::now();
+        let started = until(&mut events, |event| {
+            matches!(event, TimerEvent::SessionStarted { .. })
+        })
+        .await;
+        assert_eq!(started - snoozed, minutes(5));
+
+        drop(commands);
+        let counts = timer.await.unwrap();
+        assert_eq!(counts.idle, minutes(10));
+        assert_eq!(counts.snoozed, minutes(5));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn undoing_a_skip_after_a_wait_keeps_the_time_that_was_left() {
+        let (commands, mut events, timer) = spawn();
+        sleep(minutes(1)).await;
+        commands.send(TimerCommand::Skip).await.unwrap();
+        sleep(Duration::from_secs(8)).await;
+        commands.send(TimerCommand::Undo).await.unwrap();
+        let undone = until(&mut events, |event| {
+            matches!(event, TimerEvent::SkipUndone { .. })
+        })
+        .await;
+        let completed = until(&mut events, |event| {
+            matches!(event, TimerEvent::SessionCompleted { .. })
+        })
+        .await;
+        assert_eq!(completed - undone, minutes(24));
+
+        drop(commands);
+        let counts = timer.await.unwrap();
+        assert_eq!(counts.completed_pomodoros, 1);
+        assert_eq!(counts.focused, minutes(25));
+    }
+}
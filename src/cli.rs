@@ -0,0 +1,228 @@
+use std::{path::PathBuf, time::Duration};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+
+use chrono::{NaiveDate, NaiveTime};
+
+use crate::{
+    duration::{parse_date, parse_duration, parse_time_of_day},
+    error::PomodoroError,
+    format::{ClockStyle, CountdownFormat},
+    icons::IconStyle,
+    stats::Period,
+};
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "A simple Pomodoro timer for the terminal",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    // Running without a subcommand is the same as `start`
+    #[command(flatten)]
+    pub start: StartArgs,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run the timer (the default when no subcommand is given)
+    Start(StartArgs),
+    /// Show the sessions recorded so far
+    Stats {
+        /// Only sum up today, this week (from Monday), this month or all time,
+        /// instead of today, this week and all time
+        #[arg(long, value_enum, value_name = "PERIOD", conflicts_with_all = ["from", "to"])]
+        period: Option<Period>,
+
+        /// Only sessions started on or after this day (YYYY-MM-DD)
+        #[arg(long, value_name = "DATE", value_parser = parse_date)]
+        from: Option<NaiveDate>,
+
+        /// Only sessions started on or before this day (YYYY-MM-DD)
+        #[arg(long, value_name = "DATE", value_parser = parse_date)]
+        to: Option<NaiveDate>,
+
+        /// Print the summary as a JSON object
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the resolved settings and where each one came from
+    Config(StartArgs),
+    /// Print a shell completion script, e.g.
+    /// `completions zsh > _pomodoro_timer`
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+}
+
+#[derive(Args)]
+pub struct StartArgs {
+    /// Work session length, e.g. 25, 25m, 1h30m (skips the work duration
+    /// prompt)
+    #[arg(long = "work", value_name = "DURATION", value_parser = parse_duration)]
+    pub work: Option<Duration>,
+
+    /// Break length, e.g. 5, 90s, 10m (skips the break duration prompt)
+    #[arg(long = "break", value_name = "DURATION", value_parser = parse_duration)]
+    pub break_: Option<Duration>,
+
+    /// Use the durations from a [profiles.NAME] table in the config file
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Long break length, taken after every few completed work sessions
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub long_break: Option<Duration>,
+
+    /// Number of completed work sessions before a long break
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    pub sessions_until_long_break: Option<u64>,
+
+    /// End the first work session at this local time (HH:MM, 24-hour);
+    /// a time that has already passed today means tomorrow
+    #[arg(long, value_name = "HH:MM", value_parser = parse_time_of_day)]
+    pub until: Option<NaiveTime>,
+
+    /// Kind of session to begin with
+    #[arg(long, value_enum, value_name = "SESSION", default_value_t = StartWith::Work)]
+    pub start_with: StartWith,
+
+    /// Exit after this many completed work sessions
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    pub cycles: Option<u64>,
+
+    /// With --cycles, exit as soon as the last work session ends instead of
+    /// after its break
+    #[arg(long, requires = "cycles")]
+    pub no_final_break: bool,
+
+    /// Aim to complete this many work sessions today, counting earlier runs
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    pub goal: Option<u64>,
+
+    /// Run a single work session without a break, then exit
+    #[arg(long, conflicts_with = "cycles")]
+    pub once: bool,
+
+    /// Sound file played when a session finishes, passed to the sound command
+    #[arg(long, value_name = "PATH")]
+    pub sound_file: Option<PathBuf>,
+
+    /// Disable the terminal bell and the sound command
+    #[arg(long, visible_alias = "silent")]
+    pub mute: bool,
+
+    /// Start each session as soon as the previous one ends (the default)
+    #[arg(long, conflicts_with = "manual_start")]
+    pub auto_start: bool,
+
+    /// Wait for Enter before starting each session after the first
+    #[arg(long)]
+    pub manual_start: bool,
+
+    /// Countdown line template using {state}, {remaining}, {elapsed},
+    /// {session}, {total_sessions}, {ends_at} and {duration}
+    #[arg(long, value_name = "TEMPLATE", value_parser = check_format)]
+    pub format: Option<String>,
+
+    /// Show the time a session ends in 24-hour or 12-hour format
+    #[arg(long, value_enum)]
+    pub clock: Option<ClockStyle>,
+
+    /// Put icons in front of headers, the countdown and the summary, emoji
+    /// when the locale is UTF-8 and otherwise ASCII such as [W]
+    #[arg(long, value_enum, value_name = "STYLE", num_args = 0..=1, default_missing_value = "auto")]
+    pub icons: Option<IconStyle>,
+
+    /// Only print session start and finish lines, not the countdown
+    /// (implied when stdout is not a terminal)
+    #[arg(long)]
+    pub quiet: bool,
+
+    /// Take over the terminal with a full-screen view, where keys work
+    /// without pressing Enter
+    #[arg(long, conflicts_with_all = ["json", "quiet"])]
+    pub tui: bool,
+
+    /// Show the remaining time in large block digits above the countdown line
+    #[arg(long, conflicts_with_all = ["json", "quiet", "tui"])]
+    pub big: bool,
+
+    /// Show the remaining time in the terminal's title
+    #[arg(long)]
+    pub title: bool,
+
+    /// Print without colors, as when NO_COLOR is set or stdout is not a
+    /// terminal
+    #[arg(long)]
+    pub no_color: bool,
+
+    /// Don't show a progress bar after the countdown
+    #[arg(long)]
+    pub no_bar: bool,
+
+    /// Don't flash the screen or show a banner when a session finishes
+    #[arg(long)]
+    pub no_flash: bool,
+
+    /// Repeat the alert while a finished session waits for the next one to
+    /// be started, until a key is pressed
+    #[arg(long)]
+    pub nag: bool,
+
+    /// Don't show a desktop notification when a session finishes
+    #[arg(long)]
+    pub no_notify: bool,
+
+    /// Don't suggest something to do at the start of each break
+    #[arg(long)]
+    pub no_suggestions: bool,
+
+    /// Quit without asking, even in the middle of a work session
+    #[arg(long)]
+    pub no_confirm: bool,
+
+    /// Don't let work sessions be paused, skipped, restarted or extended
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Print one JSON object per line for each timer event instead of text
+    /// (implies --no-prompt)
+    #[arg(long)]
+    pub json: bool,
+
+    /// Print a single line such as "work 12:34 3/4" every second instead of
+    /// text, for shell prompts and scripts (implies --no-prompt)
+    #[arg(long, conflicts_with_all = ["json", "tui", "big"])]
+    pub status_line: bool,
+
+    /// Append every timer event to this file as one JSON object per line
+    #[arg(long, value_name = "PATH")]
+    pub log_events: Option<PathBuf>,
+
+    /// With --status-line, only print a line when it changes
+    #[arg(long, requires = "status_line")]
+    pub on_change: bool,
+
+    /// Skip all prompts and start the first work session immediately
+    #[arg(long, visible_alias = "yes")]
+    pub no_prompt: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum StartWith {
+    Work,
+    Break,
+}
+
+// Reject bad --format templates before anything is prompted for
+fn check_format(format: &str) -> Result<String, PomodoroError> {
+    CountdownFormat::parse(format)?;
+    Ok(format.to_string())
+}
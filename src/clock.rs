@@ -0,0 +1,106 @@
+use std::{
+    cell::Cell,
+    sync::mpsc::{Receiver, RecvTimeoutError, TryRecvError},
+    time::{Duration, Instant, SystemTime},
+};
+
+use chrono::{Local, NaiveDateTime, TimeDelta};
+
+// Where the timer loop gets the time from, so that it can be run against
+// simulated time as well as the real thing
+pub trait Clock {
+    // Monotonic time, which stands still while the computer is asleep
+    fn now(&self) -> Instant;
+
+    // Wall-clock time, which keeps going while the computer is asleep
+    fn wall_time(&self) -> SystemTime;
+
+    // The local date and time, e.g. for --until
+    fn local_time(&self) -> NaiveDateTime;
+
+    // Wait up to `timeout` for the next message on `receiver`
+    fn wait<T>(&self, receiver: &Receiver<T>, timeout: Duration) -> Result<T, RecvTimeoutError>;
+}
+
+// The real clocks
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn wall_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn local_time(&self) -> NaiveDateTime {
+        Local::now().naive_local()
+    }
+
+    fn wait<T>(&self, receiver: &Receiver<T>, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        receiver.recv_timeout(timeout)
+    }
+}
+
+// A clock that only moves when told to. Waiting never blocks: with no
+// message ready, time jumps straight to the end of the wait, so a whole
+// cycle of sessions runs in no time at all.
+pub struct MockClock {
+    start: Instant,
+    wall_start: SystemTime,
+    local_start: NaiveDateTime,
+    elapsed: Cell<Duration>,
+    // Time only the wall clock has seen, as if the computer had been asleep
+    asleep: Cell<Duration>,
+}
+
+impl MockClock {
+    pub fn new(local_start: NaiveDateTime) -> Self {
+        MockClock {
+            start: Instant::now(),
+            wall_start: SystemTime::now(),
+            local_start,
+            elapsed: Cell::new(Duration::ZERO),
+            asleep: Cell::new(Duration::ZERO),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.elapsed.set(self.elapsed.get() + by);
+    }
+
+    // Move the wall clock on but not the monotonic one
+    pub fn sleep(&self, by: Duration) {
+        self.asleep.set(self.asleep.get() + by);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed.get()
+    }
+
+    fn wall_time(&self) -> SystemTime {
+        self.wall_start + self.elapsed.get() + self.asleep.get()
+    }
+
+    fn local_time(&self) -> NaiveDateTime {
+        let passed = self.elapsed.get() + self.asleep.get();
+        TimeDelta::from_std(passed)
+            .ok()
+            .and_then(|passed| self.local_start.checked_add_signed(passed))
+            .unwrap_or(NaiveDateTime::MAX)
+    }
+
+    fn wait<T>(&self, receiver: &Receiver<T>, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        match receiver.try_recv() {
+            Ok(message) => Ok(message),
+            Err(TryRecvError::Empty) => {
+                self.advance(timeout);
+                Err(RecvTimeoutError::Timeout)
+            }
+            Err(TryRecvError::Disconnected) => Err(RecvTimeoutError::Disconnected),
+        }
+    }
+}
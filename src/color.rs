@@ -0,0 +1,121 @@
+use std::fmt;
+
+use serde::{Deserialize, Deserializer, de};
+
+use crate::{error::PomodoroError, timer::TimerState};
+
+const COLOR_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+// A terminal text style such as "bold red", "green" or "default" for no
+// color, as written in the [colors] table
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Color {
+    // Index into COLOR_NAMES
+    color: Option<u8>,
+    bold: bool,
+}
+
+impl Color {
+    const fn new(color: Option<u8>, bold: bool) -> Self {
+        Color { color, bold }
+    }
+
+    pub fn parse(name: &str) -> Result<Self, PomodoroError> {
+        let mut color = Color::new(None, false);
+        let mut named = false;
+        for word in name.split_whitespace() {
+            let word = word.to_lowercase();
+            if word == "bold" && !color.bold {
+                color.bold = true;
+            } else if word == "default" && !named {
+                named = true;
+            } else if let Some(index) = COLOR_NAMES.iter().position(|&known| known == word)
+                && !named
+            {
+                color.color = Some(index as u8);
+                named = true;
+            } else {
+                return Err(PomodoroError::InvalidConfig(format!(
+                    "invalid color '{}', expected one of {} or default, optionally after bold",
+                    name,
+                    COLOR_NAMES.join(", ")
+                )));
+            }
+        }
+        if named || color.bold {
+            Ok(color)
+        } else {
+            Err(PomodoroError::InvalidConfig(
+                "color must not be empty".to_string(),
+            ))
+        }
+    }
+
+    // `text` wrapped in the escape codes for this style
+    pub fn paint(self, text: &str) -> String {
+        let mut codes = Vec::new();
+        if self.bold {
+            codes.push("1".to_string());
+        }
+        if let Some(color) = self.color {
+            codes.push((30 + color).to_string());
+        }
+        if codes.is_empty() {
+            text.to_string()
+        } else {
+            format!("\x1b[{}m{}\x1b[0m", codes.join(";"), text)
+        }
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = self
+            .color
+            .map_or("default", |color| COLOR_NAMES[usize::from(color)]);
+        if self.bold {
+            write!(f, "bold {}", name)
+        } else {
+            write!(f, "{}", name)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Color::parse(&name).map_err(de::Error::custom)
+    }
+}
+
+// The colors the console output is drawn in
+#[derive(Clone, Copy)]
+pub struct Palette {
+    pub work: Color,
+    pub break_: Color,
+    pub paused: Color,
+}
+
+impl Palette {
+    // The color of a session's header and countdown, long breaks share the
+    // break color
+    pub fn session(&self, kind: TimerState) -> Color {
+        match kind {
+            TimerState::Work => self.work,
+            TimerState::Break | TimerState::LongBreak => self.break_,
+            TimerState::Stopped => Color::new(None, false),
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            work: Color::new(Some(1), true),
+            break_: Color::new(Some(2), false),
+            paused: Color::new(Some(3), false),
+        }
+    }
+}
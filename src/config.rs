@@ -0,0 +1,376 @@
+use std::{
+    collections::BTreeMap,
+    env, fmt, fs,
+    io::{ErrorKind, Write},
+    path::PathBuf,
+    time::Duration,
+};
+
+use serde::Deserialize;
+
+use crate::{
+    color::Color,
+    duration::MAX_SECS,
+    error::PomodoroError,
+    format::{ClockStyle, CountdownFormat},
+    icons::IconStyle,
+};
+
+// Settings read from ~/.config/pomodoro/config.toml, every key is optional.
+// Durations are in minutes and may be fractional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub work_minutes: Option<f64>,
+    pub break_minutes: Option<f64>,
+    pub long_break_minutes: Option<f64>,
+    pub sessions_until_long_break: Option<u64>,
+    // Work sessions to complete each day
+    pub goal: Option<u64>,
+    // Ask what each work session is for before it starts
+    pub ask_task: Option<bool>,
+    // Work sessions can only be completed or abandoned
+    pub strict: Option<bool>,
+    // Where ended sessions are kept
+    pub history: Option<HistoryBackend>,
+    pub sound_command: Option<String>,
+    pub sound_file: Option<PathBuf>,
+    pub start_sound_file: Option<PathBuf>,
+    pub sound_on_start: Option<bool>,
+    pub sound_on_end: Option<bool>,
+    pub mute: Option<bool>,
+    pub auto_start: Option<bool>,
+    // Override auto_start for breaks and for work sessions
+    pub auto_start_breaks: Option<bool>,
+    pub auto_start_work: Option<bool>,
+    pub format: Option<String>,
+    // Shown when sessions start and end, with the same placeholders as
+    // `format`
+    pub message_work_start: Option<String>,
+    pub message_work_end: Option<String>,
+    pub message_break_start: Option<String>,
+    pub message_break_end: Option<String>,
+    pub clock: Option<ClockStyle>,
+    pub sleep_threshold_minutes: Option<f64>,
+    // What the extend key adds to the running session
+    pub extend_minutes: Option<f64>,
+    // How long the snooze key puts off the next session
+    pub snooze_minutes: Option<f64>,
+    pub on_sleep: Option<SleepPolicy>,
+    pub title: Option<bool>,
+    pub notify: Option<bool>,
+    pub notify_urgency: Option<Urgency>,
+    pub nag: Option<bool>,
+    pub nag_interval_seconds: Option<u64>,
+    pub show_elapsed: Option<bool>,
+    pub icons: Option<IconStyle>,
+    #[serde(default)]
+    pub keys: KeysConfig,
+    #[serde(default)]
+    pub colors: ColorsConfig,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+// The [keys] table, mapping each command to the key that triggers it
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeysConfig {
+    pub pause: Option<String>,
+    pub resume: Option<String>,
+    pub skip: Option<String>,
+    pub skip_break: Option<String>,
+    pub undo: Option<String>,
+    pub next: Option<String>,
+    pub extend: Option<String>,
+    pub add_minute: Option<String>,
+    pub remove_minute: Option<String>,
+    pub restart: Option<String>,
+    pub long_break: Option<String>,
+    #[serde(rename = "break")]
+    pub break_: Option<String>,
+    pub snooze: Option<String>,
+    pub note: Option<String>,
+    pub save: Option<String>,
+    pub toggle: Option<String>,
+    pub mute: Option<String>,
+    pub status: Option<String>,
+    pub goal: Option<String>,
+    pub strict: Option<String>,
+    pub help: Option<String>,
+    pub stop: Option<String>,
+    pub quit: Option<String>,
+}
+
+// The [colors] table, e.g. work = "bold red"
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColorsConfig {
+    pub work: Option<Color>,
+    #[serde(rename = "break")]
+    pub break_: Option<Color>,
+    pub paused: Option<Color>,
+}
+
+// A named set of durations, e.g. [profiles.writing], overriding the top level
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    // Filled in from the table name after loading
+    #[serde(skip)]
+    pub name: String,
+    pub work: Option<f64>,
+    #[serde(rename = "break")]
+    pub break_: Option<f64>,
+    pub long_break: Option<f64>,
+}
+
+// What to do with time the computer spent asleep in the middle of a session
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SleepPolicy {
+    // Pause and let the user choose one of the others
+    #[default]
+    Ask,
+    Count,
+    Discard,
+    Abandon,
+}
+
+impl fmt::Display for SleepPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SleepPolicy::Ask => write!(f, "ask"),
+            SleepPolicy::Count => write!(f, "count"),
+            SleepPolicy::Discard => write!(f, "discard"),
+            SleepPolicy::Abandon => write!(f, "abandon"),
+        }
+    }
+}
+
+// How insistent a desktop notification is, for the notification daemon
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Urgency {
+    Low,
+    #[default]
+    Normal,
+    Critical,
+}
+
+impl fmt::Display for Urgency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Urgency::Low => write!(f, "low"),
+            Urgency::Normal => write!(f, "normal"),
+            Urgency::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+// Where the session history is kept: the CSV file, or a SQLite database
+// when built with the sqlite feature
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryBackend {
+    #[default]
+    Csv,
+    Sqlite,
+}
+
+impl fmt::Display for HistoryBackend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HistoryBackend::Csv => write!(f, "csv"),
+            HistoryBackend::Sqlite => write!(f, "sqlite"),
+        }
+    }
+}
+
+// The runtime settings written back by the save-config command
+pub struct Defaults {
+    pub work: Duration,
+    pub break_: Duration,
+    pub long_break: Duration,
+    pub sessions_until_long_break: u64,
+}
+
+impl Config {
+    // Look up a profile, listing the valid names if it doesn't exist
+    pub fn profile(&self, name: &str) -> Result<&Profile, PomodoroError> {
+        self.profiles
+            .get(name)
+            .ok_or_else(|| PomodoroError::UnknownProfile {
+                name: name.to_string(),
+                available: self.profiles.keys().cloned().collect(),
+            })
+    }
+
+    pub fn profile_names(&self) -> Vec<&str> {
+        self.profiles.keys().map(String::as_str).collect()
+    }
+}
+
+// $XDG_CONFIG_HOME/pomodoro/config.toml, falling back to ~/.config
+pub fn config_path() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("pomodoro").join("config.toml"))
+}
+
+// Load the config file, a missing file just means "use the defaults"
+pub fn load() -> Result<Config, PomodoroError> {
+    let Some(path) = config_path() else {
+        return Ok(Config::default());
+    };
+
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Config::default()),
+        Err(source) => {
+            return Err(PomodoroError::Io {
+                action: "read",
+                path,
+                source,
+            });
+        }
+    };
+
+    let parse_error = |message: String| PomodoroError::ConfigParse {
+        path: path.clone(),
+        message,
+    };
+    let table: toml::Table =
+        toml::from_str(&contents).map_err(|err| parse_error(err.to_string()))?;
+    let mut config = Config::deserialize(table).map_err(|err| parse_error(err.to_string()))?;
+    for (name, profile) in &mut config.profiles {
+        profile.name = name.clone();
+    }
+
+    let invalid = |key: &str, reason: &str| parse_error(format!("`{}` {}", key, reason));
+
+    let mut minutes = vec![
+        ("work_minutes".to_string(), config.work_minutes),
+        ("break_minutes".to_string(), config.break_minutes),
+        ("long_break_minutes".to_string(), config.long_break_minutes),
+        (
+            "sleep_threshold_minutes".to_string(),
+            config.sleep_threshold_minutes,
+        ),
+        ("extend_minutes".to_string(), config.extend_minutes),
+        ("snooze_minutes".to_string(), config.snooze_minutes),
+    ];
+    for (name, profile) in &config.profiles {
+        minutes.push((format!("profiles.{}.work", name), profile.work));
+        minutes.push((format!("profiles.{}.break", name), profile.break_));
+        minutes.push((format!("profiles.{}.long_break", name), profile.long_break));
+    }
+    // Held to the same limits as the durations typed in, since a length the
+    // timer can't add to the clock would only fail once it's running
+    let invalid_minutes = |key: &str, reason: &str| {
+        PomodoroError::InvalidConfig(format!("`{}` in {} {}", key, path.display(), reason))
+    };
+    for (key, value) in minutes {
+        match value {
+            Some(value) if !(value.is_finite() && value * 60.0 >= 1.0) => {
+                return Err(invalid_minutes(&key, "must be at least one second"));
+            }
+            Some(value) if value * 60.0 > MAX_SECS => {
+                return Err(invalid_minutes(&key, "must be at most a year"));
+            }
+            _ => {}
+        }
+    }
+    for (key, format) in [
+        ("format", &config.format),
+        ("message_work_start", &config.message_work_start),
+        ("message_work_end", &config.message_work_end),
+        ("message_break_start", &config.message_break_start),
+        ("message_break_end", &config.message_break_end),
+    ] {
+        if let Some(format) = format {
+            CountdownFormat::parse(format)
+                .map_err(|err| invalid(key, &format!("is invalid: {}", err)))?;
+        }
+    }
+    if config.sessions_until_long_break == Some(0) {
+        return Err(invalid(
+            "sessions_until_long_break",
+            "must be greater than zero",
+        ));
+    }
+    if config.goal == Some(0) {
+        return Err(invalid("goal", "must be greater than zero"));
+    }
+
+    Ok(config)
+}
+
+// Convert a minutes value from the config file, rounded to whole seconds
+pub fn minutes_to_duration(minutes: f64) -> Duration {
+    Duration::from_secs((minutes * 60.0).round() as u64)
+}
+
+// Write the durations back to the config file, keeping every other key.
+// The new file is written next to the old one and renamed over it so that
+// a crash part way through can't leave a truncated config behind.
+pub fn save(defaults: &Defaults) -> Result<PathBuf, PomodoroError> {
+    let path = config_path().ok_or(PomodoroError::NoConfigDir)?;
+    let io_error = |source: std::io::Error| PomodoroError::Io {
+        action: "write",
+        path: path.clone(),
+        source,
+    };
+
+    let mut table = match fs::read_to_string(&path) {
+        Ok(contents) => toml::from_str(&contents).map_err(|err| PomodoroError::ConfigParse {
+            path: path.clone(),
+            message: err.to_string(),
+        })?,
+        Err(err) if err.kind() == ErrorKind::NotFound => toml::Table::new(),
+        Err(source) => {
+            return Err(PomodoroError::Io {
+                action: "read",
+                path,
+                source,
+            });
+        }
+    };
+
+    for (key, duration) in [
+        ("work_minutes", defaults.work),
+        ("break_minutes", defaults.break_),
+        ("long_break_minutes", defaults.long_break),
+    ] {
+        table.insert(key.to_string(), duration_to_minutes(duration));
+    }
+    table.insert(
+        "sessions_until_long_break".to_string(),
+        toml::Value::Integer(defaults.sessions_until_long_break as i64),
+    );
+
+    let contents =
+        toml::to_string(&table).map_err(|err| PomodoroError::InvalidConfig(err.to_string()))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(io_error)?;
+    }
+    let temp_path = path.with_extension("toml.tmp");
+    let mut file = fs::File::create(&temp_path).map_err(io_error)?;
+    file.write_all(contents.as_bytes()).map_err(io_error)?;
+    file.sync_all().map_err(io_error)?;
+    fs::rename(&temp_path, &path).map_err(io_error)?;
+
+    Ok(path)
+}
+
+// Whole minutes are written as integers so hand-edited files stay tidy
+fn duration_to_minutes(duration: Duration) -> toml::Value {
+    let secs = duration.as_secs();
+    if secs.is_multiple_of(60) {
+        toml::Value::Integer((secs / 60) as i64)
+    } else {
+        toml::Value::Float(secs as f64 / 60.0)
+    }
+}
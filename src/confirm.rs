@@ -0,0 +1,126 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use pomodoro_timer::timer::{TimerCommand, TimerEvent, TimerState};
+
+// Quitting a work session that has run longer than this asks first
+const CONFIRM_AFTER: Duration = Duration::from_secs(60);
+
+// The timer listener that keeps track of whether quitting would throw away a
+// work session worth keeping, for the input loops to ask before they do.
+// Left out with --no-confirm, so nothing ever needs confirming.
+pub struct QuitGuard {
+    at_risk: Arc<AtomicBool>,
+    kind: TimerState,
+    length: Duration,
+    remaining: Duration,
+}
+
+impl QuitGuard {
+    pub fn new(at_risk: Arc<AtomicBool>) -> Self {
+        QuitGuard {
+            at_risk,
+            kind: TimerState::Work,
+            length: Duration::ZERO,
+            remaining: Duration::ZERO,
+        }
+    }
+
+    pub fn on_event(&mut self, event: &TimerEvent) {
+        let at_risk = match *event {
+            TimerEvent::SessionStarted { kind, duration, .. } => {
+                self.kind = kind;
+                self.length = duration;
+                self.remaining = duration;
+                false
+            }
+            TimerEvent::Tick { remaining } => {
+                self.remaining = remaining;
+                self.running_work()
+            }
+            // Quitting while paused is taken to be deliberate
+            TimerEvent::Paused { .. } | TimerEvent::PausedTick { .. } => false,
+            TimerEvent::Resumed { .. } => self.running_work(),
+            // Nothing is at stake again until the next minute has gone by
+            TimerEvent::Restarted { duration, .. } => {
+                self.length = duration;
+                self.remaining = duration;
+                false
+            }
+            TimerEvent::SkipUndone {
+                kind,
+                duration,
+                remaining,
+                ..
+            } => {
+                self.kind = kind;
+                self.length = duration;
+                self.remaining = remaining;
+                self.running_work()
+            }
+            // Counted from the next tick
+            TimerEvent::Extended { added, duration } => {
+                self.length = duration;
+                self.remaining += added;
+                return;
+            }
+            TimerEvent::Nudged {
+                duration,
+                remaining,
+            } => {
+                self.length = duration;
+                self.remaining = remaining;
+                return;
+            }
+            TimerEvent::Suspended { .. }
+            | TimerEvent::SessionEnded { .. }
+            | TimerEvent::SnoozeTick { .. }
+            | TimerEvent::Upcoming { .. }
+            | TimerEvent::Reminder { .. }
+            | TimerEvent::Acknowledged
+            | TimerEvent::StrictToggled { .. } => return,
+            TimerEvent::SessionCompleted { .. }
+            | TimerEvent::SessionSkipped { .. }
+            | TimerEvent::SessionAbandoned { .. }
+            | TimerEvent::Interrupted { .. }
+            | TimerEvent::Snoozed { .. }
+            | TimerEvent::Stopped { .. }
+            | TimerEvent::Finished => false,
+        };
+        self.at_risk.store(at_risk, Ordering::SeqCst);
+    }
+
+    fn running_work(&self) -> bool {
+        self.kind == TimerState::Work && self.length - self.remaining > CONFIRM_AFTER
+    }
+}
+
+// What's asked before sending `command`, which is Quit or ToggleStrict
+pub fn question(command: TimerCommand) -> &'static str {
+    match command {
+        TimerCommand::ToggleStrict => "Turn strict mode on or off? (y/n)",
+        _ => "Quit and abandon the current session? (y/n)",
+    }
+}
+
+// What's said when the answer to question() is no
+pub fn declined(command: TimerCommand) -> &'static str {
+    match command {
+        TimerCommand::ToggleStrict => "Strict mode left as it was.",
+        _ => "Not quitting.",
+    }
+}
+
+// The answer to question(), None for anything that's neither yes nor no
+pub fn answer(input: &str) -> Option<bool> {
+    match input.trim().to_lowercase().as_str() {
+        "y" | "yes" => Some(true),
+        "n" | "no" => Some(false),
+        _ => None,
+    }
+}
@@ -0,0 +1,723 @@
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use chrono::Local;
+use pomodoro_timer::{
+    color::{Color, Palette},
+    config::SleepPolicy,
+    duration::{format_duration, format_hours_minutes},
+    format::{
+        BIG_ROWS, ClockStyle, CountdownFormat, Messages, Snapshot, big_text, format_clock,
+        next_preview, progress_bar,
+    },
+    history::FocusedToday,
+    icons::Icons,
+    keys::{Action, KeyBindings},
+    timer::{Counts, PomodoroConfig, Session, TimerEvent, TimerState},
+};
+
+use crate::{
+    display::{Display, terminal_height, terminal_width},
+    end_time,
+    notes::Notes,
+};
+
+// Terminals narrower than this get the countdown without a progress bar
+const MIN_BAR_COLUMNS: usize = 30;
+
+// Terminals narrower than this only get the time, e.g. "12:34"
+const MIN_LINE_COLUMNS: usize = 20;
+
+// Bounds on the number of cells between the bar's brackets
+const MIN_BAR_WIDTH: usize = 10;
+const MAX_BAR_WIDTH: usize = 40;
+
+// Marks the countdown line while the session is paused
+const PAUSED_MARKER: &str = "\u{23f8}";
+// Marks the countdown line while the sound is muted
+const MUTED_MARKER: &str = "\u{1f507}";
+
+// Frames of the spinner after the paused-for counter, turning once a second,
+// so that a paused timer doesn't look like it has hung
+const SPINNER_FRAMES: [&str; 4] = ["\u{25d0}", "\u{25d3}", "\u{25d1}", "\u{25d2}"];
+const SPINNER_INTERVAL: Duration = Duration::from_millis(250);
+
+// Where the spinner goes in the paused line, replaced by a frame when drawn
+const SPINNER_SLOT: &str = "\u{1}";
+
+// The paused countdown, painted, and the spinner's frame. Shared with the
+// thread animating it until the session resumes, which sets it to None.
+type Frozen = Arc<Mutex<Option<(String, usize)>>>;
+
+// How the countdown is drawn while a session runs
+pub struct CountdownStyle {
+    // Redraw it on every tick, only done on a terminal
+    pub shown: bool,
+    // Follow it with a progress bar, turned off by --no-bar
+    pub bar: bool,
+    // Put the remaining time above it in block digits, with --big
+    pub big: bool,
+    // Show the time elapsed instead of the time remaining, switched by the
+    // timer loop when the toggle key is pressed
+    pub elapsed: Arc<AtomicBool>,
+    // Time focused today, shown after it as "[today: 2h05m]"
+    pub today: FocusedToday,
+    // Followed by a muted marker while set, switched by the timer loop when
+    // the mute key is pressed
+    pub muted: Arc<AtomicBool>,
+    // Animate it while paused. Only done when keys aren't echoed, since
+    // redrawing the line would wipe them out.
+    pub spinner: bool,
+}
+
+// The timer listener that prints what happens for a human to read
+pub struct Console {
+    display: Display,
+    keys: KeyBindings,
+    format: CountdownFormat,
+    // In place of the lines at the start and end of sessions
+    messages: Messages,
+    clock: ClockStyle,
+    countdown: CountdownStyle,
+    // None when colors are turned off
+    colors: Option<Palette>,
+    // In front of the headers and the countdown, with --icons
+    icons: Option<Icons>,
+    // Shown as {total_sessions} in the countdown line
+    total_sessions: u64,
+    sessions_until_long_break: u64,
+    cycles: Option<u64>,
+    // With --once there's no cycle to show progress through
+    once: bool,
+    // The kind, number and length of the running session
+    session: (TimerState, u64, Duration),
+    // As of the last tick, for redrawing the countdown when it pauses
+    remaining: Duration,
+    // The run's focused time before the running session
+    focused_before: Duration,
+    // Set while the spinner is turning
+    frozen: Option<Frozen>,
+    // What comes after the running session, as next_preview() puts it
+    next: Option<String>,
+    // The session put off by a snooze
+    snoozed: Option<Session>,
+    // Typed before commands that don't have a key, like "work 50"
+    command_prefix: String,
+    // Where the task asked for before each work session is kept, None when
+    // it isn't asked for
+    tasks: Option<Notes>,
+    // The running work session's task, in its header and countdown line
+    task: Option<String>,
+    // Work headers are marked [STRICT] while it's on
+    strict: bool,
+}
+
+impl Console {
+    pub fn new(
+        display: Display,
+        keys: KeyBindings,
+        format: CountdownFormat,
+        clock: ClockStyle,
+        countdown: CountdownStyle,
+        colors: Option<Palette>,
+        plan: &PomodoroConfig,
+    ) -> Self {
+        Console {
+            display,
+            keys,
+            format,
+            messages: Messages::default(),
+            clock,
+            countdown,
+            colors,
+            icons: None,
+            total_sessions: plan.cycles().unwrap_or(plan.sessions_until_long_break()),
+            sessions_until_long_break: plan.sessions_until_long_break(),
+            cycles: plan.cycles(),
+            once: plan.once(),
+            session: (TimerState::Work, 0, Duration::ZERO),
+            remaining: Duration::ZERO,
+            focused_before: Duration::ZERO,
+            frozen: None,
+            next: None,
+            snoozed: None,
+            command_prefix: String::new(),
+            tasks: None,
+            task: None,
+            strict: plan.strict(),
+        }
+    }
+
+    pub fn with_messages(self, messages: Messages) -> Self {
+        Console { messages, ..self }
+    }
+
+    pub fn with_icons(self, icons: Option<Icons>) -> Self {
+        Console { icons, ..self }
+    }
+
+    pub fn with_command_prefix(self, command_prefix: String) -> Self {
+        Console {
+            command_prefix,
+            ..self
+        }
+    }
+
+    pub fn with_tasks(self, tasks: Option<Notes>) -> Self {
+        Console { tasks, ..self }
+    }
+
+    // `text` after one of the icons, if there are any
+    fn icon(&self, icon: impl Fn(&Icons) -> &'static str, text: String) -> String {
+        match &self.icons {
+            Some(icons) => format!("{} {}", icon(icons), text),
+            None => text,
+        }
+    }
+
+    // Where a session falls in the run, e.g. "Pomodoro 3 of 4 until long
+    // break — 2 completed so far, 50 min focused"
+    fn overview(&self, kind: TimerState, number: u64, counts: Counts) -> String {
+        let minutes = counts.focused.as_secs() / 60;
+        if kind != TimerState::Work {
+            return format!(
+                "{} completed so far, {} min focused",
+                count(counts.completed_pomodoros, "pomodoro", "pomodoros"),
+                minutes
+            );
+        }
+        let until = self.sessions_until_long_break;
+        let mut position = format!(
+            "Pomodoro {} of {} until long break",
+            (number.max(1) - 1) % until + 1,
+            until
+        );
+        if let Some(cycles) = self.cycles {
+            position += &format!(", {} of {} planned", counts.completed_pomodoros + 1, cycles);
+        }
+        format!(
+            "{} — {} completed so far, {} min focused",
+            position, counts.completed_pomodoros, minutes
+        )
+    }
+
+    // The task for a session of `kind`, only work sessions have one
+    fn work_task(&self, kind: TimerState) -> Option<String> {
+        self.tasks
+            .as_ref()
+            .filter(|_| kind == TimerState::Work)
+            .and_then(Notes::task)
+    }
+
+    // The countdown line for `remaining`, followed by a progress bar when
+    // there's room for one
+    fn countdown_line(&self, remaining: Duration) -> String {
+        let (kind, number, length) = self.session;
+        let snapshot = Snapshot {
+            state: kind.name(),
+            remaining,
+            elapsed: length - remaining,
+            session: number,
+            total_sessions: self.total_sessions,
+            // Worked out from now rather than the session start so that time
+            // spent paused pushes it back
+            ends_at: end_time(remaining),
+            duration: length,
+        };
+        let line = if self.countdown.elapsed.load(Ordering::Relaxed) {
+            format!(
+                "Elapsed: {} / {}",
+                format_clock(snapshot.elapsed),
+                format_clock(length)
+            )
+        } else {
+            self.format.render(&snapshot)
+        };
+        let line = match &self.task {
+            Some(task) => format!("{} — {}", line, task),
+            None => line,
+        };
+        self.with_bar(self.icon(|icons| icons.session(kind), line), remaining)
+    }
+
+    // "⏸ Paused for 03:12 (12:34 remaining in Work Session 2)", with the
+    // progress bar where it stopped
+    fn paused_line(&self, paused: Duration) -> String {
+        let (kind, number, length) = self.session;
+        let position = if self.countdown.elapsed.load(Ordering::Relaxed) {
+            format!(
+                "{} of {} elapsed",
+                format_clock(length - self.remaining),
+                format_clock(length)
+            )
+        } else {
+            format!("{} remaining", format_clock(self.remaining))
+        };
+        let spinner = if self.countdown.spinner {
+            format!(" {}", SPINNER_SLOT)
+        } else {
+            String::new()
+        };
+        let line = format!(
+            "{} Paused for {}{} ({} in {})",
+            self.icons.map_or(PAUSED_MARKER, |icons| icons.paused),
+            format_clock(paused),
+            spinner,
+            position,
+            kind.label(number)
+        );
+        self.with_bar(line, self.remaining)
+    }
+
+    // "Snoozing 02:59 before Break 1", redrawn every second of the snooze
+    fn draw_snooze(&self, remaining: Duration) {
+        let Some(next) = self.snoozed.filter(|_| self.countdown.shown) else {
+            return;
+        };
+        let line = format!(
+            "Snoozing {} before {}",
+            format_clock(remaining),
+            next.label()
+        );
+        let line = self.icon(|icons| icons.paused, line);
+        self.display
+            .countdown(&self.paint(|colors| colors.paused, &line));
+    }
+
+    // `line` and the time focused today, followed by a progress bar when
+    // there's room for one
+    fn with_bar(&self, line: String, remaining: Duration) -> String {
+        // Asked every time, so that a resized terminal gets a line to fit
+        // from the next tick on
+        let columns = terminal_width().unwrap_or(80);
+        if columns < MIN_LINE_COLUMNS {
+            return format_clock(self.shown(remaining));
+        }
+        let mut line = format!(
+            "{} [today: {}]",
+            line,
+            format_hours_minutes(self.countdown.today.total())
+        );
+        if self.countdown.muted.load(Ordering::Relaxed) {
+            line.push(' ');
+            line.push_str(self.icons.map_or(MUTED_MARKER, |icons| icons.muted));
+        }
+        let length = self.session.2;
+        if !self.countdown.bar || columns < MIN_BAR_COLUMNS {
+            return line;
+        }
+        // Leave room for the space and brackets around the bar and the last
+        // column, which some terminals wrap on, with a couple to spare
+        let room = columns.saturating_sub(line.chars().count() + 6);
+        if room < MIN_BAR_WIDTH {
+            return line;
+        }
+        let bar = progress_bar(length - remaining, length, room.min(MAX_BAR_WIDTH));
+        format!("{} {}", line, bar)
+    }
+
+    // `line` and what comes next below it, with --big under the remaining
+    // or elapsed time in block digits unless the terminal is too small for
+    // them
+    fn countdown_text(&self, remaining: Duration, line: String) -> String {
+        let line = match &self.next {
+            Some(next) if terminal_width().is_none_or(|columns| columns >= MIN_LINE_COLUMNS) => {
+                format!("{}\n{}", line, next)
+            }
+            _ => line,
+        };
+        if !self.countdown.big {
+            return line;
+        }
+        let digits = big_text(&format_clock(self.shown(remaining)));
+        let width = digits[0].chars().count();
+        // The digits, a blank line and the countdown lines, with a row to
+        // spare
+        let rows = BIG_ROWS + 1 + line.lines().count();
+        let fits = terminal_width().is_some_and(|columns| columns > width)
+            && terminal_height().is_some_and(|rows_there| rows_there > rows);
+        if !fits {
+            return line;
+        }
+        format!("{}\n\n{}", digits.join("\n"), line)
+    }
+
+    // The remaining or elapsed time, whichever the countdown shows
+    fn shown(&self, remaining: Duration) -> Duration {
+        if self.countdown.elapsed.load(Ordering::Relaxed) {
+            self.session.2 - remaining
+        } else {
+            remaining
+        }
+    }
+
+    // Show the countdown where it stopped, dimmed and with how long it has
+    // been paused
+    fn freeze_countdown(&mut self, paused: Duration) {
+        if !self.countdown.shown {
+            return;
+        }
+        let display = self.display;
+        let text = self.countdown_text(self.remaining, self.paused_line(paused));
+        let text = self.paint(|colors| colors.paused, &text);
+        if !self.countdown.spinner {
+            display.frozen_countdown(&text);
+            return;
+        }
+        match &self.frozen {
+            Some(frozen) => {
+                if let Ok(mut frozen) = frozen.lock()
+                    && let Some((_, frame)) = *frozen
+                {
+                    display.frozen_countdown(&spinner_frame(&text, frame));
+                    *frozen = Some((text, frame));
+                }
+            }
+            None => {
+                display.frozen_countdown(&spinner_frame(&text, 0));
+                self.frozen = Some(spin(display, text));
+            }
+        }
+    }
+
+    // Stop the spinner, with nothing drawn by it from here on
+    fn stop_spinner(&mut self) {
+        if let Some(frozen) = self.frozen.take()
+            && let Ok(mut frozen) = frozen.lock()
+        {
+            *frozen = None;
+        }
+    }
+
+    // Bring the time focused today up to date with the running session,
+    // starting again from nothing once the date changes
+    fn count_today(&mut self) {
+        let (kind, _, length) = self.session;
+        let mut focused = self.focused_before;
+        if kind == TimerState::Work {
+            focused += length - self.remaining;
+        }
+        self.countdown
+            .today
+            .update(Local::now().date_naive(), focused);
+    }
+
+    // `text` in one of the palette's colors, unless colors are turned off
+    fn paint(&self, color: impl Fn(&Palette) -> Color, text: &str) -> String {
+        match &self.colors {
+            Some(colors) => color(colors).paint(text),
+            None => text.to_string(),
+        }
+    }
+
+    pub fn on_event(&mut self, event: &TimerEvent) {
+        if !matches!(
+            event,
+            TimerEvent::Paused { .. } | TimerEvent::PausedTick { .. }
+        ) {
+            self.stop_spinner();
+        }
+        let display = self.display;
+        let (kind, number, _) = self.session;
+        match *event {
+            TimerEvent::SessionStarted {
+                kind,
+                number,
+                duration,
+                counts,
+                resumed,
+            } => {
+                self.session = (kind, number, duration);
+                self.remaining = duration;
+                self.focused_before = counts.focused;
+                self.task = self.work_task(kind);
+                let ends_at = end_time(duration);
+                let header = self
+                    .messages
+                    .start(kind, number, duration, ends_at)
+                    .unwrap_or_else(|| {
+                        format!(
+                            "--- {}{} {}{} (ends at {}) ---",
+                            kind.label(number),
+                            if self.strict && kind == TimerState::Work {
+                                " [STRICT]"
+                            } else {
+                                ""
+                            },
+                            if resumed { "Resumed" } else { "Started" },
+                            self.task
+                                .as_ref()
+                                .map_or(String::new(), |task| format!(": {}", task)),
+                            self.clock.format(ends_at)
+                        )
+                    });
+                if !header.is_empty() {
+                    let header = self.icon(|icons| icons.session(kind), header);
+                    say!(
+                        display,
+                        "\n{}",
+                        self.paint(|colors| colors.session(kind), &header)
+                    );
+                }
+                if !self.once {
+                    say!(display, "{}", self.overview(kind, number, counts));
+                }
+                // Strict mode leaves a pomodoro nothing but running its course
+                // or being abandoned
+                let hint = if self.strict && kind == TimerState::Work {
+                    self.keys.hint(&[Action::Stop, Action::Help, Action::Quit])
+                } else {
+                    self.keys
+                        .hint(&[Action::Pause, Action::Skip, Action::Help, Action::Quit])
+                };
+                say!(display, "{}", hint);
+            }
+            TimerEvent::Restarted {
+                kind,
+                number,
+                duration,
+            } => {
+                self.remaining = duration;
+                let header = format!(
+                    "--- {} Restarted (ends at {}) ---",
+                    kind.label(number),
+                    self.clock.format(end_time(duration))
+                );
+                let header = self.icon(|icons| icons.session(kind), header);
+                say!(
+                    display,
+                    "\n{}",
+                    self.paint(|colors| colors.session(kind), &header)
+                );
+            }
+            TimerEvent::SkipUndone {
+                kind,
+                number,
+                duration,
+                remaining,
+                counts,
+            } => {
+                self.session = (kind, number, duration);
+                self.remaining = remaining;
+                self.focused_before = counts.focused;
+                self.task = self.work_task(kind);
+                let header = format!(
+                    "--- {} Back, Skip Undone (ends at {}) ---",
+                    kind.label(number),
+                    self.clock.format(end_time(remaining))
+                );
+                let header = self.icon(|icons| icons.session(kind), header);
+                say!(
+                    display,
+                    "\n{}",
+                    self.paint(|colors| colors.session(kind), &header)
+                );
+            }
+            // Drawn from the next tick, after the timer loop reports it
+            TimerEvent::Extended { added, duration } => {
+                self.session.2 = duration;
+                self.remaining += added;
+            }
+            TimerEvent::Nudged {
+                duration,
+                remaining,
+            } => {
+                self.session.2 = duration;
+                self.remaining = remaining;
+            }
+            TimerEvent::Upcoming { next } => self.next = Some(next_preview(kind, next)),
+            TimerEvent::Tick { remaining } => {
+                self.remaining = remaining;
+                self.count_today();
+                if self.countdown.shown {
+                    let text = self.countdown_text(remaining, self.countdown_line(remaining));
+                    display.countdown(&self.paint(|colors| colors.session(kind), &text));
+                }
+            }
+            TimerEvent::Paused { away: None } => {
+                say!(
+                    display,
+                    "{} {}",
+                    self.paint(|colors| colors.paused, "Timer Paused."),
+                    self.keys
+                        .hint(&[Action::Resume, Action::Skip, Action::Quit])
+                );
+                self.freeze_countdown(Duration::ZERO);
+            }
+            TimerEvent::Paused { away: Some(away) } => {
+                say!(
+                    display,
+                    "Timer was suspended for {}. Press Enter to count that time, \
+                     '{}' to resume without it, or '{}' to abandon {}.",
+                    format_duration(away),
+                    self.keys.key(Action::Resume),
+                    self.keys.key(Action::Skip),
+                    kind.label(number)
+                );
+                self.freeze_countdown(Duration::ZERO);
+            }
+            TimerEvent::PausedTick { paused, remaining } => {
+                self.remaining = remaining;
+                self.count_today();
+                self.freeze_countdown(paused);
+            }
+            TimerEvent::Resumed { counted: None } => say!(display, "Timer Resumed."),
+            TimerEvent::Resumed {
+                counted: Some(counted),
+            } => say!(
+                display,
+                "Timer Resumed, counting the {} away.",
+                format_duration(counted)
+            ),
+            TimerEvent::Suspended { away, policy } => {
+                let handling = match policy {
+                    SleepPolicy::Count => "counting it".to_string(),
+                    SleepPolicy::Abandon => format!("abandoning {}", kind.label(number)),
+                    SleepPolicy::Discard | SleepPolicy::Ask => "not counting it".to_string(),
+                };
+                say!(
+                    display,
+                    "Timer was suspended for {}, {}.",
+                    format_duration(away),
+                    handling
+                )
+            }
+            TimerEvent::SessionCompleted { kind, number, .. } => {
+                let header = self
+                    .messages
+                    .end(kind, number, self.session.2, end_time(Duration::ZERO))
+                    .unwrap_or_else(|| format!("--- {} Finished! ---", kind.label(number)));
+                if !header.is_empty() {
+                    let header = self.icon(
+                        |icons| match kind {
+                            TimerState::Work => icons.completed,
+                            kind => icons.session(kind),
+                        },
+                        header,
+                    );
+                    say!(
+                        display,
+                        "\n{}",
+                        self.paint(|colors| colors.session(kind), &header)
+                    )
+                }
+            }
+            TimerEvent::SessionSkipped { .. } => say!(display, "Skipping current session."),
+            TimerEvent::SessionAbandoned { .. } => say!(display, "Abandoning current session."),
+            TimerEvent::Interrupted { kind, number } => say!(
+                display,
+                "Cutting {} short for a long break.",
+                kind.label(number)
+            ),
+            TimerEvent::Snoozed { next, remaining } => {
+                say!(
+                    display,
+                    "Snoozing, {} starts in {} (at {}). Press Enter to start it now.",
+                    next.label(),
+                    format_duration(remaining),
+                    self.clock.format(end_time(remaining))
+                );
+                self.snoozed = Some(next);
+                self.draw_snooze(remaining);
+            }
+            TimerEvent::SnoozeTick { remaining } => self.draw_snooze(remaining),
+            // Only heard, the prompt to start the next session is still there
+            TimerEvent::SessionEnded { .. }
+            | TimerEvent::Reminder { .. }
+            | TimerEvent::Acknowledged => {}
+            // Reported by the timer loop, and shown from the next header
+            TimerEvent::StrictToggled { strict } => self.strict = strict,
+            // The totals so far and what can be done from here
+            TimerEvent::Stopped { counts } => {
+                say!(display, "{}", summary(&counts, self.cycles, self.icons));
+                say!(
+                    display,
+                    "Timer stopped. Press Enter to start work, '{}' to take a break or '{}' to \
+                     quit.\nThe lengths can be changed first with '{p}work 50', '{p}break 10' or \
+                     '{p}long_break 20'.",
+                    self.keys.key(Action::TakeBreak),
+                    self.keys.key(Action::Quit),
+                    p = self.command_prefix
+                );
+            }
+            // The summary depends on why the run ended, main() prints it
+            TimerEvent::Finished => {}
+        }
+    }
+}
+
+// `number` of something, e.g. "1 pomodoro" or "2 pomodoros"
+pub fn count(number: u64, one: &str, many: &str) -> String {
+    format!("{} {}", number, if number == 1 { one } else { many })
+}
+
+// "--- Completed 3 of 4 pomodoros (2 breaks taken, 1 session skipped, 50 min
+// focused) ---", the totals for the run, printed when it stops or ends
+pub fn summary(counts: &Counts, cycles: Option<u64>, icons: Option<Icons>) -> String {
+    let completed = match cycles {
+        Some(cycles) => format!(
+            "{} of {}",
+            counts.completed_pomodoros,
+            count(cycles, "pomodoro", "pomodoros")
+        ),
+        None => count(counts.completed_pomodoros, "pomodoro", "pomodoros"),
+    };
+    // Time spent waiting to start sessions, thrown away by restarting
+    // them or snoozing, is only worth a mention when there was some
+    let mention = |time: Duration, what: &str| match time.as_secs() / 60 {
+        0 => String::new(),
+        minutes => format!(", {} min {}", minutes, what),
+    };
+    let idle = mention(counts.idle, "idle")
+        + &mention(counts.interrupted, "interrupted")
+        + &mention(counts.snoozed, "snoozed");
+    // Likewise for sessions that were abandoned or restarted
+    let ended = |sessions: u64, how: &str| match sessions {
+        0 => String::new(),
+        sessions => format!(", {} {}", sessions, how),
+    };
+    let ended = ended(counts.abandoned_sessions, "abandoned")
+        + &ended(counts.restarted_sessions, "restarted");
+    let icon = icons.map_or(String::new(), |icons| format!("{} ", icons.completed));
+    format!(
+        "\n{}--- Completed {} ({} taken, {} skipped{}, {} min focused{}) ---",
+        icon,
+        completed,
+        count(counts.breaks_taken, "break", "breaks"),
+        count(counts.skipped_sessions, "session", "sessions"),
+        ended,
+        counts.focused.as_secs() / 60,
+        idle
+    )
+}
+
+// Redraw the paused countdown `text` on a thread of its own with the next
+// frame of the spinner a few times a second, until it's set to None
+fn spin(display: Display, text: String) -> Frozen {
+    let frozen = Arc::new(Mutex::new(Some((text, 0))));
+    let shared = frozen.clone();
+    thread::spawn(move || {
+        loop {
+            thread::sleep(SPINNER_INTERVAL);
+            let Ok(mut frozen) = shared.lock() else {
+                return;
+            };
+            let Some((text, frame)) = frozen.as_mut() else {
+                return;
+            };
+            *frame = (*frame + 1) % SPINNER_FRAMES.len();
+            display.frozen_countdown(&spinner_frame(text, *frame));
+        }
+    });
+    frozen
+}
+
+fn spinner_frame(text: &str, frame: usize) -> String {
+    text.replace(SPINNER_SLOT, SPINNER_FRAMES[frame])
+}
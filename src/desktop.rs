@@ -0,0 +1,249 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+    time::Duration,
+};
+
+use chrono::Local;
+use notify_rust::{Notification, error::Result};
+use pomodoro_timer::{
+    config::Urgency,
+    duration::format_duration,
+    format::Messages,
+    timer::{Session, TimerCommand, TimerEvent, TimerState},
+};
+
+use crate::display::Display;
+
+const APP_NAME: &str = "pomodoro_timer";
+
+// What the "+5 min" button adds to a finished work session
+const OVERTIME: Duration = Duration::from_secs(5 * 60);
+
+// The buttons stay for this long, after which the break goes ahead as it
+// would have without them
+const ACTION_TIMEOUT: Duration = Duration::from_secs(60);
+
+const START_ACTION: &str = "start";
+const OVERTIME_ACTION: &str = "overtime";
+
+// The timer listener that shows a desktop notification when a session
+// finishes, for when the terminal is out of sight. Turned off with
+// --no-notify.
+pub struct DesktopNotifier {
+    // Notifications are shown from their own thread, since reaching the
+    // notification daemon can take a while
+    sender: mpsc::Sender<Message>,
+    // Breaks wait for Enter, so the notification can start them
+    break_waits: bool,
+    // The end messages replace the body
+    messages: Messages,
+    // The length of the running session
+    length: Duration,
+    // Set while the sound is muted, which the notification's own sound
+    // follows
+    muted: Arc<AtomicBool>,
+}
+
+struct Message {
+    summary: String,
+    body: String,
+    silent: bool,
+    // Buttons along with it, each with the command it sends to the timer
+    actions: Vec<(&'static str, &'static str, TimerCommand)>,
+}
+
+impl DesktopNotifier {
+    // Clicked buttons send their commands through `commands`, like keys do
+    pub fn new(
+        display: Display,
+        urgency: Urgency,
+        break_waits: bool,
+        commands: mpsc::Sender<TimerCommand>,
+        muted: Arc<AtomicBool>,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel::<Message>();
+        thread::spawn(move || {
+            // Asked once, and only when there's something to show
+            let mut supports_actions = None;
+            // Reported once, a missing daemon would fail every time
+            let mut warned = false;
+            for mut message in receiver {
+                if !message.actions.is_empty()
+                    && !*supports_actions.get_or_insert_with(actions_supported)
+                {
+                    message.actions.clear();
+                }
+                if let Err(err) = show(message, urgency, &commands)
+                    && !warned
+                {
+                    warned = true;
+                    say!(
+                        display,
+                        "warning: could not show a desktop notification: {}",
+                        err
+                    );
+                }
+            }
+        });
+        DesktopNotifier {
+            sender,
+            break_waits,
+            messages: Messages::default(),
+            length: Duration::ZERO,
+            muted,
+        }
+    }
+
+    pub fn with_messages(self, messages: Messages) -> Self {
+        DesktopNotifier { messages, ..self }
+    }
+
+    pub fn on_event(&mut self, event: &TimerEvent) {
+        if let TimerEvent::SessionStarted { duration, .. }
+        | TimerEvent::SkipUndone { duration, .. }
+        | TimerEvent::Nudged { duration, .. }
+        | TimerEvent::Extended { duration, .. } = *event
+        {
+            self.length = duration;
+        }
+        if let TimerEvent::SessionCompleted { kind, number, next } = *event {
+            let (summary, mut body) = text(kind, number, next);
+            match self
+                .messages
+                .end(kind, number, self.length, Local::now().time())
+            {
+                // An empty message leaves out the notification as well
+                Some(message) if message.is_empty() => return,
+                Some(message) => body = message,
+                None => {}
+            }
+            let mut actions = Vec::new();
+            if kind == TimerState::Work && next.is_some_and(|next| next.state != TimerState::Work) {
+                if self.break_waits {
+                    actions.push((START_ACTION, "Start break", TimerCommand::Start));
+                }
+                actions.push((OVERTIME_ACTION, "+5 min", TimerCommand::Overtime(OVERTIME)));
+            }
+            let _ = self.sender.send(Message {
+                summary,
+                body,
+                silent: self.muted.load(Ordering::Relaxed),
+                actions,
+            });
+        }
+    }
+}
+
+// "Work session finished" and "Work Session 3 is done, take a 5 minute
+// break."
+fn text(kind: TimerState, number: u64, next: Option<Session>) -> (String, String) {
+    let summary = match kind {
+        TimerState::Work => "Work session finished".to_string(),
+        kind => format!("{} over", kind.name()),
+    };
+    let done = format!("{} is done", kind.label(number));
+    let body = match next {
+        None => format!("{}, that was the last one.", done),
+        Some(next) if next.state == TimerState::Work => {
+            format!("{}, back to work for {}.", done, length(next.length, true))
+        }
+        Some(next) => format!(
+            "{}, take a {} {}.",
+            done,
+            length(next.length, false),
+            next.state.name().to_lowercase()
+        ),
+    };
+    (summary, body)
+}
+
+// "25 minutes", or "5 minute" before a noun, and "1m30s" for lengths that
+// aren't whole minutes
+fn length(duration: Duration, plural: bool) -> String {
+    let seconds = duration.as_secs();
+    match seconds / 60 {
+        _ if !seconds.is_multiple_of(60) => format_duration(duration),
+        1 => "1 minute".to_string(),
+        minutes if plural => format!("{} minutes", minutes),
+        minutes => format!("{} minute", minutes),
+    }
+}
+
+fn notification(message: &Message, urgency: Urgency) -> Notification {
+    let mut notification = Notification::new();
+    notification
+        .appname(APP_NAME)
+        .summary(&message.summary)
+        .body(&message.body);
+    // The daemon's own sound is left out along with ours
+    #[cfg(all(unix, not(target_os = "macos")))]
+    if message.silent {
+        notification.hint(notify_rust::Hint::SuppressSound(true));
+    }
+    // Only the freedesktop and Windows backends know about urgency
+    #[cfg(any(target_os = "windows", all(unix, not(target_os = "macos"))))]
+    notification.urgency(match urgency {
+        Urgency::Low => notify_rust::Urgency::Low,
+        Urgency::Normal => notify_rust::Urgency::Normal,
+        Urgency::Critical => notify_rust::Urgency::Critical,
+    });
+    #[cfg(not(any(target_os = "windows", all(unix, not(target_os = "macos")))))]
+    let _ = urgency;
+    notification
+}
+
+// Whether the notification daemon shows buttons
+#[cfg(all(unix, not(target_os = "macos")))]
+fn actions_supported() -> bool {
+    notify_rust::get_capabilities()
+        .is_ok_and(|capabilities| capabilities.iter().any(|name| name == "actions"))
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn actions_supported() -> bool {
+    false
+}
+
+// Show the notification, and wait on a thread of its own for one of its
+// buttons to be clicked. A click after ACTION_TIMEOUT is ignored even if the
+// daemon keeps the notification around.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn show(message: Message, urgency: Urgency, commands: &mpsc::Sender<TimerCommand>) -> Result<()> {
+    let mut notification = notification(&message, urgency);
+    if message.actions.is_empty() {
+        notification.show()?;
+        return Ok(());
+    }
+    for (name, label, _) in &message.actions {
+        notification.action(name, label);
+    }
+    let handle = notification.timeout(ACTION_TIMEOUT).show()?;
+    let shown = std::time::Instant::now();
+    let commands = commands.clone();
+    thread::spawn(move || {
+        handle.wait_for_action(|clicked| {
+            let command = message
+                .actions
+                .iter()
+                .find(|(name, _, _)| *name == clicked)
+                .map(|(_, _, command)| *command);
+            if let Some(command) = command
+                && shown.elapsed() < ACTION_TIMEOUT
+            {
+                let _ = commands.send(command);
+            }
+        })
+    });
+    Ok(())
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn show(message: Message, urgency: Urgency, _: &mpsc::Sender<TimerCommand>) -> Result<()> {
+    notification(&message, urgency).show()?;
+    Ok(())
+}
@@ -0,0 +1,180 @@
+use std::{
+    fmt,
+    io::{Write, stdout},
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use terminal_size::{Height, Width, terminal_size};
+
+// Clear from the cursor to the end of the line
+const CLEAR_LINE: &str = "\r\x1b[K";
+
+// Clear from the start of the line to the end of the screen
+const CLEAR_BELOW: &str = "\r\x1b[J";
+
+// What the countdown last drawn takes up on screen, shared by every copy of
+// the Display
+static DRAWN: Mutex<Drawn> = Mutex::new(Drawn {
+    widths: Vec::new(),
+    entered: 0,
+});
+
+struct Drawn {
+    // The visible width of each of its lines
+    widths: Vec<usize>,
+    // Lines of input echoed below it since, only counted with --big
+    entered: usize,
+}
+
+impl Drawn {
+    // Rows above the cursor that hold the countdown, with the terminal
+    // `columns` wide. After the terminal is narrowed its lines may have
+    // been wrapped onto several rows each.
+    fn rows_above(&self, columns: usize) -> usize {
+        let rows: usize = self
+            .widths
+            .iter()
+            .map(|width| width.div_ceil(columns).max(1))
+            .sum();
+        rows.saturating_sub(1) + self.entered
+    }
+}
+
+// Set while a note is being typed, so the countdown doesn't draw over it
+static HELD: AtomicBool = AtomicBool::new(false);
+
+// Faint text, used for the countdown while it's paused
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+// Where the timer's human-readable output goes. While a session runs on a
+// terminal the countdown is redrawn in place, so every message first clears
+// it rather than leaving pieces of it behind.
+#[derive(Clone, Copy)]
+pub struct Display {
+    // The countdown is being redrawn, which only happens on a terminal,
+    // so escape codes are never written anywhere else
+    live: bool,
+    // With --json or --status-line stdout is reserved for their output
+    json: bool,
+    // With --tui the screen belongs to the full-screen view
+    hidden: bool,
+}
+
+impl Display {
+    pub fn new(live: bool, json: bool) -> Self {
+        Display {
+            live,
+            json,
+            hidden: false,
+        }
+    }
+
+    // A display that prints nothing at all
+    pub fn hidden() -> Self {
+        Display {
+            live: false,
+            json: false,
+            hidden: true,
+        }
+    }
+
+    // Redraw the countdown in place, which may take up several lines
+    pub fn countdown(&self, text: &str) {
+        if self.live && !HELD.load(Ordering::SeqCst) {
+            print!("{}{}", clear_countdown(text), text);
+            let _ = stdout().flush();
+        }
+    }
+
+    // Redraw the countdown dimmed, to show it isn't moving
+    pub fn frozen_countdown(&self, text: &str) {
+        if self.live && !HELD.load(Ordering::SeqCst) {
+            print!("{}{}{}{}", clear_countdown(text), DIM, text, RESET);
+            let _ = stdout().flush();
+        }
+    }
+
+    // The terminal echoed a line of input, moving the cursor down below a
+    // --big countdown, which now has one more row above the cursor. A
+    // single-line countdown is left in place along with what was typed.
+    pub fn line_entered(&self) {
+        if self.live
+            && let Ok(mut drawn) = DRAWN.lock()
+            && drawn.widths.len() > 1
+        {
+            drawn.entered += 1;
+        }
+    }
+
+    // Stop redrawing the countdown while something is typed on the line
+    // below it, and start again from the next tick once `held` is false
+    pub fn hold_countdown(&self, held: bool) {
+        HELD.store(held, Ordering::SeqCst);
+    }
+
+    // Print a message on its own line, use the say! macro rather than
+    // calling this directly
+    pub fn message(&self, args: fmt::Arguments) {
+        if self.json {
+            eprintln!("{}", args);
+        } else if self.live {
+            println!("{}{}", clear_countdown(""), args);
+        } else if !self.hidden {
+            println!("{}", args);
+        }
+    }
+}
+
+// Move back to the start of the countdown on screen and clear all of it,
+// making way for `text`. Going from a taller countdown to a shorter one, or
+// a wider one to a narrower one, leaves nothing behind.
+fn clear_countdown(text: &str) -> String {
+    let Ok(mut drawn) = DRAWN.lock() else {
+        return CLEAR_LINE.to_string();
+    };
+    // Measured now rather than when it was drawn, in case the terminal has
+    // been resized since
+    let columns = terminal_width().unwrap_or(usize::MAX).max(1);
+    let above = drawn.rows_above(columns);
+    *drawn = Drawn {
+        widths: text.lines().map(visible_width).collect(),
+        entered: 0,
+    };
+    match above {
+        0 => CLEAR_LINE.to_string(),
+        above => format!("\x1b[{}A{}", above, CLEAR_BELOW),
+    }
+}
+
+// The columns `line` takes up, leaving out the escape codes that color it
+fn visible_width(line: &str) -> usize {
+    let mut width = 0;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // "\x1b[" and parameters, up to the letter that ends it
+            chars.next();
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
+
+// The width of the terminal stdout is connected to, if it is one
+pub fn terminal_width() -> Option<usize> {
+    terminal_size().map(|(Width(width), _)| usize::from(width))
+}
+
+pub fn terminal_height() -> Option<usize> {
+    terminal_size().map(|(_, Height(height))| usize::from(height))
+}
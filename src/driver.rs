@@ -0,0 +1,377 @@
+// The loop that runs a PomodoroTimer: it measures the time that passes on a
+// Clock and feeds it to the timer along with the commands that arrive,
+// leaving whatever is shown or saved along the way to a Frontend
+
+use std::{
+    sync::mpsc::{Receiver, RecvTimeoutError},
+    time::Instant,
+};
+
+use crate::{
+    clock::Clock,
+    timer::{Phase, PomodoroTimer, Reply, SUSPEND_TOLERANCE, TimerCommand},
+};
+
+// What run() tells the frontend about as it goes. Nothing is needed for a
+// timer that's only listened to.
+pub trait Frontend {
+    // A session is ready to begin. Holding it back leaves it waiting for
+    // the next command, e.g. while asking what the session is for.
+    fn hold(&mut self, _timer: &PomodoroTimer) -> bool {
+        false
+    }
+
+    // `command` arrived while the timer was in `phase`, and did `reply`
+    fn replied(
+        &mut self,
+        _timer: &PomodoroTimer,
+        _command: TimerCommand,
+        _phase: Phase,
+        _reply: Reply,
+    ) {
+    }
+
+    // Once every time round the loop, with the phase the timer was in when
+    // the loop came round, and the time on the clock
+    fn stepped(&mut self, _timer: &PomodoroTimer, _phase: Phase, _now: Instant) {}
+}
+
+// Listening to the timer is all it needs
+impl Frontend for () {}
+
+// Feed `timer` the time that passes on `clock` and the commands that arrive
+// on `commands` until it finishes, and quit once every sender has gone
+// away. Everything happens in this one loop, which waits for whichever
+// comes first: a command, or the next tick while a session is running.
+pub fn run<C: Clock>(
+    clock: &C,
+    timer: &mut PomodoroTimer,
+    commands: &Receiver<TimerCommand>,
+    frontend: &mut impl Frontend,
+) {
+    // Time since this was last taken counts towards the running session
+    let mut last_counted = clock.now();
+
+    while timer.phase() != Phase::Finished {
+        if timer.phase() == Phase::Ready && !frontend.hold(timer) {
+            timer.begin(clock.local_time());
+            last_counted = clock.now();
+        }
+
+        // While waiting to start there is nothing to count but idle time, so
+        // just wait for the next command
+        let phase = timer.phase();
+        let running = phase == Phase::Running;
+        let wait = timer.until_next_tick();
+        let waited_from = clock.now();
+        let wall_from = clock.wall_time();
+        let command = if running {
+            clock.wait(commands, wait)
+        } else if matches!(phase, Phase::Paused | Phase::Snoozed) {
+            // Wakes up every second to show how long it's been paused, or
+            // what's left of the snooze
+            let command = clock.wait(commands, wait);
+            timer.advance(clock.now().saturating_duration_since(waited_from));
+            command
+        } else {
+            // Wakes up for the next reminder that the session is waiting
+            let command = match timer.until_reminder() {
+                Some(wait) => clock.wait(commands, wait),
+                None => commands.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            // Only adds to the time spent idle
+            timer.advance(clock.now().saturating_duration_since(waited_from));
+            command
+        };
+
+        if running {
+            // Time the process spent stopped, e.g. with Ctrl+Z, shows up on
+            // both clocks, time the computer spent asleep only on the wall
+            // clock. Neither counts towards the session unless the sleep
+            // policy says so.
+            let stopped = clock
+                .now()
+                .saturating_duration_since(waited_from)
+                .saturating_sub(wait);
+            let asleep = clock
+                .wall_time()
+                .duration_since(wall_from)
+                .unwrap_or_default()
+                .saturating_sub(wait);
+            let now = clock.now();
+            let mut counted = now.saturating_duration_since(last_counted);
+            last_counted = now;
+            if stopped > SUSPEND_TOLERANCE {
+                counted = counted.saturating_sub(stopped);
+            }
+            let ended = timer
+                .advance(counted)
+                .or_else(|| timer.away(stopped.max(asleep)));
+            // A command that came in just as the session ended is meant for
+            // the next one
+            if ended.is_some() && timer.phase() == Phase::Ready && command.is_ok() {
+                timer.begin(clock.local_time());
+                last_counted = clock.now();
+            }
+        }
+
+        let command = match command {
+            Ok(command) => Some(command),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => Some(TimerCommand::Quit),
+        };
+        if let Some(command) = command {
+            let phase = timer.phase();
+            let reply = timer.handle(command);
+            frontend.replied(timer, command, phase, reply);
+        }
+        if timer.phase() == Phase::Running && phase != Phase::Running {
+            last_counted = clock.now();
+        }
+        frontend.stepped(timer, phase, clock.now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex, mpsc},
+        thread,
+        time::Duration,
+    };
+
+    use chrono::{NaiveDate, NaiveDateTime};
+
+    use super::*;
+    use crate::{
+        clock::{MockClock, SystemClock},
+        timer::{PomodoroConfig, TimerEvent, TimerState},
+    };
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, 14)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap()
+    }
+
+    // Every event the timer sends from now on
+    fn record(timer: &mut PomodoroTimer) -> Arc<Mutex<Vec<TimerEvent>>> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        timer.subscribe(move |event| sink.lock().unwrap().push(*event));
+        events
+    }
+
+    // Pauses once the run has stepped `after` times, and quits once that's
+    // been handled, keeping the time on the clock for both
+    struct PausesLater<'a> {
+        clock: &'a MockClock,
+        sender: mpsc::Sender<TimerCommand>,
+        after: usize,
+        steps: usize,
+        sent_at: Option<Instant>,
+        handled_at: Option<Instant>,
+    }
+
+    impl Frontend for PausesLater<'_> {
+        fn replied(&mut self, _: &PomodoroTimer, command: TimerCommand, _: Phase, _: Reply) {
+            if command == TimerCommand::Pause {
+                self.handled_at = Some(self.clock.now());
+                self.sender.send(TimerCommand::Quit).unwrap();
+            }
+        }
+
+        fn stepped(&mut self, _: &PomodoroTimer, _: Phase, now: Instant) {
+            self.steps += 1;
+            if self.steps == self.after {
+                self.sender.send(TimerCommand::Pause).unwrap();
+                self.sent_at = Some(now);
+            }
+        }
+    }
+
+    #[test]
+    fn commands_typed_together_are_handled_at_once() {
+        let mut timer = PomodoroTimer::new(PomodoroConfig::builder().build().unwrap());
+        let events = record(&mut timer);
+        let clock = MockClock::new(now());
+        let start = clock.now();
+        let (sender, commands) = mpsc::channel();
+        for command in [
+            TimerCommand::Pause,
+            TimerCommand::Resume,
+            TimerCommand::Pause,
+            TimerCommand::Quit,
+        ] {
+            sender.send(command).unwrap();
+        }
+
+        run(&clock, &mut timer, &commands, &mut ());
+
+        // None of them waited for a tick
+        assert_eq!(clock.now(), start);
+        let handled: Vec<_> = events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|event| {
+                matches!(
+                    event,
+                    TimerEvent::Paused { .. } | TimerEvent::Resumed { .. } | TimerEvent::Finished
+                )
+            })
+            .copied()
+            .collect();
+        assert_eq!(
+            handled,
+            [
+                TimerEvent::Paused { away: None },
+                TimerEvent::Resumed { counted: None },
+                TimerEvent::Paused { away: None },
+                TimerEvent::Finished
+            ]
+        );
+    }
+
+    #[test]
+    fn a_command_between_ticks_is_handled_when_it_arrives() {
+        let mut timer = PomodoroTimer::new(PomodoroConfig::builder().build().unwrap());
+        let clock = MockClock::new(now());
+        let start = clock.now();
+        let (sender, commands) = mpsc::channel();
+        let mut frontend = PausesLater {
+            clock: &clock,
+            sender,
+            after: 3,
+            steps: 0,
+            sent_at: None,
+            handled_at: None,
+        };
+
+        run(&clock, &mut timer, &commands, &mut frontend);
+
+        let sent_at = frontend.sent_at.unwrap();
+        assert_eq!(sent_at - start, Duration::from_secs(3));
+        assert_eq!(frontend.handled_at, Some(sent_at));
+        assert_eq!(timer.phase(), Phase::Finished);
+    }
+
+    #[test]
+    fn runs_a_whole_cycle_on_the_mock_clock() {
+        let config = PomodoroConfig::builder()
+            .work(minutes(25))
+            .break_(minutes(5))
+            .cycles(Some(2))
+            .final_break(false)
+            .build()
+            .unwrap();
+        let mut timer = PomodoroTimer::new(config);
+        let events = record(&mut timer);
+        let clock = MockClock::new(now());
+        let start = clock.now();
+        // Kept so the run isn't quit for want of anyone to send commands
+        let (_sender, commands) = mpsc::channel();
+
+        run(&clock, &mut timer, &commands, &mut ());
+
+        let events = events.lock().unwrap();
+        let started: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                TimerEvent::SessionStarted { kind, number, .. } => Some((*kind, *number)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            started,
+            [
+                (TimerState::Work, 1),
+                (TimerState::Break, 1),
+                (TimerState::Work, 2)
+            ]
+        );
+        let completed = events
+            .iter()
+            .filter(|event| matches!(event, TimerEvent::SessionCompleted { .. }))
+            .count();
+        assert_eq!(completed, 3);
+        assert_eq!(events.last(), Some(&TimerEvent::Finished));
+        assert_eq!(timer.counts().completed_pomodoros, 2);
+        assert_eq!(timer.counts().breaks_taken, 1);
+        assert_eq!(clock.now() - start, minutes(55));
+    }
+
+    // A timer left in `phase`, as the run would find it
+    fn in_phase(phase: Phase) -> PomodoroTimer {
+        let config = PomodoroConfig::builder().auto_start(false).build().unwrap();
+        let mut timer = PomodoroTimer::new(config);
+        if phase != Phase::Ready {
+            timer.begin(now());
+        }
+        match phase {
+            Phase::Paused => {
+                timer.handle(TimerCommand::Pause);
+            }
+            Phase::Waiting | Phase::Snoozed => {
+                timer.advance(minutes(25));
+                if phase == Phase::Snoozed {
+                    timer.handle(TimerCommand::Snooze(minutes(5)));
+                }
+            }
+            _ => {}
+        }
+        assert_eq!(timer.phase(), phase);
+        timer
+    }
+
+    // Whether a run on the real clock, from `phase`, is over soon after it's
+    // sent a Quit, or after its commands stop coming for good
+    fn stops_soon(phase: Phase, quit: bool) -> bool {
+        let mut timer = in_phase(phase);
+        let (sender, commands) = mpsc::channel();
+        let (done, stopped) = mpsc::channel();
+        thread::spawn(move || {
+            run(&SystemClock, &mut timer, &commands, &mut ());
+            done.send(timer.phase()).unwrap();
+        });
+        // Long enough for the run to be waiting on the channel
+        thread::sleep(Duration::from_millis(50));
+        if quit {
+            sender.send(TimerCommand::Quit).unwrap();
+        } else {
+            drop(sender);
+        }
+        stopped.recv_timeout(Duration::from_secs(1)) == Ok(Phase::Finished)
+    }
+
+    #[test]
+    fn quitting_stops_the_run_in_every_phase() {
+        for phase in [
+            Phase::Ready,
+            Phase::Running,
+            Phase::Paused,
+            Phase::Waiting,
+            Phase::Snoozed,
+        ] {
+            assert!(stops_soon(phase, true), "{phase:?}");
+        }
+    }
+
+    #[test]
+    fn losing_every_sender_stops_the_run_in_every_phase() {
+        for phase in [
+            Phase::Ready,
+            Phase::Running,
+            Phase::Paused,
+            Phase::Waiting,
+            Phase::Snoozed,
+        ] {
+            assert!(stops_soon(phase, false), "{phase:?}");
+        }
+    }
+}
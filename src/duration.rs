@@ -0,0 +1,310 @@
+use std::time::Duration;
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, TimeDelta};
+
+use crate::error::PomodoroError;
+
+// Parse a session length such as "25m", "90s", "1h", "1h30m" or "2.5m".
+// A bare number is a number of minutes, matching the original prompt, and
+// may be fractional ("0.5" is thirty seconds).
+pub fn parse_duration(input: &str) -> Result<Duration, PomodoroError> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err(PomodoroError::InvalidDuration(
+            "duration must not be empty".to_string(),
+        ));
+    }
+
+    let total_secs = if input.bytes().all(|byte| is_number_byte(&byte)) {
+        parse_number(input, input)? * 60.0
+    } else {
+        parse_units(input)?
+    };
+
+    // The countdown works in whole seconds, so round to the nearest one
+    let total_secs = total_secs.round();
+    if total_secs < 1.0 {
+        Err(PomodoroError::InvalidDuration(format!(
+            "duration '{}' must be at least one second",
+            input
+        )))
+    } else if total_secs > MAX_SECS {
+        Err(PomodoroError::InvalidDuration(format!(
+            "duration '{}' is too long",
+            input
+        )))
+    } else {
+        Ok(Duration::from_secs(total_secs as u64))
+    }
+}
+
+// Render a duration in the same notation parse_duration accepts
+pub fn format_duration(duration: Duration) -> String {
+    let total = duration.as_secs();
+    let (hours, minutes, seconds) = (total / 3600, total % 3600 / 60, total % 60);
+
+    let mut out = String::new();
+    if hours > 0 {
+        out.push_str(&format!("{}h", hours));
+    }
+    if minutes > 0 {
+        out.push_str(&format!("{}m", minutes));
+    }
+    if seconds > 0 || out.is_empty() {
+        out.push_str(&format!("{}s", seconds));
+    }
+    out
+}
+
+// Whole hours and minutes for a running total, e.g. "45m" or "2h05m"
+pub fn format_hours_minutes(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    match minutes / 60 {
+        0 => format!("{}m", minutes),
+        hours => format!("{}h{:02}m", hours, minutes % 60),
+    }
+}
+
+// Parse a 24-hour local time of day such as "14:00" for --until
+pub fn parse_time_of_day(input: &str) -> Result<NaiveTime, PomodoroError> {
+    NaiveTime::parse_from_str(input.trim(), "%H:%M").map_err(|_| {
+        PomodoroError::InvalidTime(format!(
+            "invalid time '{}', expected HH:MM in 24-hour time",
+            input
+        ))
+    })
+}
+
+// A day as given to `stats --from` and `--to`, YYYY-MM-DD
+pub fn parse_date(input: &str) -> Result<NaiveDate, PomodoroError> {
+    NaiveDate::parse_from_str(input.trim(), "%Y-%m-%d").map_err(|_| {
+        PomodoroError::InvalidDate(format!("invalid date '{}', expected YYYY-MM-DD", input))
+    })
+}
+
+// Time left until the next occurrence of `target`. A time that has already
+// passed today, including the current instant, means that time tomorrow,
+// so --until never produces an empty session.
+pub fn duration_until(now: NaiveDateTime, target: NaiveTime) -> Duration {
+    let mut end = now.date().and_time(target);
+    if end <= now {
+        end += TimeDelta::days(1);
+    }
+    (end - now).to_std().unwrap_or_default()
+}
+
+// Anything longer than a year is certainly a typo
+pub(crate) const MAX_SECS: f64 = 365.0 * 24.0 * 3600.0;
+
+// Sum "<number><unit>" pairs where units appear at most once, largest first
+fn parse_units(input: &str) -> Result<f64, PomodoroError> {
+    let invalid = || {
+        PomodoroError::InvalidDuration(format!(
+            "invalid duration '{}', expected something like 25m, 90s or 1h30m",
+            input
+        ))
+    };
+
+    let mut total = 0.0;
+    let mut last_unit_secs = f64::INFINITY;
+    let mut rest = input;
+
+    while !rest.is_empty() {
+        let len = rest.bytes().take_while(is_number_byte).count();
+        if len == 0 {
+            return Err(invalid());
+        }
+        let value = parse_number(&rest[..len], input)?;
+        let unit_secs = match rest[len..].chars().next() {
+            Some('h') => 3600.0,
+            Some('m') => 60.0,
+            Some('s') => 1.0,
+            _ => return Err(invalid()),
+        };
+        if unit_secs >= last_unit_secs {
+            return Err(invalid());
+        }
+        last_unit_secs = unit_secs;
+
+        total += value * unit_secs;
+        rest = &rest[len + 1..];
+    }
+
+    Ok(total)
+}
+
+fn is_number_byte(byte: &u8) -> bool {
+    byte.is_ascii_digit() || *byte == b'.'
+}
+
+// Parse one numeric component, rejecting things like "1.2.3" or "."
+fn parse_number(number: &str, input: &str) -> Result<f64, PomodoroError> {
+    number
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite())
+        .ok_or_else(|| {
+            PomodoroError::InvalidDuration(format!(
+                "invalid number '{}' in duration '{}'",
+                number, input
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(input: &str) -> u64 {
+        parse_duration(input).unwrap().as_secs()
+    }
+
+    #[test]
+    fn bare_numbers_are_minutes() {
+        assert_eq!(secs("25"), 25 * 60);
+        assert_eq!(secs(" 25 "), 25 * 60);
+    }
+
+    #[test]
+    fn units() {
+        assert_eq!(secs("90s"), 90);
+        assert_eq!(secs("25m"), 25 * 60);
+        assert_eq!(secs("1h"), 3600);
+        assert_eq!(secs("1h30m"), 90 * 60);
+        assert_eq!(secs("1h30m15s"), 90 * 60 + 15);
+    }
+
+    #[test]
+    fn fractions() {
+        assert_eq!(secs("2.5m"), 150);
+        assert_eq!(secs("2.5"), 150);
+        assert_eq!(secs("0.5"), 30);
+        assert_eq!(secs("1.5h"), 90 * 60);
+    }
+
+    #[test]
+    fn rejects_empty_input() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("   ").is_err());
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(parse_duration("1.2.3").is_err());
+        assert!(parse_duration(".").is_err());
+        assert!(parse_duration("abc").is_err());
+        assert!(parse_duration("25x").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("-5").is_err());
+    }
+
+    #[test]
+    fn rejects_zero() {
+        assert!(parse_duration("0s").is_err());
+        assert!(parse_duration("0").is_err());
+        assert!(parse_duration("0.001").is_err());
+    }
+
+    #[test]
+    fn rejects_more_than_a_year() {
+        assert_eq!(secs("8760h"), 365 * 24 * 3600);
+        assert!(parse_duration("8761h").is_err());
+        assert!(parse_duration("1e12").is_err());
+    }
+
+    #[test]
+    fn rejects_units_out_of_order() {
+        assert!(parse_duration("30m1h").is_err());
+        assert!(parse_duration("10s5m").is_err());
+        assert!(parse_duration("5m5m").is_err());
+    }
+
+    fn at(hour: u32, minute: u32, second: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, 14)
+            .unwrap()
+            .and_hms_opt(hour, minute, second)
+            .unwrap()
+    }
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn until_later_today() {
+        assert_eq!(
+            duration_until(at(13, 15, 0), time(14, 0)),
+            Duration::from_secs(45 * 60)
+        );
+        assert_eq!(
+            duration_until(at(13, 59, 30), time(14, 0)),
+            Duration::from_secs(30)
+        );
+    }
+
+    #[test]
+    fn until_midnight_from_a_minute_before() {
+        assert_eq!(
+            duration_until(at(23, 59, 0), time(0, 0)),
+            Duration::from_secs(60)
+        );
+    }
+
+    #[test]
+    fn until_now_is_tomorrow() {
+        assert_eq!(
+            duration_until(at(14, 0, 0), time(14, 0)),
+            Duration::from_secs(24 * 3600)
+        );
+    }
+
+    #[test]
+    fn until_a_time_already_passed_is_tomorrow() {
+        assert_eq!(
+            duration_until(at(14, 1, 0), time(14, 0)),
+            Duration::from_secs(24 * 3600 - 60)
+        );
+    }
+
+    #[test]
+    fn times_of_day() {
+        assert_eq!(parse_time_of_day("14:00").unwrap(), time(14, 0));
+        assert_eq!(parse_time_of_day(" 9:05 ").unwrap(), time(9, 5));
+        assert!(matches!(
+            parse_time_of_day("25:00"),
+            Err(PomodoroError::InvalidTime(_))
+        ));
+        assert!(matches!(
+            parse_time_of_day("2pm"),
+            Err(PomodoroError::InvalidTime(_))
+        ));
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(
+            parse_date("2026-10-14").unwrap(),
+            NaiveDate::from_ymd_opt(2026, 10, 14).unwrap()
+        );
+        assert!(matches!(
+            parse_date("2026-02-30"),
+            Err(PomodoroError::InvalidDate(_))
+        ));
+        assert!(matches!(
+            parse_date("14/10/2026"),
+            Err(PomodoroError::InvalidDate(_))
+        ));
+    }
+
+    #[test]
+    fn format_round_trips() {
+        for input in ["45s", "1m30s", "25m", "1h", "1h30m", "1h30m15s"] {
+            let duration = parse_duration(input).unwrap();
+            assert_eq!(format_duration(duration), input);
+            assert_eq!(
+                parse_duration(&format_duration(duration)).unwrap(),
+                duration
+            );
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::{error::Error, fmt, io, path::PathBuf};
+
+// Everything that can go wrong in the library, each with the message shown
+// to the user
+#[derive(Debug)]
+pub enum PomodoroError {
+    // The config file isn't valid TOML, or one of its values is invalid
+    ConfigParse {
+        path: PathBuf,
+        message: String,
+    },
+    // Settings that can't work together, wherever they came from
+    InvalidConfig(String),
+    UnknownProfile {
+        name: String,
+        available: Vec<String>,
+    },
+    // Neither XDG_CONFIG_HOME nor HOME is set
+    NoConfigDir,
+    Io {
+        // "read", "write" or "remove"
+        action: &'static str,
+        path: PathBuf,
+        source: io::Error,
+    },
+    // The history database can't be opened, read or written
+    #[cfg(feature = "sqlite")]
+    Database {
+        path: PathBuf,
+        source: rusqlite::Error,
+    },
+    InvalidDuration(String),
+    // A time of day or a date that can't be read
+    InvalidTime(String),
+    InvalidDate(String),
+    InvalidFormat(String),
+    // The sound command can't be used
+    SoundBackend(String),
+}
+
+impl fmt::Display for PomodoroError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PomodoroError::ConfigParse { path, message } => {
+                write!(f, "invalid config file {}: {}", path.display(), message)
+            }
+            PomodoroError::UnknownProfile { name, available } if available.is_empty() => {
+                write!(f, "unknown profile '{}', no profiles are configured", name)
+            }
+            PomodoroError::UnknownProfile { name, available } => write!(
+                f,
+                "unknown profile '{}', available profiles: {}",
+                name,
+                available.join(", ")
+            ),
+            PomodoroError::NoConfigDir => {
+                write!(f, "could not find the config directory, HOME is not set")
+            }
+            PomodoroError::Io {
+                action,
+                path,
+                source,
+            } => write!(f, "could not {} {}: {}", action, path.display(), source),
+            #[cfg(feature = "sqlite")]
+            PomodoroError::Database { path, source } => write!(
+                f,
+                "could not use the history database {}: {}",
+                path.display(),
+                source
+            ),
+            PomodoroError::InvalidConfig(message)
+            | PomodoroError::InvalidDuration(message)
+            | PomodoroError::InvalidTime(message)
+            | PomodoroError::InvalidDate(message)
+            | PomodoroError::InvalidFormat(message)
+            | PomodoroError::SoundBackend(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for PomodoroError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PomodoroError::Io { source, .. } => Some(source),
+            #[cfg(feature = "sqlite")]
+            PomodoroError::Database { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,344 @@
+// The --log-events file: every timer event appended as one JSON object per
+// line, the raw feed for putting together dashboards of your own. The field
+// names are kept as they are, new ones may be added.
+//
+// Every line has
+//   event      what happened, one of the names below
+//   timestamp  when, as RFC 3339, e.g. "2026-10-14T09:00:00+01:00"
+//
+// and, depending on the event:
+//   session_started    kind, session, duration_secs, resumed
+//   paused             away_secs when the timer paused itself after a gap,
+//                      e.g. the computer sleeping
+//   resumed            counted_secs when that gap was counted
+//   suspended          away_secs, policy ("count", "discard" or "abandon")
+//   session_ended      kind, session, outcome ("completed", "skipped",
+//                      "abandoned" or "restarted"), duration_secs,
+//                      elapsed_secs, extended_secs, nudged_secs, pauses,
+//                      paused_secs, resumed
+//   session_completed  kind, session, and next_kind, next_session and
+//                      next_duration_secs unless the run is over
+//   session_skipped    kind, session
+//   session_abandoned  kind, session
+//   restarted          kind, session, duration_secs
+//   skip_undone        kind, session, duration_secs, remaining_secs
+//   interrupted        kind, session
+//   snoozed            next_kind, next_session, next_duration_secs,
+//                      remaining_secs
+//   extended           added_secs, duration_secs
+//   nudged             duration_secs, remaining_secs
+//   upcoming           next_kind, next_session, next_duration_secs unless
+//                      the run ends with the running session
+//   reminder           repeat
+//   acknowledged
+//   strict_toggled     strict
+//   stopped            completed_pomodoros, breaks_taken, skipped_sessions,
+//                      abandoned_sessions, restarted_sessions
+//   finished
+//
+// `kind` and `next_kind` are "work", "break" or "long_break", and `session`
+// the number in the session's header, e.g. 3 for "Work Session 3". Times are
+// whole seconds, and `nudged_secs` is negative when more was taken off than
+// added. `resumed` is true for a session taken up where a timer that didn't
+// get to quit left it. The per-second ticks, while running, paused or
+// snoozed, are left out.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{Local, SecondsFormat};
+use serde::Serialize;
+
+use crate::{
+    error::PomodoroError,
+    timer::{Session, TimerEvent},
+};
+
+pub struct EventLog {
+    path: PathBuf,
+    // Written to straight away a line at a time, so a crash loses at most
+    // the event being written
+    file: File,
+}
+
+#[derive(Default, Serialize)]
+struct Entry {
+    event: &'static str,
+    timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kind: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    session: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    outcome: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    elapsed_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remaining_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    added_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extended_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nudged_secs: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pauses: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    paused_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    away_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    counted_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    policy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_kind: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_session: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_duration_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repeat: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    strict: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resumed: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    completed_pomodoros: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    breaks_taken: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    skipped_sessions: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    abandoned_sessions: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    restarted_sessions: Option<u64>,
+}
+
+impl EventLog {
+    // Open `path` to append to, creating it and its directory if need be.
+    // Whatever is in it already is left alone, though a last line that was
+    // cut off is ended first so the next event starts a line of its own.
+    pub fn open(path: &Path) -> Result<Self, PomodoroError> {
+        let error = |source| PomodoroError::Io {
+            action: "write",
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(error)?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)
+            .map_err(error)?;
+        if file.metadata().map_err(error)?.len() > 0 {
+            let mut last = [0];
+            file.seek(SeekFrom::End(-1)).map_err(error)?;
+            file.read_exact(&mut last).map_err(error)?;
+            if last != *b"\n" {
+                file.write_all(b"\n").map_err(error)?;
+            }
+        }
+        Ok(EventLog {
+            path: path.to_path_buf(),
+            file,
+        })
+    }
+
+    // The timer listener behind --log-events
+    pub fn on_event(&mut self, event: &TimerEvent) -> Result<(), PomodoroError> {
+        let Some(entry) = entry(event) else {
+            return Ok(());
+        };
+        let Ok(mut line) = serde_json::to_string(&entry) else {
+            return Ok(());
+        };
+        line.push('\n');
+        self.file
+            .write_all(line.as_bytes())
+            .map_err(|source| PomodoroError::Io {
+                action: "write",
+                path: self.path.clone(),
+                source,
+            })
+    }
+}
+
+// The line for `event`, None for the ticks
+fn entry(event: &TimerEvent) -> Option<Entry> {
+    let secs = |duration: Duration| Some(duration.as_secs());
+    let entry = match *event {
+        TimerEvent::SessionStarted {
+            kind,
+            number,
+            duration,
+            resumed,
+            ..
+        } => Entry {
+            event: "session_started",
+            kind: Some(kind.id()),
+            session: Some(number),
+            duration_secs: secs(duration),
+            resumed: Some(resumed),
+            ..Entry::default()
+        },
+        TimerEvent::Tick { .. } | TimerEvent::PausedTick { .. } | TimerEvent::SnoozeTick { .. } => {
+            return None;
+        }
+        TimerEvent::Paused { away } => Entry {
+            event: "paused",
+            away_secs: away.and_then(secs),
+            ..Entry::default()
+        },
+        TimerEvent::Resumed { counted } => Entry {
+            event: "resumed",
+            counted_secs: counted.and_then(secs),
+            ..Entry::default()
+        },
+        TimerEvent::Suspended { away, policy } => Entry {
+            event: "suspended",
+            away_secs: secs(away),
+            policy: Some(policy.to_string()),
+            ..Entry::default()
+        },
+        TimerEvent::SessionEnded { end } => Entry {
+            event: "session_ended",
+            kind: Some(end.session.state.id()),
+            session: Some(end.session.number),
+            outcome: Some(end.outcome.id()),
+            duration_secs: secs(end.session.length),
+            elapsed_secs: secs(end.elapsed),
+            extended_secs: secs(end.extended),
+            nudged_secs: Some(end.nudged),
+            pauses: Some(end.pauses),
+            paused_secs: secs(end.paused),
+            resumed: Some(end.resumed),
+            ..Entry::default()
+        },
+        TimerEvent::SessionCompleted { kind, number, next } => Entry {
+            event: "session_completed",
+            kind: Some(kind.id()),
+            session: Some(number),
+            ..with_next(next)
+        },
+        TimerEvent::SessionSkipped { kind, number } => Entry {
+            event: "session_skipped",
+            kind: Some(kind.id()),
+            session: Some(number),
+            ..Entry::default()
+        },
+        TimerEvent::SessionAbandoned { kind, number } => Entry {
+            event: "session_abandoned",
+            kind: Some(kind.id()),
+            session: Some(number),
+            ..Entry::default()
+        },
+        TimerEvent::Restarted {
+            kind,
+            number,
+            duration,
+        } => Entry {
+            event: "restarted",
+            kind: Some(kind.id()),
+            session: Some(number),
+            duration_secs: secs(duration),
+            ..Entry::default()
+        },
+        TimerEvent::SkipUndone {
+            kind,
+            number,
+            duration,
+            remaining,
+            ..
+        } => Entry {
+            event: "skip_undone",
+            kind: Some(kind.id()),
+            session: Some(number),
+            duration_secs: secs(duration),
+            remaining_secs: secs(remaining),
+            ..Entry::default()
+        },
+        TimerEvent::Interrupted { kind, number } => Entry {
+            event: "interrupted",
+            kind: Some(kind.id()),
+            session: Some(number),
+            ..Entry::default()
+        },
+        TimerEvent::Snoozed { next, remaining } => Entry {
+            event: "snoozed",
+            remaining_secs: secs(remaining),
+            ..with_next(Some(next))
+        },
+        TimerEvent::Extended { added, duration } => Entry {
+            event: "extended",
+            added_secs: secs(added),
+            duration_secs: secs(duration),
+            ..Entry::default()
+        },
+        TimerEvent::Nudged {
+            duration,
+            remaining,
+        } => Entry {
+            event: "nudged",
+            duration_secs: secs(duration),
+            remaining_secs: secs(remaining),
+            ..Entry::default()
+        },
+        TimerEvent::Upcoming { next } => Entry {
+            event: "upcoming",
+            ..with_next(next)
+        },
+        TimerEvent::Reminder { repeat } => Entry {
+            event: "reminder",
+            repeat: Some(repeat),
+            ..Entry::default()
+        },
+        TimerEvent::Acknowledged => Entry {
+            event: "acknowledged",
+            ..Entry::default()
+        },
+        TimerEvent::StrictToggled { strict } => Entry {
+            event: "strict_toggled",
+            strict: Some(strict),
+            ..Entry::default()
+        },
+        TimerEvent::Stopped { counts } => Entry {
+            event: "stopped",
+            completed_pomodoros: Some(counts.completed_pomodoros),
+            breaks_taken: Some(counts.breaks_taken),
+            skipped_sessions: Some(counts.skipped_sessions),
+            abandoned_sessions: Some(counts.abandoned_sessions),
+            restarted_sessions: Some(counts.restarted_sessions),
+            ..Entry::default()
+        },
+        TimerEvent::Finished => Entry {
+            event: "finished",
+            ..Entry::default()
+        },
+    };
+    Some(Entry {
+        timestamp: Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+        ..entry
+    })
+}
+
+fn with_next(next: Option<Session>) -> Entry {
+    match next {
+        Some(next) => Entry {
+            next_kind: Some(next.state.id()),
+            next_session: Some(next.number),
+            next_duration_secs: Some(next.length.as_secs()),
+            ..Entry::default()
+        },
+        None => Entry::default(),
+    }
+}
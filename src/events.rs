@@ -0,0 +1,379 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Local, SecondsFormat, TimeDelta};
+use serde::{Serialize, Serializer};
+
+use crate::{
+    format::format_clock,
+    timer::{Outcome, Phase, PomodoroConfig, PomodoroTimer, TimerEvent, TimerState},
+};
+
+// What the timer is doing right now, kept up to date from the timer's events
+// and written out as one line of the --json event stream for each of them
+#[derive(Default, Serialize)]
+pub struct Status {
+    pub state: &'static str,
+    pub remaining_secs: u64,
+    // The number in the session header, e.g. 3 for "Work Session 3"
+    pub session: u64,
+    pub completed_pomodoros: u64,
+    pub breaks_taken: u64,
+    pub skipped_sessions: u64,
+    pub abandoned_sessions: u64,
+    pub restarted_sessions: u64,
+    // Only known when the status is taken from the timer itself
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub details: Option<Details>,
+}
+
+// The part of a status that the events don't tell, see Status::of()
+#[derive(Serialize)]
+pub struct Details {
+    #[serde(skip)]
+    pub kind: TimerState,
+    pub phase: Phase,
+    // When the running session will end, None unless it's running
+    #[serde(serialize_with = "rfc3339")]
+    pub ends_at: Option<DateTime<Local>>,
+    pub focused_secs: u64,
+    pub paused_secs: u64,
+    // Waiting for a session to be started
+    pub idle_secs: u64,
+    // Thrown away by restarting sessions
+    pub interrupted_secs: u64,
+    // Spent snoozing before sessions
+    pub snoozed_secs: u64,
+    // What's left of the snooze, zero unless snoozed
+    #[serde(skip)]
+    pub snooze_remaining: Duration,
+}
+
+fn rfc3339<S: Serializer>(
+    time: &Option<DateTime<Local>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match time {
+        Some(time) => serializer.serialize_str(&time.to_rfc3339_opts(SecondsFormat::Secs, false)),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[derive(Serialize)]
+struct Event<'a> {
+    event: &'a str,
+    #[serde(flatten)]
+    status: &'a Status,
+    timestamp: String,
+}
+
+impl Status {
+    // Everything about `timer` right now, with the totals for the whole run
+    pub fn of(timer: &PomodoroTimer) -> Self {
+        let session = timer.session();
+        let counts = timer.counts();
+        let phase = timer.phase();
+        // The running work session counts too, not just the finished ones
+        let mut focused = counts.focused;
+        if session.state == TimerState::Work && matches!(phase, Phase::Running | Phase::Paused) {
+            focused += timer.elapsed();
+        }
+        let ends_at = (phase == Phase::Running)
+            .then(|| TimeDelta::from_std(timer.remaining()).ok())
+            .flatten()
+            .and_then(|remaining| Local::now().checked_add_signed(remaining));
+        Status {
+            state: session.state.id(),
+            remaining_secs: timer.remaining().as_secs(),
+            session: session.number,
+            completed_pomodoros: counts.completed_pomodoros,
+            breaks_taken: counts.breaks_taken,
+            skipped_sessions: counts.skipped_sessions,
+            abandoned_sessions: counts.abandoned_sessions,
+            restarted_sessions: counts.restarted_sessions,
+            details: Some(Details {
+                kind: session.state,
+                phase,
+                ends_at,
+                focused_secs: focused.as_secs(),
+                paused_secs: counts.paused.as_secs(),
+                idle_secs: counts.idle.as_secs(),
+                interrupted_secs: counts.interrupted.as_secs(),
+                snoozed_secs: counts.snoozed.as_secs(),
+                snooze_remaining: timer.snooze_remaining(),
+            }),
+        }
+    }
+
+    // The timer listener behind --json
+    pub fn on_event(&mut self, event: &TimerEvent) {
+        let name = match *event {
+            TimerEvent::SessionStarted {
+                kind,
+                number,
+                duration,
+                ..
+            } => {
+                self.state = kind.id();
+                self.session = number;
+                self.remaining_secs = duration.as_secs();
+                "started"
+            }
+            TimerEvent::Tick { remaining } => {
+                self.remaining_secs = remaining.as_secs();
+                "tick"
+            }
+            TimerEvent::Paused { .. } => "paused",
+            // The stream only ticks while a session is running
+            TimerEvent::PausedTick { .. } => return,
+            TimerEvent::Resumed { .. } => "resumed",
+            // Only its effects are part of the stream
+            TimerEvent::Suspended { .. } => return,
+            // The events that follow it tell how it ended
+            TimerEvent::SessionEnded { end } => {
+                match end.outcome {
+                    Outcome::Abandoned => self.abandoned_sessions += 1,
+                    Outcome::Restarted => self.restarted_sessions += 1,
+                    Outcome::Completed | Outcome::Skipped => {}
+                }
+                return;
+            }
+            TimerEvent::SessionCompleted { kind, .. } => {
+                match kind {
+                    TimerState::Work => self.completed_pomodoros += 1,
+                    _ => self.breaks_taken += 1,
+                }
+                self.remaining_secs = 0;
+                "finished"
+            }
+            TimerEvent::SessionSkipped { .. } => {
+                self.skipped_sessions += 1;
+                "skipped"
+            }
+            // Counted as abandoned when the session ended
+            TimerEvent::SessionAbandoned { .. } => "abandoned",
+            TimerEvent::SkipUndone {
+                kind,
+                number,
+                remaining,
+                counts,
+                ..
+            } => {
+                self.state = kind.id();
+                self.session = number;
+                self.remaining_secs = remaining.as_secs();
+                self.completed_pomodoros = counts.completed_pomodoros;
+                self.breaks_taken = counts.breaks_taken;
+                self.skipped_sessions = counts.skipped_sessions;
+                self.abandoned_sessions = counts.abandoned_sessions;
+                self.restarted_sessions = counts.restarted_sessions;
+                "undone"
+            }
+            TimerEvent::Restarted { duration, .. } => {
+                self.remaining_secs = duration.as_secs();
+                "restarted"
+            }
+            TimerEvent::Interrupted { .. } => "interrupted",
+            TimerEvent::Snoozed { next, .. } => {
+                self.state = next.state.id();
+                self.session = next.number;
+                self.remaining_secs = next.length.as_secs();
+                "snoozed"
+            }
+            // As with pausing, the stream only ticks while a session runs
+            TimerEvent::SnoozeTick { .. } => return,
+            TimerEvent::Extended { added, .. } => {
+                self.remaining_secs += added.as_secs();
+                "extended"
+            }
+            TimerEvent::Nudged { remaining, .. } => {
+                self.remaining_secs = remaining.as_secs();
+                "nudged"
+            }
+            // Status::of() has it when asked
+            TimerEvent::Upcoming { .. } => return,
+            TimerEvent::Reminder { .. } => "reminder",
+            TimerEvent::Acknowledged => return,
+            // Not part of the timer's status
+            TimerEvent::StrictToggled { .. } => return,
+            TimerEvent::Stopped { .. } => "stopped",
+            TimerEvent::Finished => "quit",
+        };
+        self.emit(name);
+    }
+
+    // Print `event` as a single JSON object, e.g.
+    // {"event":"tick","state":"work","remaining_secs":1499,...}
+    pub fn emit(&self, event: &str) {
+        let event = Event {
+            event,
+            status: self,
+            timestamp: Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+        };
+        if let Ok(line) = serde_json::to_string(&event) {
+            println!("{}", line);
+        }
+    }
+}
+
+// The timer listener behind --status-line, which prints a single line such
+// as "work 12:34 3/4" for every second that passes, where 3/4 is the
+// pomodoro's place before the long break. A fourth word says when the
+// session is "paused", "waiting" to be started or "snoozed". The line never
+// holds anything but plain words, so prompts can show it as it is and awk
+// can take it apart.
+pub struct StatusLine {
+    sessions_until_long_break: u64,
+    auto_start_breaks: bool,
+    auto_start_work: bool,
+    // Only print lines that differ from the one before
+    on_change: bool,
+    state: TimerState,
+    remaining: Duration,
+    // The pomodoro the session belongs to, counted from 1 since the last
+    // long break. A break belongs to the pomodoro before it.
+    pomodoro: u64,
+    phase: Option<&'static str>,
+    last: Option<String>,
+}
+
+impl StatusLine {
+    pub fn new(plan: &PomodoroConfig, on_change: bool) -> Self {
+        StatusLine {
+            sessions_until_long_break: plan.sessions_until_long_break(),
+            auto_start_breaks: plan.auto_start_breaks(),
+            auto_start_work: plan.auto_start_work(),
+            on_change,
+            state: TimerState::Work,
+            remaining: Duration::ZERO,
+            pomodoro: 0,
+            phase: None,
+            last: None,
+        }
+    }
+
+    // "work 12:34 3/4", or "work 12:34 3/4 paused"
+    pub fn line(&self) -> String {
+        let mut line = format!(
+            "{} {} {}/{}",
+            self.state.id(),
+            format_clock(self.remaining),
+            self.pomodoro,
+            self.sessions_until_long_break
+        );
+        if let Some(phase) = self.phase {
+            line.push(' ');
+            line.push_str(phase);
+        }
+        line
+    }
+
+    pub fn on_event(&mut self, event: &TimerEvent) {
+        match *event {
+            TimerEvent::SessionStarted {
+                kind,
+                number,
+                duration,
+                counts,
+                ..
+            } => {
+                self.state = kind;
+                self.remaining = duration;
+                self.pomodoro = self.place(match kind {
+                    TimerState::Work => number,
+                    _ => counts.completed_pomodoros,
+                });
+                // The session's first tick, straight after, prints it
+                return;
+            }
+            TimerEvent::Tick { remaining } => {
+                self.remaining = remaining;
+                self.phase = None;
+            }
+            TimerEvent::Paused { .. } | TimerEvent::PausedTick { .. } => {
+                self.phase = Some("paused")
+            }
+            TimerEvent::Resumed { .. } => self.phase = None,
+            TimerEvent::SkipUndone {
+                kind,
+                number,
+                remaining,
+                counts,
+                ..
+            } => {
+                self.state = kind;
+                self.remaining = remaining;
+                self.pomodoro = self.place(match kind {
+                    TimerState::Work => number,
+                    _ => counts.completed_pomodoros,
+                });
+                self.phase = None;
+            }
+            TimerEvent::Snoozed { next, .. } => {
+                self.state = next.state;
+                self.remaining = next.length;
+                if next.state == TimerState::Work {
+                    self.pomodoro = self.place(next.number);
+                }
+                self.phase = Some("snoozed");
+            }
+            TimerEvent::SnoozeTick { .. } => {}
+            // Shown right away only when the next session won't start on
+            // its own, otherwise its first tick comes straight after
+            TimerEvent::SessionCompleted {
+                next: Some(next), ..
+            } => {
+                let waits = match next.state {
+                    TimerState::Work => !self.auto_start_work,
+                    _ => !self.auto_start_breaks,
+                };
+                if !waits {
+                    return;
+                }
+                self.state = next.state;
+                self.remaining = next.length;
+                if next.state == TimerState::Work {
+                    self.pomodoro = self.place(next.number);
+                }
+                self.phase = Some("waiting");
+            }
+            TimerEvent::Stopped { .. } => {
+                self.state = TimerState::Stopped;
+                self.remaining = Duration::ZERO;
+                self.pomodoro = 0;
+                self.phase = None;
+            }
+            _ => return,
+        }
+        let line = self.line();
+        if self.on_change && self.last.as_ref() == Some(&line) {
+            return;
+        }
+        // stdout is line buffered, so each line goes out as soon as it's
+        // printed
+        println!("{}", line);
+        self.last = Some(line);
+    }
+
+    // Work session `number`'s place before the long break, from 1
+    fn place(&self, number: u64) -> u64 {
+        (number.max(1) - 1) % self.sessions_until_long_break + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Further off than the calendar goes, which leaves the end out
+    #[test]
+    fn status_of_a_session_too_long_to_end() {
+        let config = PomodoroConfig::builder()
+            .work(Duration::from_secs(100_000_000_000_000))
+            .build()
+            .unwrap();
+        let mut timer = PomodoroTimer::new(config);
+        timer.begin(Local::now().naive_local());
+        let status = Status::of(&timer);
+        assert_eq!(status.details.unwrap().ends_at, None);
+    }
+}
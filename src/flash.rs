@@ -0,0 +1,115 @@
+use std::{
+    io::{Write, stdout},
+    sync::Mutex,
+    thread,
+    time::Duration,
+};
+
+use pomodoro_timer::{
+    color::Palette,
+    timer::{Session, TimerEvent, TimerState},
+};
+
+use crate::display::{Display, terminal_width};
+
+// Reverse video for the whole screen and back, which leaves what's on it
+// alone, so the countdown keeps being redrawn as usual
+const REVERSE_SCREEN: &str = "\x1b[?5h";
+const NORMAL_SCREEN: &str = "\x1b[?5l";
+
+// Three flashes over roughly two seconds
+const FLASHES: usize = 3;
+const FLASH_INTERVAL: Duration = Duration::from_millis(300);
+
+// Width of the banner when the terminal's can't be found out
+const DEFAULT_BANNER_WIDTH: usize = 60;
+
+// Cleared by reset(), after which a flash still in progress stops
+static FLASHING: Mutex<bool> = Mutex::new(true);
+
+// The timer listener that makes a finished session hard to miss: the screen
+// flashes and a banner is left above the next session. Turned off with
+// --no-flash.
+pub struct Flash {
+    display: Display,
+    // None when colors are turned off
+    colors: Option<Palette>,
+}
+
+impl Flash {
+    pub fn new(display: Display, colors: Option<Palette>) -> Self {
+        Flash { display, colors }
+    }
+
+    pub fn on_event(&mut self, event: &TimerEvent) {
+        if let TimerEvent::SessionCompleted { kind, next, .. } = *event {
+            self.banner(kind, next);
+            flash_screen();
+        }
+    }
+
+    // "WORK SESSION FINISHED — time for a break" in reverse video across the
+    // terminal, between two blank highlighted rows
+    fn banner(&self, kind: TimerState, next: Option<Session>) {
+        let next = next.map(|next| match next.state {
+            TimerState::Work => "back to work",
+            _ => "time for a break",
+        });
+        let name = match kind {
+            TimerState::Work => "WORK SESSION".to_string(),
+            kind => kind.name().to_uppercase(),
+        };
+        let text = match next {
+            Some(next) => format!("{} FINISHED \u{2014} {}", name, next),
+            None => format!("{} FINISHED", name),
+        };
+
+        let width = terminal_width().unwrap_or(DEFAULT_BANNER_WIDTH);
+        let padding = width.saturating_sub(text.chars().count());
+        let text = format!(
+            "{}{}{}",
+            " ".repeat(padding / 2),
+            text,
+            " ".repeat(padding - padding / 2)
+        );
+        let highlight = |row: &str| match self.colors {
+            Some(colors) => format!("\x1b[7m{}\x1b[0m", colors.session(kind).paint(row)),
+            None => format!("\x1b[7;1m{}\x1b[0m", row),
+        };
+        let blank = highlight(&" ".repeat(width));
+        say!(self.display, "{}\n{}\n{}", blank, highlight(&text), blank);
+    }
+}
+
+// Flash the screen without holding up the timer
+fn flash_screen() {
+    thread::spawn(|| {
+        for _ in 0..FLASHES {
+            for escape in [REVERSE_SCREEN, NORMAL_SCREEN] {
+                let Ok(flashing) = FLASHING.lock() else {
+                    return;
+                };
+                if !*flashing {
+                    return;
+                }
+                write(escape);
+                drop(flashing);
+                thread::sleep(FLASH_INTERVAL);
+            }
+        }
+    });
+}
+
+// Stop any flash in progress and make sure the screen isn't left reversed,
+// for when the timer exits
+pub fn reset() {
+    if let Ok(mut flashing) = FLASHING.lock() {
+        *flashing = false;
+        write(NORMAL_SCREEN);
+    }
+}
+
+fn write(escape: &str) {
+    print!("{}", escape);
+    let _ = stdout().flush();
+}
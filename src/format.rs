@@ -0,0 +1,395 @@
+use std::{fmt, time::Duration};
+
+use chrono::NaiveTime;
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::{
+    duration::format_duration,
+    error::PomodoroError,
+    timer::{Session, TimerState},
+};
+
+pub const DEFAULT_FORMAT: &str = "Time remaining: {remaining}";
+
+// What the countdown line can show, captured once per tick
+pub struct Snapshot<'a> {
+    pub state: &'a str,
+    pub remaining: Duration,
+    pub elapsed: Duration,
+    pub session: u64,
+    pub total_sessions: u64,
+    pub ends_at: NaiveTime,
+    // The session's full length
+    pub duration: Duration,
+}
+
+// How wall-clock times like the session end are shown
+#[derive(Clone, Copy, Debug, Default, Deserialize, ValueEnum)]
+pub enum ClockStyle {
+    #[default]
+    #[serde(rename = "24h")]
+    #[value(name = "24h")]
+    H24,
+    #[serde(rename = "12h")]
+    #[value(name = "12h")]
+    H12,
+}
+
+impl ClockStyle {
+    pub fn format(self, time: NaiveTime) -> String {
+        match self {
+            ClockStyle::H24 => time.format("%H:%M").to_string(),
+            ClockStyle::H12 => time.format("%-I:%M %p").to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ClockStyle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClockStyle::H24 => write!(f, "24h"),
+            ClockStyle::H12 => write!(f, "12h"),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Field {
+    State,
+    Remaining,
+    Elapsed,
+    Session,
+    TotalSessions,
+    EndsAt,
+    Duration,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Field> {
+        match name {
+            "state" => Some(Field::State),
+            "remaining" => Some(Field::Remaining),
+            "elapsed" => Some(Field::Elapsed),
+            "session" => Some(Field::Session),
+            "total_sessions" => Some(Field::TotalSessions),
+            "ends_at" => Some(Field::EndsAt),
+            "duration" => Some(Field::Duration),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Part {
+    Literal(String),
+    Field(Field),
+}
+
+// A parsed --format string such as
+// "[{state} {session}/{total_sessions}] {remaining}".
+// Braces are escaped by doubling them.
+#[derive(Clone)]
+pub struct CountdownFormat {
+    parts: Vec<Part>,
+    clock: ClockStyle,
+}
+
+impl CountdownFormat {
+    pub fn parse(format: &str) -> Result<Self, PomodoroError> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = format.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    let mut closed = false;
+                    for c in chars.by_ref() {
+                        if c == '}' {
+                            closed = true;
+                            break;
+                        }
+                        name.push(c);
+                    }
+                    if !closed {
+                        return Err(PomodoroError::InvalidFormat(
+                            "unclosed '{' in format, use '{{' for a literal brace".into(),
+                        ));
+                    }
+                    let field = Field::from_name(&name).ok_or_else(|| {
+                        PomodoroError::InvalidFormat(format!(
+                            "unknown placeholder {{{}}} in format, expected one of \
+                             {{state}}, {{remaining}}, {{elapsed}}, {{session}}, {{total_sessions}}, {{ends_at}}, \
+                             {{duration}}",
+                            name
+                        ))
+                    })?;
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Field(field));
+                }
+                '}' => {
+                    return Err(PomodoroError::InvalidFormat(
+                        "unmatched '}' in format, use '}}' for a literal brace".into(),
+                    ));
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        Ok(CountdownFormat {
+            parts,
+            clock: ClockStyle::default(),
+        })
+    }
+
+    pub fn with_clock(self, clock: ClockStyle) -> Self {
+        CountdownFormat { clock, ..self }
+    }
+
+    // An empty format, which leaves out what it would have shown
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    pub fn render(&self, snapshot: &Snapshot) -> String {
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => out.push_str(text),
+                Part::Field(Field::State) => out.push_str(snapshot.state),
+                Part::Field(Field::Remaining) => out.push_str(&format_clock(snapshot.remaining)),
+                Part::Field(Field::Elapsed) => out.push_str(&format_clock(snapshot.elapsed)),
+                Part::Field(Field::Session) => out.push_str(&snapshot.session.to_string()),
+                Part::Field(Field::TotalSessions) => {
+                    out.push_str(&snapshot.total_sessions.to_string())
+                }
+                Part::Field(Field::EndsAt) => out.push_str(&self.clock.format(snapshot.ends_at)),
+                Part::Field(Field::Duration) => out.push_str(&format_duration(snapshot.duration)),
+            }
+        }
+        out
+    }
+}
+
+// The message_* templates from the config file, shown when sessions start and
+// end instead of the built-in lines. Long breaks use the break messages.
+#[derive(Clone, Default)]
+pub struct Messages {
+    pub work_start: Option<CountdownFormat>,
+    pub work_end: Option<CountdownFormat>,
+    pub break_start: Option<CountdownFormat>,
+    pub break_end: Option<CountdownFormat>,
+    // Shown as {total_sessions}
+    pub total_sessions: u64,
+}
+
+impl Messages {
+    // The message for the start of a session ending at `ends_at`. None when
+    // there's no template, so the built-in line is used, and empty when
+    // the template is, to leave it out.
+    pub fn start(
+        &self,
+        kind: TimerState,
+        number: u64,
+        duration: Duration,
+        ends_at: NaiveTime,
+    ) -> Option<String> {
+        let format = match kind {
+            TimerState::Work => self.work_start.as_ref(),
+            _ => self.break_start.as_ref(),
+        }?;
+        Some(format.render(&self.snapshot(kind, number, duration, duration, ends_at)))
+    }
+
+    // Like start(), for the end of a session at `ends_at`
+    pub fn end(
+        &self,
+        kind: TimerState,
+        number: u64,
+        duration: Duration,
+        ends_at: NaiveTime,
+    ) -> Option<String> {
+        let format = match kind {
+            TimerState::Work => self.work_end.as_ref(),
+            _ => self.break_end.as_ref(),
+        }?;
+        Some(format.render(&self.snapshot(kind, number, duration, Duration::ZERO, ends_at)))
+    }
+
+    fn snapshot(
+        &self,
+        kind: TimerState,
+        number: u64,
+        duration: Duration,
+        remaining: Duration,
+        ends_at: NaiveTime,
+    ) -> Snapshot<'static> {
+        Snapshot {
+            state: kind.name(),
+            remaining,
+            elapsed: duration - remaining,
+            session: number,
+            total_sessions: self.total_sessions,
+            ends_at,
+            duration,
+        }
+    }
+}
+
+// What follows a session of `kind`, e.g. "next: Break (5 min)" or
+// "next: Long Break (15 min) — last pomodoro before it"
+pub fn next_preview(kind: TimerState, next: Option<Session>) -> String {
+    let Some(next) = next else {
+        return "next: nothing, the run ends here".to_string();
+    };
+    let seconds = next.length.as_secs();
+    let length = if seconds.is_multiple_of(60) {
+        format!("{} min", seconds / 60)
+    } else {
+        format_duration(next.length)
+    };
+    let mut preview = format!("next: {} ({})", next.state.name(), length);
+    if next.state == TimerState::LongBreak && kind == TimerState::Work {
+        preview.push_str(" \u{2014} last pomodoro before it");
+    }
+    preview
+}
+
+// mm:ss, with minutes growing past 59 for long sessions
+pub fn format_clock(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
+// A bar such as "[#######.......]" with `width` cells between the brackets,
+// filled in proportion to how much of `total` is `done`
+pub fn progress_bar(done: Duration, total: Duration, width: usize) -> String {
+    let filled = if total.is_zero() {
+        width
+    } else {
+        let fraction = done.as_secs_f64() / total.as_secs_f64();
+        ((fraction.clamp(0.0, 1.0) * width as f64).round() as usize).min(width)
+    };
+    format!("[{}{}]", "#".repeat(filled), ".".repeat(width - filled))
+}
+
+// Rows in the block digits drawn by big_text()
+pub const BIG_ROWS: usize = 5;
+
+fn big_glyph(c: char) -> [&'static str; BIG_ROWS] {
+    match c {
+        '0' => ["█████", "█   █", "█   █", "█   █", "█████"],
+        '1' => ["  █  ", " ██  ", "  █  ", "  █  ", " ███ "],
+        '2' => ["█████", "    █", "█████", "█    ", "█████"],
+        '3' => ["█████", "    █", "█████", "    █", "█████"],
+        '4' => ["█   █", "█   █", "█████", "    █", "    █"],
+        '5' => ["█████", "█    ", "█████", "    █", "█████"],
+        '6' => ["█████", "█    ", "█████", "█   █", "█████"],
+        '7' => ["█████", "    █", "   █ ", "  █  ", "  █  "],
+        '8' => ["█████", "█   █", "█████", "█   █", "█████"],
+        '9' => ["█████", "█   █", "█████", "    █", "█████"],
+        ':' => ["   ", " █ ", "   ", " █ ", "   "],
+        _ => ["     "; BIG_ROWS],
+    }
+}
+
+// `text`, e.g. "12:34", in block digits BIG_ROWS tall, one string per row
+pub fn big_text(text: &str) -> Vec<String> {
+    (0..BIG_ROWS)
+        .map(|row| {
+            text.chars()
+                .map(|c| big_glyph(c)[row])
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> Snapshot<'static> {
+        Snapshot {
+            state: "Work",
+            remaining: Duration::from_secs(12 * 60 + 34),
+            elapsed: Duration::from_secs(12 * 60 + 26),
+            session: 2,
+            total_sessions: 4,
+            ends_at: NaiveTime::from_hms_opt(14, 5, 0).unwrap(),
+            duration: Duration::from_secs(25 * 60),
+        }
+    }
+
+    fn render(format: &str) -> String {
+        CountdownFormat::parse(format).unwrap().render(&snapshot())
+    }
+
+    #[test]
+    fn renders_every_placeholder() {
+        assert_eq!(
+            render("[{state} {session}/{total_sessions}] {remaining}"),
+            "[Work 2/4] 12:34"
+        );
+        assert_eq!(
+            render("{elapsed} of {duration}, ends at {ends_at}"),
+            "12:26 of 25m, ends at 14:05"
+        );
+        assert_eq!(render(DEFAULT_FORMAT), "Time remaining: 12:34");
+    }
+
+    #[test]
+    fn twelve_hour_clock() {
+        let format = CountdownFormat::parse("{ends_at}")
+            .unwrap()
+            .with_clock(ClockStyle::H12);
+        assert_eq!(format.render(&snapshot()), "2:05 PM");
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        assert_eq!(render("{{"), "{");
+        assert_eq!(render("}}"), "}");
+        assert_eq!(render("{{{remaining}}}"), "{12:34}");
+        assert_eq!(render("{{state}}"), "{state}");
+    }
+
+    #[test]
+    fn text_without_placeholders() {
+        assert_eq!(render("focus"), "focus");
+        assert!(CountdownFormat::parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_unclosed_brace() {
+        assert!(CountdownFormat::parse("{remaining").is_err());
+        assert!(CountdownFormat::parse("ends {").is_err());
+    }
+
+    #[test]
+    fn rejects_unmatched_closing_brace() {
+        assert!(CountdownFormat::parse("remaining}").is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_placeholder() {
+        let err = CountdownFormat::parse("{remainder}").err().unwrap();
+        assert!(err.to_string().contains("{remainder}"));
+        assert!(CountdownFormat::parse("{}").is_err());
+    }
+}
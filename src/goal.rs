@@ -0,0 +1,157 @@
+use std::{
+    io::{Write, stdout},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use chrono::{Local, NaiveDate, NaiveTime};
+use pomodoro_timer::timer::{TimerEvent, TimerState};
+
+use crate::{console::count, display::Display};
+
+// The bell rings this many times when the goal is reached, so it doesn't
+// sound like the end of an ordinary session
+const CHEERS: usize = 3;
+const CHEER_INTERVAL: Duration = Duration::from_millis(250);
+
+// Pomodoros completed today towards --goal, including earlier runs from the
+// history file. Shared between the timer listener, which counts them, and
+// whatever reports the progress. Starts again from nothing at local
+// midnight.
+#[derive(Clone)]
+pub struct Goal {
+    inner: Arc<Mutex<Inner>>,
+    display: Display,
+    // The terminal bell cheers when the goal is reached, unless stdout
+    // isn't for humans
+    bell: bool,
+    muted: Arc<AtomicBool>,
+}
+
+struct Inner {
+    // None when there's no goal, the pomodoros are still counted
+    target: Option<u64>,
+    date: NaiveDate,
+    // From the history file, before this run
+    recorded: u64,
+    // Completed in this run
+    completed: u64,
+    // When this run reached the goal
+    reached: Option<NaiveTime>,
+    // The work session counted last, since overtime finishes it a second
+    // time. Cleared when a break starts or the timer stops.
+    counted: Option<u64>,
+}
+
+// How far along the goal is
+pub struct Progress {
+    pub target: Option<u64>,
+    pub done: u64,
+    pub reached: Option<NaiveTime>,
+}
+
+impl Goal {
+    pub fn new(
+        target: Option<u64>,
+        recorded: u64,
+        display: Display,
+        bell: bool,
+        muted: Arc<AtomicBool>,
+    ) -> Self {
+        Goal {
+            inner: Arc::new(Mutex::new(Inner {
+                target,
+                date: Local::now().date_naive(),
+                recorded,
+                completed: 0,
+                reached: None,
+                counted: None,
+            })),
+            display,
+            bell,
+            muted,
+        }
+    }
+
+    pub fn progress(&self) -> Progress {
+        let Ok(mut inner) = self.inner.lock() else {
+            return Progress {
+                target: None,
+                done: 0,
+                reached: None,
+            };
+        };
+        inner.catch_up(Local::now().date_naive());
+        Progress {
+            target: inner.target,
+            done: inner.recorded + inner.completed,
+            reached: inner.reached,
+        }
+    }
+
+    // The timer listener that counts the pomodoros and cheers the one that
+    // reaches the goal
+    pub fn on_event(&mut self, event: &TimerEvent) {
+        let Ok(mut inner) = self.inner.lock() else {
+            return;
+        };
+        match *event {
+            TimerEvent::SessionStarted { kind, .. } if kind != TimerState::Work => {
+                inner.counted = None;
+            }
+            TimerEvent::Stopped { .. } => inner.counted = None,
+            TimerEvent::SessionCompleted {
+                kind: TimerState::Work,
+                number,
+                ..
+            } if inner.counted != Some(number) => {
+                let now = Local::now();
+                inner.catch_up(now.date_naive());
+                inner.counted = Some(number);
+                inner.completed += 1;
+                let done = inner.recorded + inner.completed;
+                if inner.target == Some(done) {
+                    inner.reached = Some(now.time());
+                    drop(inner);
+                    self.cheer(done);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn cheer(&self, done: u64) {
+        say!(
+            self.display,
+            "\n*** Daily goal reached: {} today! ***",
+            count(done, "pomodoro", "pomodoros")
+        );
+        if !self.bell || self.muted.load(Ordering::Relaxed) {
+            return;
+        }
+        thread::spawn(|| {
+            for _ in 0..CHEERS {
+                print!("\x07"); // ASCII bell character
+                let _ = stdout().flush();
+                thread::sleep(CHEER_INTERVAL);
+            }
+        });
+    }
+}
+
+impl Inner {
+    // A new day starts over, without the history file's sessions, which
+    // were all from before it
+    fn catch_up(&mut self, date: NaiveDate) {
+        if date != self.date {
+            self.date = date;
+            self.recorded = 0;
+            self.completed = 0;
+            self.reached = None;
+        }
+    }
+}
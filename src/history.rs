@@ -0,0 +1,443 @@
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    env,
+    fs::{self, OpenOptions},
+    io::{BufRead, BufReader, ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, Local, NaiveDate, SecondsFormat};
+
+use crate::{config::HistoryBackend, error::PomodoroError, timer::Outcome};
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+// $XDG_DATA_HOME/pomodoro, falling back to ~/.local/share/pomodoro
+fn data_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?)
+            .join(".local")
+            .join("share"),
+    };
+    Some(base.join("pomodoro"))
+}
+
+// $XDG_DATA_HOME/pomodoro/history.csv, falling back to ~/.local/share
+pub fn history_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("history.csv"))
+}
+
+// Where `history = "sqlite"` keeps the sessions instead, next to the CSV
+// file
+pub fn database_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("history.db"))
+}
+
+// Where the running session is kept in case the timer doesn't get to quit,
+// see recovery.rs
+pub fn state_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("state.json"))
+}
+
+// A session that ended, as the history keeps it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    pub start: DateTime<Local>,
+    // None in files from before it was kept
+    pub end: Option<DateTime<Local>>,
+    // "work", "break" or "long_break", or "overtime" for the time added to
+    // a work session after it finished
+    pub kind: String,
+    // What it was meant to last, before Extend and the minute keys
+    pub planned: Duration,
+    pub focused: Duration,
+    // None in files from before outcomes were kept
+    pub outcome: Option<Outcome>,
+    pub pauses: u32,
+    pub paused: Duration,
+    // Net seconds added with AddMinute and RemoveMinute
+    pub nudged: i64,
+    // Taken up again after the timer that ran it didn't get to quit
+    pub resumed: bool,
+    pub task: Option<String>,
+    pub note: Option<String>,
+    // Each of the pauses, which only the database keeps
+    pub pause_log: Vec<Pause>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pause {
+    pub start: DateTime<Local>,
+    pub length: Duration,
+}
+
+impl Record {
+    // Time spent working, rather than on a break
+    pub fn is_work(&self) -> bool {
+        matches!(self.kind.as_str(), "work" | "overtime")
+    }
+
+    // A completed work session. Those from before sessions had an outcome
+    // all count.
+    pub fn is_pomodoro(&self) -> bool {
+        self.kind == "work"
+            && self
+                .outcome
+                .is_none_or(|outcome| outcome == Outcome::Completed)
+    }
+}
+
+// The days to look up sessions for, by the local date they started on.
+// Both ends are included, and None leaves that end open.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DateRange {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl DateRange {
+    pub fn day(date: NaiveDate) -> Self {
+        DateRange {
+            from: Some(date),
+            to: Some(date),
+        }
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.from.is_none_or(|from| date >= from) && self.to.is_none_or(|to| date <= to)
+    }
+}
+
+// Where sessions go once they end: the CSV file, or with the sqlite feature
+// a database, as the config file's `history` says
+pub trait HistoryStore: Send {
+    // A record with the same start and kind as one already kept, written
+    // again with notes typed after it ended or once its skip was undone,
+    // takes that one's place
+    fn append(&mut self, record: &Record) -> Result<(), PomodoroError>;
+
+    // The sessions that started in `range`, oldest first
+    fn records(&self, range: DateRange) -> Result<Vec<Record>, PomodoroError>;
+
+    // Where they're kept, for messages
+    fn path(&self) -> &Path;
+}
+
+// The store `backend` names, None when there's nowhere to keep one because
+// HOME isn't set
+pub fn open(backend: HistoryBackend) -> Result<Option<Box<dyn HistoryStore>>, PomodoroError> {
+    match backend {
+        HistoryBackend::Csv => {
+            Ok(history_path().map(|path| Box::new(CsvStore::new(path)) as Box<dyn HistoryStore>))
+        }
+        #[cfg(feature = "sqlite")]
+        HistoryBackend::Sqlite => match database_path() {
+            Some(path) => Ok(Some(Box::new(sqlite::SqliteStore::open(path)?))),
+            None => Ok(None),
+        },
+        #[cfg(not(feature = "sqlite"))]
+        HistoryBackend::Sqlite => Err(PomodoroError::InvalidConfig(
+            "history = \"sqlite\" needs pomodoro_timer built with the sqlite feature".to_string(),
+        )),
+    }
+}
+
+// The history file's columns. The note is free text and comes last, so the
+// commas in it can be kept.
+pub const HEADER: &str = "start,end,kind,planned_secs,focused_secs,outcome,pauses,paused_secs,nudged_secs,resumed,task,note";
+
+// The history as a CSV file, one row per session
+pub struct CsvStore {
+    path: PathBuf,
+}
+
+impl CsvStore {
+    pub fn new(path: PathBuf) -> Self {
+        CsvStore { path }
+    }
+}
+
+impl HistoryStore for CsvStore {
+    // The file and its directory are created with the header on first use,
+    // and rows follow the header already there otherwise. Each row goes out
+    // in a single append, so that two timers writing at once don't mix up
+    // each other's lines.
+    fn append(&mut self, record: &Record) -> Result<(), PomodoroError> {
+        let write = |source| PomodoroError::Io {
+            action: "write",
+            path: self.path.clone(),
+            source,
+        };
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(write)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(write)?;
+        let mut header = String::new();
+        BufReader::new(&file)
+            .read_line(&mut header)
+            .map_err(write)?;
+        let text = if header.trim().is_empty() {
+            format!("{}\n{}\n", HEADER, row(record, HEADER))
+        } else {
+            format!("{}\n", row(record, &header))
+        };
+        file.write_all(text.as_bytes()).map_err(write)
+    }
+
+    // No file means no history
+    fn records(&self, range: DateRange) -> Result<Vec<Record>, PomodoroError> {
+        match fs::read_to_string(&self.path) {
+            Ok(contents) => Ok(parse(&contents, range)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+            Err(source) => Err(PomodoroError::Io {
+                action: "read",
+                path: self.path.clone(),
+                source,
+            }),
+        }
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+// One line of the history file, with the columns `header` has. A file from
+// before some of them existed goes on without them, so its rows still line
+// up.
+fn row(record: &Record, header: &str) -> String {
+    header
+        .split(',')
+        .map(|column| match column.trim() {
+            "start" => timestamp(record.start),
+            "end" => record.end.map(timestamp).unwrap_or_default(),
+            "kind" => record.kind.clone(),
+            "planned_secs" => record.planned.as_secs().to_string(),
+            "focused_secs" => record.focused.as_secs().to_string(),
+            "outcome" => record
+                .outcome
+                .map(Outcome::id)
+                .unwrap_or_default()
+                .to_string(),
+            "pauses" => record.pauses.to_string(),
+            "paused_secs" => record.paused.as_secs().to_string(),
+            "nudged_secs" => record.nudged.to_string(),
+            "resumed" => record.resumed.to_string(),
+            // A comma in the task would be taken for a separator
+            "task" => record
+                .task
+                .as_deref()
+                .map(|task| task.replace(',', ";").replace('\n', " "))
+                .unwrap_or_default(),
+            "note" => record
+                .note
+                .as_deref()
+                .map(|note| note.replace('\n', " "))
+                .unwrap_or_default(),
+            _ => String::new(),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn timestamp(time: DateTime<Local>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, false)
+}
+
+fn parse_timestamp(text: &str) -> Option<DateTime<Local>> {
+    DateTime::parse_from_rfc3339(text)
+        .ok()
+        .map(|time| time.with_timezone(&Local))
+}
+
+// The rows of a history file that started in `range`. The columns are found
+// by name in its header, so files from before some of them existed still
+// work, and rows that can't be read are left out rather than spoiling the
+// rest.
+fn parse(contents: &str, range: DateRange) -> Vec<Record> {
+    let mut lines = contents.lines();
+    let header: Vec<&str> = lines.next().unwrap_or_default().split(',').collect();
+    let column = |name: &str| header.iter().position(|column| column.trim() == name);
+    let (Some(start), Some(kind)) = (column("start"), column("kind")) else {
+        return Vec::new();
+    };
+    let [
+        end,
+        planned,
+        focused,
+        outcome,
+        pauses,
+        paused,
+        nudged,
+        resumed,
+        task,
+        note,
+    ] = [
+        "end",
+        "planned_secs",
+        "focused_secs",
+        "outcome",
+        "pauses",
+        "paused_secs",
+        "nudged_secs",
+        "resumed",
+        "task",
+        "note",
+    ]
+    .map(column);
+    let rows = lines
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(',').collect();
+            let get = |column: Option<usize>| {
+                column
+                    .and_then(|column| fields.get(column))
+                    .map(|field| field.trim())
+                    .filter(|field| !field.is_empty())
+            };
+            let secs = |column| {
+                get(column)
+                    .and_then(|field| field.parse().ok())
+                    .map_or(Duration::ZERO, Duration::from_secs)
+            };
+            let start = parse_timestamp(get(Some(start))?)?;
+            if !range.contains(start.date_naive()) {
+                return None;
+            }
+            Some(Record {
+                start,
+                end: get(end).and_then(parse_timestamp),
+                kind: get(Some(kind))?.to_string(),
+                planned: secs(planned),
+                focused: secs(focused),
+                outcome: get(outcome)
+                    .and_then(|id| Outcome::ALL.into_iter().find(|outcome| outcome.id() == id)),
+                pauses: get(pauses)
+                    .and_then(|field| field.parse().ok())
+                    .unwrap_or_default(),
+                paused: secs(paused),
+                nudged: get(nudged)
+                    .and_then(|field| field.parse().ok())
+                    .unwrap_or_default(),
+                resumed: get(resumed) == Some("true"),
+                task: get(task).map(str::to_string),
+                // The note is the last column, so commas in it are kept
+                note: note
+                    .and_then(|note| fields.get(note..))
+                    .map(|text| text.join(",").trim().to_string())
+                    .filter(|text| !text.is_empty()),
+                pause_log: Vec::new(),
+            })
+        })
+        .collect();
+    latest(rows)
+}
+
+// The rows with a session written again left out, keeping the place of
+// its first row with the later one
+fn latest(rows: Vec<Record>) -> Vec<Record> {
+    let mut records: Vec<Record> = Vec::new();
+    let mut kept = HashMap::new();
+    for record in rows {
+        match kept.entry((record.start, record.kind.clone())) {
+            Entry::Occupied(entry) => records[*entry.get()] = record,
+            Entry::Vacant(entry) => {
+                entry.insert(records.len());
+                records.push(record);
+            }
+        }
+    }
+    records
+}
+
+// Time spent in work sessions, overtime included
+pub fn focused(records: &[Record]) -> Duration {
+    records
+        .iter()
+        .filter(|record| record.is_work())
+        .map(|record| record.focused)
+        .sum()
+}
+
+// Work sessions completed
+pub fn pomodoros(records: &[Record]) -> u64 {
+    records.iter().filter(|record| record.is_pomodoro()).count() as u64
+}
+
+// How many of the sessions ended each way, in the order of Outcome::ALL.
+// Sessions without an outcome are left out.
+pub fn outcomes(records: &[Record]) -> Vec<(Outcome, usize)> {
+    Outcome::ALL
+        .iter()
+        .map(|outcome| {
+            let sessions = records
+                .iter()
+                .filter(|record| record.outcome == Some(*outcome))
+                .count();
+            (*outcome, sessions)
+        })
+        .collect()
+}
+
+// The sessions' notes, each with the start and kind of the session it's
+// about, e.g. "2026-10-14 09:00 work: finished the parser refactor".
+// Sessions without one are left out.
+pub fn notes(records: &[Record]) -> Vec<String> {
+    records
+        .iter()
+        .filter_map(|record| {
+            let note = record.note.as_deref()?;
+            Some(format!(
+                "{} {}: {}",
+                record.start.format("%Y-%m-%d %H:%M"),
+                record.kind,
+                note
+            ))
+        })
+        .collect()
+}
+
+// Time focused today, from the history file and this run, starting again
+// from nothing at local midnight
+#[derive(Clone, Copy, Debug)]
+pub struct FocusedToday {
+    date: NaiveDate,
+    // From the history file, before this run
+    recorded: Duration,
+    // The run's own focused time from before today began
+    before_today: Duration,
+    total: Duration,
+}
+
+impl FocusedToday {
+    pub fn new(date: NaiveDate, recorded: Duration) -> Self {
+        FocusedToday {
+            date,
+            recorded,
+            before_today: Duration::ZERO,
+            total: recorded,
+        }
+    }
+
+    // Catch up with the run's focused time so far, including the running
+    // work session, as of `date`
+    pub fn update(&mut self, date: NaiveDate, run_focused: Duration) {
+        if date != self.date {
+            self.date = date;
+            self.recorded = Duration::ZERO;
+            self.before_today = run_focused;
+        }
+        self.total = self.recorded + run_focused.saturating_sub(self.before_today);
+    }
+
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+}
@@ -0,0 +1,213 @@
+use std::{fs, path::Path, path::PathBuf, time::Duration};
+
+use rusqlite::{Connection, OptionalExtension, TransactionBehavior, params};
+
+use super::{DateRange, HistoryStore, Pause, Record, parse_timestamp, timestamp};
+use crate::{error::PomodoroError, timer::Outcome};
+
+// Each step brings the schema up from the one before it. The database keeps
+// how many it has had in its user_version, so new steps go at the end.
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE sessions (
+        id INTEGER PRIMARY KEY,
+        start TEXT NOT NULL,
+        -- The local date it started on, which date ranges go by
+        date TEXT NOT NULL,
+        end TEXT,
+        kind TEXT NOT NULL,
+        planned_secs INTEGER NOT NULL,
+        focused_secs INTEGER NOT NULL,
+        outcome TEXT,
+        pauses INTEGER NOT NULL,
+        paused_secs INTEGER NOT NULL,
+        nudged_secs INTEGER NOT NULL,
+        task TEXT,
+        note TEXT
+    );
+    CREATE INDEX sessions_date ON sessions (date);
+    CREATE TABLE pauses (
+        session INTEGER NOT NULL REFERENCES sessions (id) ON DELETE CASCADE,
+        start TEXT NOT NULL,
+        paused_secs INTEGER NOT NULL
+    );
+    CREATE INDEX pauses_session ON pauses (session);
+",
+    "
+    ALTER TABLE sessions ADD COLUMN resumed INTEGER NOT NULL DEFAULT 0;
+",
+];
+
+// How long to wait for another timer that's writing at the same time
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+// The history as a SQLite database, with a row for every session and
+// another for each of its pauses
+pub struct SqliteStore {
+    path: PathBuf,
+    connection: Connection,
+}
+
+impl SqliteStore {
+    // Open the database at `path`, creating it and bringing its schema up
+    // to date first if need be
+    pub fn open(path: PathBuf) -> Result<Self, PomodoroError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|source| PomodoroError::Io {
+                action: "write",
+                path: dir.to_path_buf(),
+                source,
+            })?;
+        }
+        let error = |source| PomodoroError::Database {
+            path: path.clone(),
+            source,
+        };
+        let mut connection = Connection::open(&path).map_err(error)?;
+        connection.busy_timeout(BUSY_TIMEOUT).map_err(error)?;
+        migrate(&mut connection).map_err(error)?;
+        Ok(SqliteStore { path, connection })
+    }
+
+    fn error(&self, source: rusqlite::Error) -> PomodoroError {
+        PomodoroError::Database {
+            path: self.path.clone(),
+            source,
+        }
+    }
+
+    fn insert(&mut self, record: &Record) -> rusqlite::Result<()> {
+        let transaction = self.connection.transaction()?;
+        // A session written again keeps its row, with the pauses replaced
+        let existing: Option<i64> = transaction
+            .query_row(
+                "SELECT id FROM sessions WHERE start = ?1 AND kind = ?2",
+                params![timestamp(record.start), record.kind],
+                |row| row.get(0),
+            )
+            .optional()?;
+        transaction.execute("DELETE FROM pauses WHERE session = ?1", [existing])?;
+        transaction.execute(
+            "INSERT OR REPLACE INTO sessions (id, start, date, end, kind, planned_secs,
+                focused_secs, outcome, pauses, paused_secs, nudged_secs, resumed, task, note)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            params![
+                existing,
+                timestamp(record.start),
+                record.start.date_naive().to_string(),
+                record.end.map(timestamp),
+                record.kind,
+                record.planned.as_secs() as i64,
+                record.focused.as_secs() as i64,
+                record.outcome.map(Outcome::id),
+                record.pauses,
+                record.paused.as_secs() as i64,
+                record.nudged,
+                record.resumed,
+                record.task,
+                record.note,
+            ],
+        )?;
+        let session = transaction.last_insert_rowid();
+        for pause in &record.pause_log {
+            transaction.execute(
+                "INSERT INTO pauses (session, start, paused_secs) VALUES (?1, ?2, ?3)",
+                params![
+                    session,
+                    timestamp(pause.start),
+                    pause.length.as_secs() as i64
+                ],
+            )?;
+        }
+        transaction.commit()
+    }
+
+    fn select(&self, range: DateRange) -> rusqlite::Result<Vec<Record>> {
+        let mut sessions = self.connection.prepare(
+            "SELECT id, start, end, kind, planned_secs, focused_secs, outcome, pauses,
+                paused_secs, nudged_secs, resumed, task, note
+            FROM sessions
+            WHERE (?1 IS NULL OR date >= ?1) AND (?2 IS NULL OR date <= ?2)
+            ORDER BY id",
+        )?;
+        let mut pauses = self
+            .connection
+            .prepare("SELECT start, paused_secs FROM pauses WHERE session = ?1 ORDER BY start")?;
+        let from = range.from.map(|date| date.to_string());
+        let to = range.to.map(|date| date.to_string());
+        // Rows with a start that can't be read are left out
+        let rows = sessions.query_map(params![from, to], |row| {
+            let Some(start) = parse_timestamp(&row.get::<_, String>(1)?) else {
+                return Ok(None);
+            };
+            let outcome: Option<String> = row.get(6)?;
+            let record = Record {
+                start,
+                end: row
+                    .get::<_, Option<String>>(2)?
+                    .as_deref()
+                    .and_then(parse_timestamp),
+                kind: row.get(3)?,
+                planned: secs(row.get(4)?),
+                focused: secs(row.get(5)?),
+                outcome: outcome
+                    .and_then(|id| Outcome::ALL.into_iter().find(|outcome| outcome.id() == id)),
+                pauses: row.get(7)?,
+                paused: secs(row.get(8)?),
+                nudged: row.get(9)?,
+                resumed: row.get(10)?,
+                task: row.get(11)?,
+                note: row.get(12)?,
+                pause_log: Vec::new(),
+            };
+            Ok(Some((row.get::<_, i64>(0)?, record)))
+        })?;
+        let mut records = Vec::new();
+        for row in rows {
+            let Some((id, mut record)) = row? else {
+                continue;
+            };
+            for pause in pauses.query_map([id], |row| {
+                Ok((row.get::<_, String>(0)?, secs(row.get(1)?)))
+            })? {
+                let (start, length) = pause?;
+                if let Some(start) = parse_timestamp(&start) {
+                    record.pause_log.push(Pause { start, length });
+                }
+            }
+            records.push(record);
+        }
+        Ok(records)
+    }
+}
+
+impl HistoryStore for SqliteStore {
+    fn append(&mut self, record: &Record) -> Result<(), PomodoroError> {
+        self.insert(record).map_err(|source| self.error(source))
+    }
+
+    fn records(&self, range: DateRange) -> Result<Vec<Record>, PomodoroError> {
+        self.select(range).map_err(|source| self.error(source))
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+// Bring the schema up to date. Two timers starting at once take turns, so
+// only one of them migrates.
+fn migrate(connection: &mut Connection) -> rusqlite::Result<()> {
+    let transaction = connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let version: i64 = transaction.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    for (step, sql) in (1..).zip(MIGRATIONS).skip(version.max(0) as usize) {
+        transaction.execute_batch(sql)?;
+        transaction.pragma_update(None, "user_version", step)?;
+    }
+    transaction.commit()
+}
+
+// Seconds as they're stored, which SQLite only has signed integers for
+fn secs(seconds: i64) -> Duration {
+    Duration::from_secs(seconds.max(0) as u64)
+}
@@ -0,0 +1,197 @@
+use std::{
+    sync::mpsc,
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use chrono::{DateTime, Local};
+use pomodoro_timer::{
+    history::{HistoryStore, Pause, Record},
+    timer::{Outcome, SessionEnd, TimerEvent, TimerState},
+};
+
+use crate::{display::Display, notes::Notes};
+
+// The timer listener that adds every session that ends, however it ends, to
+// the history. They're written from a thread of their own, so a slow disk
+// never holds up the timer.
+pub struct HistoryLog {
+    sender: mpsc::Sender<Record>,
+    notes: Notes,
+    // The running session, from when it started
+    running: Option<Started>,
+    // When the running session was paused, while it is
+    paused_at: Option<DateTime<Local>>,
+    // The session that ended last, in case its skip is undone
+    ended: Option<Started>,
+    // The session notes go with once it has ended, as it was last written.
+    // Notes typed in the break after it have it written again, which
+    // readers of the history take in place of the earlier row.
+    noted: Option<(u64, Record)>,
+    // The work session completed last, since overtime starts it again
+    completed: Option<u64>,
+}
+
+#[derive(Clone)]
+struct Started {
+    at: DateTime<Local>,
+    // Its place among the sessions started, which its notes go by
+    started: u64,
+    overtime: bool,
+    task: Option<String>,
+    pauses: Vec<Pause>,
+}
+
+impl HistoryLog {
+    // The writing thread stops once the log is dropped along with the
+    // timer, and should be waited for so the last sessions aren't lost
+    pub fn new(
+        mut store: Box<dyn HistoryStore>,
+        notes: Notes,
+        display: Display,
+    ) -> (Self, JoinHandle<()>) {
+        let (sender, receiver) = mpsc::channel::<Record>();
+        let writer = thread::spawn(move || {
+            // Reported once, a history that can't be written fails every time
+            let mut warned = false;
+            for record in receiver {
+                if let Err(err) = store.append(&record)
+                    && !warned
+                {
+                    warned = true;
+                    say!(display, "warning: {}", err);
+                }
+            }
+        });
+        let log = HistoryLog {
+            sender,
+            notes,
+            running: None,
+            paused_at: None,
+            ended: None,
+            noted: None,
+            completed: None,
+        };
+        (log, writer)
+    }
+
+    pub fn on_event(&mut self, event: &TimerEvent) {
+        match *event {
+            TimerEvent::SessionStarted { kind, number, .. } => {
+                self.write_notes();
+                // Unless it's a break, notes go with this one from now on
+                if let Some((started, _)) = &self.noted
+                    && !self.notes.taking(*started)
+                {
+                    self.noted = None;
+                }
+                // Even when it cuts into the break that followed
+                let overtime = kind == TimerState::Work && self.completed == Some(number);
+                self.paused_at = None;
+                self.running = Some(Started {
+                    at: Local::now(),
+                    // The notes listener has already counted this one
+                    started: self.notes.started(),
+                    overtime,
+                    task: if kind == TimerState::Work {
+                        self.notes.task()
+                    } else {
+                        None
+                    },
+                    pauses: Vec::new(),
+                });
+            }
+            TimerEvent::Paused { .. } => self.paused_at = Some(Local::now()),
+            TimerEvent::Resumed { .. } => self.resumed(),
+            // Starting over makes a new session, the old one is kept as
+            // restarted
+            TimerEvent::Restarted { .. } => {
+                if let Some(running) = &mut self.running {
+                    running.at = Local::now();
+                    running.pauses.clear();
+                }
+            }
+            TimerEvent::SessionEnded { end } => {
+                if end.outcome == Outcome::Completed && end.session.state == TimerState::Work {
+                    self.completed = Some(end.session.number);
+                }
+                // Ending while paused ends the pause too
+                self.resumed();
+                let Some(started) = self.running.clone() else {
+                    return;
+                };
+                self.write_notes();
+                let record = self.record(&started, &end);
+                let _ = self.sender.send(record.clone());
+                if end.outcome != Outcome::Restarted && self.notes.taking(started.started) {
+                    self.noted = Some((started.started, record));
+                }
+                self.ended = Some(started);
+            }
+            // The skipped session carries on, and is written again when it
+            // ends for good
+            TimerEvent::SkipUndone { .. } => {
+                if let Some(started) = self.ended.take() {
+                    self.noted = None;
+                    self.running = Some(started);
+                }
+            }
+            TimerEvent::Stopped { .. } => self.completed = None,
+            TimerEvent::Finished => self.write_notes(),
+            _ => {}
+        }
+    }
+
+    fn resumed(&mut self) {
+        if let (Some(start), Some(running)) = (self.paused_at.take(), &mut self.running) {
+            running.pauses.push(Pause {
+                start,
+                length: (Local::now() - start).to_std().unwrap_or_default(),
+            });
+        }
+    }
+
+    // Write the session notes go with again if more were typed since it
+    // was last written
+    fn write_notes(&mut self) {
+        let Some((started, record)) = &mut self.noted else {
+            return;
+        };
+        let note = self.notes.note_for(*started);
+        if note != record.note {
+            record.note = note;
+            let _ = self.sender.send(record.clone());
+        }
+    }
+
+    fn record(&self, started: &Started, end: &SessionEnd) -> Record {
+        // A restarted session's notes are about the one that took its place
+        let note = match end.outcome {
+            Outcome::Restarted => None,
+            _ => self.notes.note_for(started.started),
+        };
+        let kind = if started.overtime {
+            "overtime"
+        } else {
+            end.session.state.id()
+        };
+        // What it was meant to last before Extend and the minute keys
+        let planned =
+            end.session.length.as_secs() as i64 - end.extended.as_secs() as i64 - end.nudged;
+        Record {
+            start: started.at,
+            end: Some(Local::now()),
+            kind: kind.to_string(),
+            planned: Duration::from_secs(planned.max(0) as u64),
+            focused: end.elapsed,
+            outcome: Some(end.outcome),
+            pauses: end.pauses,
+            paused: end.paused,
+            nudged: end.nudged,
+            resumed: end.resumed,
+            task: started.task.clone(),
+            note,
+            pause_log: started.pauses.clone(),
+        }
+    }
+}
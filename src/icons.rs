@@ -0,0 +1,101 @@
+use std::fmt;
+
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::timer::TimerState;
+
+// The glyphs put in front of headers, the countdown and the summary with
+// --icons, kept in one place so that anything showing the timer's state
+// can use the same ones
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Icons {
+    pub work: &'static str,
+    pub break_: &'static str,
+    pub long_break: &'static str,
+    pub paused: &'static str,
+    pub stopped: &'static str,
+    // After the countdown while the sound is muted
+    pub muted: &'static str,
+    // A completed pomodoro
+    pub completed: &'static str,
+}
+
+pub const EMOJI: Icons = Icons {
+    work: "\u{1f345}",
+    break_: "\u{2615}",
+    long_break: "\u{1f334}",
+    paused: "\u{23f8}",
+    stopped: "\u{23f9}",
+    muted: "\u{1f507}",
+    completed: "\u{2705}",
+};
+
+// For terminals that can't show the emoji
+pub const ASCII: Icons = Icons {
+    work: "[W]",
+    break_: "[B]",
+    long_break: "[L]",
+    paused: "[P]",
+    stopped: "[S]",
+    muted: "[M]",
+    completed: "[x]",
+};
+
+impl Icons {
+    pub fn session(&self, kind: TimerState) -> &'static str {
+        match kind {
+            TimerState::Work => self.work,
+            TimerState::Break => self.break_,
+            TimerState::LongBreak => self.long_break,
+            TimerState::Stopped => self.stopped,
+        }
+    }
+}
+
+// Which icons --icons asks for. Auto picks the emoji when the locale is
+// UTF-8 and the ASCII set otherwise.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum IconStyle {
+    #[default]
+    Off,
+    Auto,
+    Emoji,
+    Ascii,
+}
+
+impl IconStyle {
+    // The icons to use, None when they're off. `locale` looks up a locale
+    // environment variable, e.g. LANG.
+    pub fn icons(self, locale: impl Fn(&str) -> Option<String>) -> Option<Icons> {
+        match self {
+            IconStyle::Off => None,
+            IconStyle::Emoji => Some(EMOJI),
+            IconStyle::Ascii => Some(ASCII),
+            IconStyle::Auto => {
+                // The first of these that is set decides, as for the C
+                // library
+                let utf8 = ["LC_ALL", "LC_CTYPE", "LANG"]
+                    .iter()
+                    .find_map(|name| locale(name).filter(|value| !value.is_empty()))
+                    .is_some_and(|value| {
+                        let value = value.to_lowercase();
+                        value.contains("utf-8") || value.contains("utf8")
+                    });
+                Some(if utf8 { EMOJI } else { ASCII })
+            }
+        }
+    }
+}
+
+impl fmt::Display for IconStyle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IconStyle::Off => write!(f, "off"),
+            IconStyle::Auto => write!(f, "auto"),
+            IconStyle::Emoji => write!(f, "emoji"),
+            IconStyle::Ascii => write!(f, "ascii"),
+        }
+    }
+}
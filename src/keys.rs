@@ -0,0 +1,309 @@
+use std::time::Duration;
+
+use crate::{config::KeysConfig, error::PomodoroError, timer::TimerCommand};
+
+// Something the user can ask for by pressing a key
+#[derive(Clone, Copy, PartialEq)]
+pub enum Action {
+    Pause,
+    Resume,
+    Skip,
+    SkipBreak,
+    Undo,
+    OverrideNext,
+    Extend,
+    AddMinute,
+    RemoveMinute,
+    Restart,
+    LongBreak,
+    TakeBreak,
+    Snooze,
+    Note,
+    SaveConfig,
+    ToggleElapsed,
+    Mute,
+    Status,
+    Goal,
+    Strict,
+    Help,
+    Stop,
+    Quit,
+}
+
+impl Action {
+    const ALL: [Action; 23] = [
+        Action::Pause,
+        Action::Resume,
+        Action::Skip,
+        Action::SkipBreak,
+        Action::Undo,
+        Action::OverrideNext,
+        Action::Extend,
+        Action::AddMinute,
+        Action::RemoveMinute,
+        Action::Restart,
+        Action::LongBreak,
+        Action::TakeBreak,
+        Action::Snooze,
+        Action::Note,
+        Action::SaveConfig,
+        Action::ToggleElapsed,
+        Action::Mute,
+        Action::Status,
+        Action::Goal,
+        Action::Strict,
+        Action::Help,
+        Action::Stop,
+        Action::Quit,
+    ];
+
+    // Name of the action's key in the [keys] config table
+    fn config_name(self) -> &'static str {
+        match self {
+            Action::Pause => "pause",
+            Action::Resume => "resume",
+            Action::Skip => "skip",
+            Action::SkipBreak => "skip_break",
+            Action::Undo => "undo",
+            Action::OverrideNext => "next",
+            Action::Extend => "extend",
+            Action::AddMinute => "add_minute",
+            Action::RemoveMinute => "remove_minute",
+            Action::Restart => "restart",
+            Action::LongBreak => "long_break",
+            Action::TakeBreak => "break",
+            Action::Snooze => "snooze",
+            Action::Note => "note",
+            Action::SaveConfig => "save",
+            Action::ToggleElapsed => "toggle",
+            Action::Mute => "mute",
+            Action::Status => "status",
+            Action::Goal => "goal",
+            Action::Strict => "strict",
+            Action::Help => "help",
+            Action::Stop => "stop",
+            Action::Quit => "quit",
+        }
+    }
+
+    fn default_key(self) -> &'static str {
+        match self {
+            Action::Pause => "p",
+            Action::Resume => "r",
+            Action::Skip => "s",
+            Action::SkipBreak => "S",
+            Action::Undo => "u",
+            Action::OverrideNext => "d",
+            Action::Extend => "e",
+            Action::AddMinute => "+",
+            Action::RemoveMinute => "-",
+            Action::Restart => "R",
+            Action::LongBreak => "L",
+            Action::TakeBreak => "b",
+            Action::Snooze => "z",
+            Action::Note => "n",
+            Action::SaveConfig => "w",
+            Action::ToggleElapsed => "t",
+            Action::Mute => "m",
+            Action::Status => "i",
+            Action::Goal => "g",
+            Action::Strict => "k",
+            Action::Help => "h",
+            Action::Stop => "x",
+            Action::Quit => "q",
+        }
+    }
+
+    // The command the action sends to the timer, None for OverrideNext,
+    // Extend and Snooze which need a length as well and for Help and Note
+    // which the frontend answers itself
+    pub fn command(self) -> Option<TimerCommand> {
+        match self {
+            Action::Pause => Some(TimerCommand::Pause),
+            Action::Resume => Some(TimerCommand::Resume),
+            Action::Skip => Some(TimerCommand::Skip),
+            Action::SkipBreak => Some(TimerCommand::SkipBreak),
+            Action::Undo => Some(TimerCommand::Undo),
+            Action::OverrideNext | Action::Extend | Action::Snooze => None,
+            Action::AddMinute => Some(TimerCommand::AddMinute),
+            Action::RemoveMinute => Some(TimerCommand::RemoveMinute),
+            Action::Restart => Some(TimerCommand::Restart),
+            Action::LongBreak => Some(TimerCommand::LongBreak),
+            Action::TakeBreak => Some(TimerCommand::TakeBreak),
+            Action::SaveConfig => Some(TimerCommand::SaveConfig),
+            Action::ToggleElapsed => Some(TimerCommand::ToggleElapsed),
+            Action::Mute => Some(TimerCommand::ToggleMute),
+            Action::Status => Some(TimerCommand::Status),
+            Action::Goal => Some(TimerCommand::Goal),
+            Action::Strict => Some(TimerCommand::ToggleStrict),
+            Action::Help | Action::Note => None,
+            Action::Stop => Some(TimerCommand::Stop),
+            Action::Quit => Some(TimerCommand::Quit),
+        }
+    }
+
+    // Completes "Press 'p' to ..."
+    fn description(self) -> &'static str {
+        match self {
+            Action::Pause => "pause",
+            Action::Resume => "resume",
+            Action::Skip => "skip",
+            Action::SkipBreak => "skip the break and start working",
+            Action::Undo => "undo the last skip",
+            Action::OverrideNext => "set the next session's length",
+            Action::Extend => "add time to the running session",
+            Action::AddMinute => "add a minute to the running session",
+            Action::RemoveMinute => "take a minute off the running session",
+            Action::Restart => "start the running session over",
+            Action::LongBreak => "take a long break now",
+            Action::TakeBreak => "take a break instead of the next session",
+            Action::Snooze => "put off the next session",
+            Action::Note => "add a note to the session",
+            Action::SaveConfig => "save settings",
+            Action::ToggleElapsed => "switch between time remaining and elapsed",
+            Action::Mute => "mute or unmute the sound",
+            Action::Status => "show the timer's status",
+            Action::Goal => "show progress towards the daily goal",
+            Action::Strict => "turn strict mode on or off",
+            Action::Help => "list the keys and settings",
+            Action::Stop => "stop until you start again",
+            Action::Quit => "quit",
+        }
+    }
+}
+
+// The lengths the keys that take one use when pressed on their own
+#[derive(Clone, Copy)]
+pub struct Amounts {
+    pub extend: Duration,
+    pub snooze: Duration,
+}
+
+// The key typed for each action, from the [keys] table or the defaults
+#[derive(Clone)]
+pub struct KeyBindings {
+    bindings: Vec<(String, Action)>,
+}
+
+impl KeyBindings {
+    // Rejects empty keys and keys bound to more than one action
+    pub fn from_config(keys: &KeysConfig) -> Result<Self, PomodoroError> {
+        let mut bindings: Vec<(String, Action)> = Vec::new();
+        for action in Action::ALL {
+            let configured = match action {
+                Action::Pause => &keys.pause,
+                Action::Resume => &keys.resume,
+                Action::Skip => &keys.skip,
+                Action::SkipBreak => &keys.skip_break,
+                Action::Undo => &keys.undo,
+                Action::OverrideNext => &keys.next,
+                Action::Extend => &keys.extend,
+                Action::AddMinute => &keys.add_minute,
+                Action::RemoveMinute => &keys.remove_minute,
+                Action::Restart => &keys.restart,
+                Action::LongBreak => &keys.long_break,
+                Action::TakeBreak => &keys.break_,
+                Action::Snooze => &keys.snooze,
+                Action::Note => &keys.note,
+                Action::SaveConfig => &keys.save,
+                Action::ToggleElapsed => &keys.toggle,
+                Action::Mute => &keys.mute,
+                Action::Status => &keys.status,
+                Action::Goal => &keys.goal,
+                Action::Strict => &keys.strict,
+                Action::Help => &keys.help,
+                Action::Stop => &keys.stop,
+                Action::Quit => &keys.quit,
+            };
+            let key = configured
+                .as_deref()
+                .unwrap_or(action.default_key())
+                .trim()
+                .to_string();
+
+            if key.is_empty() || key.contains(char::is_whitespace) {
+                return Err(PomodoroError::InvalidConfig(format!(
+                    "`keys.{}` must be a key without spaces",
+                    action.config_name()
+                )));
+            }
+            if let Some((_, other)) = bindings.iter().find(|(bound, _)| bound == &key) {
+                return Err(PomodoroError::InvalidConfig(format!(
+                    "key '{}' is bound to both `keys.{}` and `keys.{}`",
+                    key,
+                    other.config_name(),
+                    action.config_name()
+                )));
+            }
+            bindings.push((key, action));
+        }
+        // '?' asks for help too, unless it's been given to something else
+        if !bindings.iter().any(|(key, _)| key == "?") {
+            bindings.push(("?".to_string(), Action::Help));
+        }
+        Ok(KeyBindings { bindings })
+    }
+
+    // Exact matches win, so bindings can differ only by case
+    pub fn lookup(&self, input: &str) -> Option<Action> {
+        let find = |input: &str| {
+            self.bindings
+                .iter()
+                .find(|(key, _)| key == input)
+                .map(|(_, action)| *action)
+        };
+        find(input).or_else(|| find(&input.to_lowercase()))
+    }
+
+    // Whether `input` is the start of a longer key, so reading
+    // keypresses should wait for the rest of it
+    pub fn is_prefix(&self, input: &str) -> bool {
+        let lowercase = input.to_lowercase();
+        self.bindings.iter().any(|(key, _)| {
+            key.len() > input.len() && (key.starts_with(input) || key.starts_with(&lowercase))
+        })
+    }
+
+    pub fn key(&self, action: Action) -> &str {
+        self.bindings
+            .iter()
+            .find(|(_, bound)| *bound == action)
+            .map(|(key, _)| key.as_str())
+            .unwrap_or(action.default_key())
+    }
+
+    // "Press 'p' to pause, 's' to skip, 'q' to quit."
+    pub fn hint(&self, actions: &[Action]) -> String {
+        let parts: Vec<String> = actions
+            .iter()
+            .map(|&action| format!("'{}' to {}", self.key(action), action.description()))
+            .collect();
+        format!("Press {}.", parts.join(", "))
+    }
+
+    pub fn hint_all(&self) -> String {
+        self.hint(&Action::ALL)
+    }
+
+    // Every key with what it does, one per line, starting with Enter
+    pub fn help(&self) -> Vec<String> {
+        let mut rows = vec![("Enter".to_string(), "start the next session")];
+        for action in Action::ALL {
+            let keys: Vec<&str> = self
+                .bindings
+                .iter()
+                .filter(|(_, bound)| *bound == action)
+                .map(|(key, _)| key.as_str())
+                .collect();
+            rows.push((keys.join(", "), action.description()));
+        }
+        let width = rows
+            .iter()
+            .map(|(keys, _)| keys.chars().count())
+            .max()
+            .unwrap_or(0);
+        rows.into_iter()
+            .map(|(keys, description)| format!("  {:<width$}  {}", keys, description))
+            .collect()
+    }
+}
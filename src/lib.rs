@@ -0,0 +1,22 @@
+// The timer itself and everything it's configured from, separate from the
+// terminal frontend in main.rs
+#[cfg(feature = "tokio")]
+pub mod async_timer;
+pub mod cli;
+pub mod clock;
+pub mod color;
+pub mod config;
+pub mod driver;
+pub mod duration;
+pub mod error;
+pub mod event_log;
+pub mod events;
+pub mod format;
+pub mod history;
+pub mod icons;
+pub mod keys;
+pub mod recovery;
+pub mod settings;
+pub mod sound;
+pub mod stats;
+pub mod timer;
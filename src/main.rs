@@ -1,8 +1,30 @@
+// Print a human-readable message through a Display, e.g.
+// say!(display, "Timer Paused.")
+macro_rules! say {
+    ($display:expr, $($arg:tt)*) => {
+        $display.message(format_args!($($arg)*))
+    };
+}
+
+mod confirm;
+mod console;
+mod desktop;
+mod display;
+mod flash;
+mod goal;
+mod history_log;
+mod notes;
+mod suggestions;
+mod terminal;
+mod title;
+mod tui;
+
 use std::{
-    io::{Write, stdin, stdout},
-    process::Command,
+    env,
+    io::{BufRead, IsTerminal, Write, stdin, stdout},
+    process,
     sync::{
-        Arc,
+        Arc, Mutex,
         atomic::{AtomicBool, Ordering},
         mpsc,
     },
@@ -10,186 +32,1589 @@ use std::{
     time::{Duration, Instant},
 };
 
-const DEFAULT_WORK_MINUTES: u64 = 25;
-const DEFAULT_BREAK_MINUTES: u64 = 5;
+use chrono::{Local, NaiveTime, TimeDelta};
+use clap::{CommandFactory, Parser};
+use clap_complete::Shell;
+
+use confirm::QuitGuard;
+use console::{Console, CountdownStyle, count};
+use desktop::DesktopNotifier;
+use display::Display;
+use flash::Flash;
+use goal::Goal;
+use history_log::HistoryLog;
+use notes::Notes;
+use pomodoro_timer::{
+    cli::{Cli, Command, StartArgs},
+    clock::{Clock, SystemClock},
+    config::{self, Config, Profile},
+    driver::{self, Frontend},
+    duration::{format_duration, format_hours_minutes, parse_duration},
+    error::PomodoroError,
+    event_log::EventLog,
+    events::{Status, StatusLine},
+    format::{ClockStyle, CountdownFormat, format_clock},
+    history::{self, DateRange, FocusedToday},
+    keys::{Action, Amounts, KeyBindings},
+    recovery::{self, StateFile},
+    settings,
+    sound::Notifier,
+    stats::{Period, Report},
+    timer::{
+        Checkpoint, Counts, Outcome, Phase, PomodoroConfig, PomodoroTimer, Reply, SNOOZE_WINDOW,
+        TimerCommand, TimerState, UNDO_WINDOW,
+    },
+};
+use suggestions::Suggestions;
+use title::TerminalTitle;
+use tui::Tui;
+
+// Exit status for --once when the work session was skipped or quit early
+const EXIT_SESSION_ABANDONED: i32 = 3;
+
+// Exit status after Ctrl+C, the usual 128 + SIGINT
+const EXIT_INTERRUPTED: i32 = 130;
 
-// Function to play a simple beep sound (works on most systems)
-fn play_beep() {
-    print!("\x07"); // ASCII bell character
-    stdout().flush().unwrap();
+// Exit statuses for errors, EX_IOERR and EX_CONFIG from sysexits.h
+const EXIT_IO_ERROR: i32 = 74;
+const EXIT_CONFIG_ERROR: i32 = 78;
+
+// A second Ctrl+C within this long exits straight away
+const FORCE_QUIT_WINDOW: Duration = Duration::from_secs(2);
+
+// With keys read as they're pressed, starts a command typed out in full and
+// ended with Enter, such as ":work 50", unless a binding uses it
+const COMMAND_KEY: char = ':';
+
+// Local wall-clock time once `remaining` has passed. One too far off for
+// the calendar still gives the time of day it would come round to.
+fn end_time(remaining: Duration) -> NaiveTime {
+    let now = Local::now();
+    let remaining = TimeDelta::from_std(remaining).unwrap_or(TimeDelta::MAX);
+    now.checked_add_signed(remaining).map_or_else(
+        || now.time().overflowing_add_signed(remaining).0,
+        |end| end.time(),
+    )
 }
 
-enum TimerState {
-    Work,
-    Break,
-    Paused,
-    Stopped,
+// Write the current durations to the config file and report where they went
+fn save_settings(plan: &PomodoroConfig, display: Display) {
+    let defaults = config::Defaults {
+        work: plan.work(),
+        break_: plan.break_(),
+        long_break: plan.long_break(),
+        sessions_until_long_break: plan.sessions_until_long_break(),
+    };
+    match config::save(&defaults) {
+        Ok(path) => say!(display, "Saved current settings to {}", path.display()),
+        Err(err) => say!(display, "Could not save settings: {}", err),
+    }
+}
+
+// Print every key and what it does, followed by the run's settings
+fn print_help(display: Display, key_bindings: &KeyBindings, settings: &Mutex<String>) {
+    let settings = settings
+        .lock()
+        .map(|settings| settings.clone())
+        .unwrap_or_default();
+    say!(
+        display,
+        "Keys:\n{}\n{}",
+        key_bindings.help().join("\n"),
+        settings
+    );
+}
+
+// Read one answer to a startup prompt, exiting quietly on Ctrl+D
+fn read_answer() -> String {
+    read_input_line().unwrap_or_else(|| {
+        println!();
+        process::exit(0);
+    })
+}
+
+// One trimmed line from stdin, or None once it's closed. Bytes that aren't
+// valid UTF-8 are replaced rather than treated as an error, so stray input
+// can't stop the timer from reading commands.
+fn read_input_line() -> Option<String> {
+    read_line_from(&mut stdin().lock())
+}
+
+// Like read_input_line, for a thread already holding stdin's lock, which
+// can't be taken twice
+fn read_line_from(input: &mut impl BufRead) -> Option<String> {
+    let mut line = Vec::new();
+    match input.read_until(b'\n', &mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(String::from_utf8_lossy(&line).trim().to_string()),
+    }
+}
+
+// Ask for a duration until a valid one is given, empty input keeps the default
+fn prompt_duration(label: &str, default: Duration) -> Duration {
+    loop {
+        println!(
+            "Enter {} duration (e.g. 25, 2.5, 90s, 1h30m; default {}):",
+            label,
+            format_duration(default)
+        );
+        let input = read_answer();
+        if input.is_empty() {
+            return default;
+        }
+        match parse_duration(&input) {
+            Ok(duration) => return duration,
+            Err(_) => println!("Invalid duration, please enter a positive number of minutes"),
+        }
+    }
+}
+
+// Offer the configured profiles, empty input means "no profile"
+fn prompt_profile(config: &Config) -> Option<&Profile> {
+    loop {
+        println!(
+            "Choose a profile ({}; default none):",
+            config.profile_names().join(", ")
+        );
+        let name = read_answer();
+        if name.is_empty() {
+            return None;
+        }
+        match config.profile(&name) {
+            Ok(profile) => return Some(profile),
+            Err(err) => println!("{}", err),
+        }
+    }
 }
 
-enum TimerCommand {
-    Pause,
-    Resume,
-    Skip,
-    Quit,
+// "Resume Work Session 3 with 07:12 remaining? (y/n)", until it's answered
+fn confirm_resume(checkpoint: &Checkpoint) -> bool {
+    let remaining = checkpoint.session.length.saturating_sub(checkpoint.elapsed);
+    loop {
+        println!(
+            "Resume {} with {} remaining? (y/n)",
+            checkpoint.session.label(),
+            format_clock(remaining)
+        );
+        if let Some(answer) = confirm::answer(&read_answer()) {
+            return answer;
+        }
+    }
 }
 
 fn main() {
-    println!("--- Rust Pomodoro Timer ---");
+    let cli = Cli::parse();
+    match cli.command {
+        None => start(cli.start),
+        Some(Command::Start(args)) => start(args),
+        Some(Command::Stats {
+            period,
+            from,
+            to,
+            json,
+        }) => stats(period, DateRange { from, to }, json),
+        Some(Command::Config(args)) => show_config(args),
+        Some(Command::Completions { shell }) => completions(shell),
+    }
+}
+
+// `pomodoro completions SHELL`: write the completion script to stdout
+fn completions(shell: Shell) {
+    let mut command = Cli::command();
+    let name = command.get_name().to_string();
+    clap_complete::generate(shell, &mut command, name, &mut stdout());
+}
 
-    let mut work_minutes = DEFAULT_WORK_MINUTES;
-    let mut break_minutes = DEFAULT_BREAK_MINUTES;
+// The exit status for an error, so scripts can tell a bad config file from
+// a bad command line
+fn exit_code(err: &PomodoroError) -> i32 {
+    match err {
+        PomodoroError::ConfigParse { .. }
+        | PomodoroError::InvalidConfig(_)
+        | PomodoroError::UnknownProfile { .. }
+        | PomodoroError::NoConfigDir => EXIT_CONFIG_ERROR,
+        PomodoroError::Io { .. } => EXIT_IO_ERROR,
+        _ => 1,
+    }
+}
+
+// Report a fatal error and exit with its status
+fn fail(err: PomodoroError) -> ! {
+    eprintln!("error: {}", err);
+    process::exit(exit_code(&err));
+}
+
+// Load the config file and its key bindings, exiting if either is invalid
+fn load_config() -> (Config, KeyBindings) {
+    let config = config::load().unwrap_or_else(|err| fail(err));
+    let key_bindings = KeyBindings::from_config(&config.keys).unwrap_or_else(|err| {
+        eprintln!("error: invalid config file: {}", err);
+        process::exit(exit_code(&err));
+    });
+    (config, key_bindings)
+}
 
-    println!("\nEnter work duration (minutes, default {}):", work_minutes);
-    let mut input = String::new();
-    stdin().read_line(&mut input).unwrap();
-    if let Ok(value) = input.trim().parse() {
-        if value > 0 {
-            work_minutes = value;
+// The profile named by --profile, exiting if it doesn't exist
+fn selected_profile<'a>(config: &'a Config, args: &StartArgs) -> Option<&'a Profile> {
+    args.profile
+        .as_deref()
+        .map(|name| config.profile(name).unwrap_or_else(|err| fail(err)))
+}
+
+// `pomodoro config`: print each resolved setting and where it came from
+fn show_config(args: StartArgs) {
+    let (config, _) = load_config();
+    let profile = selected_profile(&config, &args);
+    let settings = settings::resolve(&args, &config, profile, |name| env::var(name).ok());
+
+    match config::config_path() {
+        Some(path) if path.is_file() => println!("Config file: {}", path.display()),
+        Some(path) => println!("Config file: {} (not found)", path.display()),
+        None => println!("Config file: none (HOME is not set)"),
+    }
+    for (key, value, source) in settings.describe() {
+        println!("{:<26} = {:<24} ({})", key, value, source);
+    }
+}
+
+// `pomodoro stats`: sum up the session history over `period`, or the days
+// `range` covers, and by default today, this week and all time
+fn stats(period: Option<Period>, range: DateRange, json: bool) {
+    let config = config::load().unwrap_or_else(|err| fail(err));
+    let store = history::open(config.history.unwrap_or_default()).unwrap_or_else(|err| fail(err));
+    let today = Local::now().date_naive();
+    // Only the days asked about are read
+    let (range, periods) = match period {
+        Some(period) => (period.range(today), vec![Some(period)]),
+        None if range != DateRange::default() => (range, vec![None]),
+        None => (
+            range,
+            vec![Some(Period::Today), Some(Period::Week), Some(Period::All)],
+        ),
+    };
+    let records = match &store {
+        Some(store) => store.records(range).unwrap_or_else(|err| fail(err)),
+        None => Vec::new(),
+    };
+    let report = Report::new(&records, range, &periods, today);
+    // Scripts get the zeros rather than a message
+    if json {
+        if let Ok(line) = serde_json::to_string(&report) {
+            println!("{}", line);
+        }
+        return;
+    }
+    let Some(store) = store.filter(|_| !records.is_empty()) else {
+        if range == DateRange::default() {
+            println!("No sessions recorded yet.");
+        } else {
+            println!("No sessions recorded in that time.");
+        }
+        return;
+    };
+
+    println!(
+        "{} recorded in {}",
+        count(report.sessions as u64, "session", "sessions"),
+        store.path().display()
+    );
+    let outcomes: Vec<String> = report
+        .outcomes
+        .iter()
+        .filter(|count| count.sessions > 0)
+        .map(|count| format!("{} {}", count.sessions, count.outcome))
+        .collect();
+    if !outcomes.is_empty() {
+        println!("  {}", outcomes.join(", "));
+    }
+    let labels: Vec<String> = report
+        .totals
+        .iter()
+        .map(|totals| match (totals.period, totals.from, totals.to) {
+            (Some(period), _, _) => format!("{}:", period.name()),
+            (None, Some(from), Some(to)) => format!("{} to {}:", from, to),
+            (None, Some(from), None) => format!("Since {}:", from),
+            (None, None, Some(to)) => format!("Until {}:", to),
+            (None, None, None) => "All time:".to_string(),
+        })
+        .collect();
+    let width = labels.iter().map(String::len).max().unwrap_or_default();
+    for (label, totals) in labels.iter().zip(&report.totals) {
+        println!(
+            "{:<width$} {}, {} focused",
+            label,
+            count(totals.pomodoros, "pomodoro", "pomodoros"),
+            format_hours_minutes(totals.focused),
+        );
+    }
+    if let Some(average) = report.average_pomodoros {
+        println!(
+            "{:.1} pomodoros per active day, over {}",
+            average,
+            count(report.active_days as u64, "day", "days")
+        );
+    }
+    if let Some(streak) = report.longest_streak {
+        match streak.days {
+            1 => println!("Longest streak: 1 day, {}", streak.from),
+            days => println!(
+                "Longest streak: {} days, {} to {}",
+                days, streak.from, streak.to
+            ),
         }
     }
+    if !report.top_tasks.is_empty() {
+        println!("Top tasks:");
+        for count in &report.top_tasks {
+            println!("  {} ({})", count.task, count.pomodoros);
+        }
+    }
+    let notes = history::notes(&records);
+    if !notes.is_empty() {
+        println!("Notes:");
+        for note in notes {
+            println!("  {}", note);
+        }
+    }
+}
+
+// `pomodoro start`, also run when no subcommand is given
+fn start(args: StartArgs) {
+    let (config, key_bindings) = load_config();
+    let selected_profile = selected_profile(&config, &args);
+    // Opened before anything is asked, since it's no use without it
+    let event_log = args
+        .log_events
+        .as_deref()
+        .map(|path| EventLog::open(path).unwrap_or_else(|err| fail(err)));
 
-    input.clear();
-    println!("Enter break duration (minutes, default {}):", break_minutes);
-    stdin().read_line(&mut input).unwrap();
-    if let Ok(value) = input.trim().parse() {
-        if value > 0 {
-            break_minutes = value;
+    let json = args.json;
+    // stdout only gets the --json events or the --status-line lines, and
+    // the prompts would end up in the middle of them
+    let scripted = json || args.status_line;
+    let prompt = !args.no_prompt && !scripted;
+    // Redrawing the countdown is only useful on a terminal
+    let quiet = args.quiet || scripted || !stdout().is_terminal();
+    let display = Display::new(!quiet, scripted);
+
+    say!(display, "--- Rust Pomodoro Timer ---");
+    if prompt {
+        println!();
+    }
+
+    // A session left running by a timer that didn't get to quit, e.g. when
+    // the computer lost power, can be taken up again. Either way the file is
+    // replaced once this timer gets going, which without prompts means
+    // without asking.
+    let state_path = history::state_path();
+    let resumed = match state_path.as_deref().and_then(recovery::left_behind) {
+        Some(checkpoint) if !prompt => {
+            eprintln!(
+                "warning: not resuming {} left behind by a timer that didn't quit, \
+                 start without --no-prompt, --json or --status-line to be asked",
+                checkpoint.session.label()
+            );
+            None
+        }
+        left_behind => left_behind.filter(confirm_resume),
+    };
+    // Taking it up skips the other questions, it's meant to get back to work
+    let ask = prompt && resumed.is_none();
+
+    let profile = match selected_profile {
+        None if ask && !config.profiles.is_empty() => prompt_profile(&config),
+        selected => selected,
+    };
+    let mut settings = settings::resolve(&args, &config, profile, |name| env::var(name).ok());
+    if ask {
+        if args.work.is_none() {
+            settings.work = prompt_duration("work", settings.work);
+        }
+        if args.break_.is_none() {
+            settings.break_ = prompt_duration("break", settings.break_);
         }
     }
+    // Flipped by the mute key, starting out as --mute or the config says
+    let muted = Arc::new(AtomicBool::new(settings.muted));
+    let notifier = Notifier::new(&settings, !scripted, muted.clone());
+    let timer_config = settings.timer_config(&args).unwrap_or_else(|err| fail(err));
+    let countdown_format = CountdownFormat::parse(&settings.format)
+        .unwrap_or_else(|err| fail(err))
+        .with_clock(settings.clock);
+    let messages = settings
+        .messages(&timer_config)
+        .unwrap_or_else(|err| fail(err));
+    let clock = settings.clock;
+    let amounts = Amounts {
+        extend: settings.extend,
+        snooze: settings.snooze,
+    };
+    let icons = settings.icons.icons(|name| env::var(name).ok());
+    // Prompting for the task holds up each work session, which is left out
+    // of runs that are meant to go on unattended
+    let ask_task = settings.ask_task && prompt && !args.tui && !args.auto_start;
+    let once = timer_config.once();
+    let cycles = timer_config.cycles();
+    // The settings part of the help block, kept up to date by the timer
+    // thread when the lengths change
+    let help = Arc::new(Mutex::new(settings_help(&timer_config, settings.muted)));
+    let timer_help = help.clone();
+    // Flipped by the toggle key, read by whichever frontend draws the time
+    let show_elapsed = Arc::new(AtomicBool::new(settings.show_elapsed));
+
+    // Everything from here on is drawn by the full-screen view instead, and
+    // the terminal is put back once the timer is done, or by the panic hook
+    // that ratatui installs
+    let tui = if args.tui {
+        if !stdout().is_terminal() {
+            eprintln!("error: --tui needs a terminal");
+            process::exit(1);
+        }
+        let terminal = ratatui::try_init().unwrap_or_else(|err| {
+            eprintln!("error: could not set up the terminal: {}", err);
+            process::exit(1);
+        });
+        Some(Arc::new(Mutex::new(Tui::new(
+            terminal,
+            key_bindings.clone(),
+            show_elapsed.clone(),
+            muted.clone(),
+            timer_config.strict(),
+        ))))
+    } else {
+        None
+    };
+    let full_screen = tui.is_some();
+    // https://no-color.org: any non-empty NO_COLOR turns colors off
+    let colors = !args.no_color
+        && !scripted
+        && stdout().is_terminal()
+        && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty());
+    // The title is set with escape codes, which only a terminal understands
+    let title = (settings.title && !scripted && stdout().is_terminal()).then(TerminalTitle::new);
+    let titled = title.is_some();
+    // Flashing is for someone looking at the scrolling output
+    let flash = (!args.no_flash && !quiet && !full_screen)
+        .then(|| Flash::new(display, colors.then_some(settings.colors)));
+    let flashing = flash.is_some();
+    // Set while quitting would throw away a work session, never with
+    // --no-confirm
+    let quit_at_risk = Arc::new(AtomicBool::new(false));
+    let quit_guard = (!args.no_confirm).then(|| QuitGuard::new(quit_at_risk.clone()));
+    let display = if full_screen {
+        Display::hidden()
+    } else {
+        display
+    };
 
+    // Every command reaches the timer through this one channel, and the run
+    // ends only when the timer itself finishes, after a Quit or once every
+    // sender has gone away
     let (sender, receiver) = mpsc::channel::<TimerCommand>();
-    let running = Arc::new(AtomicBool::new(true));
-    let running_clone = running.clone();
+    let timer_keys = key_bindings.clone();
+    let desktop = settings.notify.then(|| {
+        DesktopNotifier::new(
+            display,
+            settings.notify_urgency,
+            !timer_config.auto_start_breaks(),
+            sender.clone(),
+            muted.clone(),
+        )
+        .with_messages(messages.clone())
+    });
+
+    // Ctrl+C quits through the channel like 'q' does, so the timer can wrap
+    // up and print a summary. If it seems stuck, a second one exits at once.
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let ctrlc_sender = sender.clone();
+    let ctrlc_interrupted = interrupted.clone();
+    let mut first_interrupt: Option<Instant> = None;
+    let handler = ctrlc::set_handler(move || {
+        if first_interrupt.is_some_and(|at| at.elapsed() < FORCE_QUIT_WINDOW) {
+            if full_screen {
+                ratatui::restore();
+            }
+            if titled {
+                title::restore();
+            }
+            if flashing {
+                flash::reset();
+            }
+            terminal::restore();
+            println!();
+            process::exit(EXIT_INTERRUPTED);
+        }
+        first_interrupt = Some(Instant::now());
+        ctrlc_interrupted.store(true, Ordering::SeqCst);
+        let _ = ctrlc_sender.send(TimerCommand::Quit);
+    });
+    if let Err(err) = handler {
+        eprintln!("warning: could not handle Ctrl+C: {}", err);
+    }
+
+    // Today's earlier sessions count towards the time focused today and the
+    // goal, and a history that can't be read just leaves them out
+    let today = Local::now().date_naive();
+    let store = history::open(settings.history).unwrap_or_else(|err| {
+        eprintln!("warning: {}", err);
+        None
+    });
+    let recorded = store
+        .as_ref()
+        .map_or(Ok(Vec::new()), |store| store.records(DateRange::day(today)))
+        .unwrap_or_else(|err| {
+            eprintln!("warning: {}", err);
+            Vec::new()
+        });
+    let recorded_today = history::focused(&recorded);
+
+    // Keys work without Enter when stdin is a terminal, while piped input is
+    // still read a line at a time
+    let keypresses = !full_screen && terminal::enter_cbreak();
 
-    // Timer thread
+    let notes = Notes::default();
+    let timer_notes = notes.clone();
+
+    // Timer thread, which measures time and feeds it to the timer. What the
+    // timer does is reported by its listeners.
+    let console = Console::new(
+        display,
+        key_bindings.clone(),
+        countdown_format,
+        clock,
+        CountdownStyle {
+            shown: !quiet,
+            bar: !args.no_bar,
+            big: args.big,
+            elapsed: show_elapsed.clone(),
+            today: FocusedToday::new(today, recorded_today),
+            muted: muted.clone(),
+            spinner: keypresses,
+        },
+        colors.then_some(settings.colors),
+        &timer_config,
+    )
+    .with_messages(messages)
+    .with_icons(icons)
+    .with_command_prefix(if keypresses {
+        COMMAND_KEY.to_string()
+    } else {
+        String::new()
+    })
+    .with_tasks(ask_task.then(|| notes.clone()));
+    // Suggestions are printed below the break's header, so not in the
+    // full-screen view
+    let suggestions = (!args.no_suggestions && !full_screen).then(|| Suggestions::new(display));
+    let status_line = args
+        .status_line
+        .then(|| StatusLine::new(&timer_config, args.on_change));
+    let goal = Goal::new(
+        settings.goal,
+        history::pomodoros(&recorded),
+        display,
+        !scripted,
+        muted.clone(),
+    );
+    let timer_goal = goal.clone();
+    // Every session that ends goes into the history
+    let (history_log, history_writer) = match store {
+        Some(store) => {
+            let (log, writer) = HistoryLog::new(store, notes.clone(), display);
+            (Some(log), Some(writer))
+        }
+        None => (None, None),
+    };
+    let state_file = state_path.map(StateFile::new);
+    let timer_tui = tui.clone();
     let timer_thread = thread::spawn(move || {
-        let mut current_state = TimerState::Work;
-        let mut session_count = 0;
-
-        while running_clone.load(Ordering::SeqCst) {
-            let (duration_minutes, session_type_name) = match current_state {
-                TimerState::Work => (work_minutes, "Work"),
-                TimerState::Break => (break_minutes, "Break"),
-                TimerState::Paused | TimerState::Stopped => {
-                    thread::sleep(Duration::from_millis(100)); // Sleep while paused/stopped
-                    continue;
+        let mut timer = PomodoroTimer::new(timer_config);
+        if let Some(checkpoint) = resumed {
+            timer.resume(checkpoint);
+        }
+        match timer_tui {
+            Some(tui) => timer.subscribe(move |event| {
+                if let Ok(mut tui) = tui.lock() {
+                    tui.on_event(event);
                 }
-            };
+            }),
+            None => {
+                let mut console = console;
+                timer.subscribe(move |event| console.on_event(event));
+            }
+        }
+        if let Some(mut suggestions) = suggestions {
+            timer.subscribe(move |event| suggestions.on_event(event));
+        }
+        let mut notes = timer_notes.clone();
+        timer.subscribe(move |event| notes.on_event(event));
+        let mut goal = timer_goal.clone();
+        timer.subscribe(move |event| goal.on_event(event));
+        if let Some(mut log) = history_log {
+            timer.subscribe(move |event| log.on_event(event));
+        }
+        if json {
+            let mut status = Status::default();
+            timer.subscribe(move |event| status.on_event(event));
+        }
+        if let Some(mut status_line) = status_line {
+            timer.subscribe(move |event| status_line.on_event(event));
+        }
+        if let Some(mut log) = event_log {
+            // Reported once, a file that can't be written fails every time
+            let mut warned = false;
+            timer.subscribe(move |event| {
+                if let Err(err) = log.on_event(event)
+                    && !warned
+                {
+                    warned = true;
+                    say!(display, "warning: {}", err);
+                }
+            });
+        }
+        if let Some(mut guard) = quit_guard {
+            timer.subscribe(move |event| guard.on_event(event));
+        }
+        if let Some(mut flash) = flash {
+            timer.subscribe(move |event| flash.on_event(event));
+        }
+        if let Some(mut desktop) = desktop {
+            timer.subscribe(move |event| desktop.on_event(event));
+        }
+        if let Some(mut title) = title {
+            timer.subscribe(move |event| title.on_event(event));
+        }
+        timer.subscribe(move |event| notifier.on_event(event));
+        let replies = Replies {
+            display,
+            keys: &timer_keys,
+            show_elapsed: &show_elapsed,
+            clock,
+            json,
+            help: &timer_help,
+            goal: &timer_goal,
+            tasks: ask_task.then_some(&timer_notes),
+            muted: &muted,
+        };
+        run_timer(&SystemClock, timer, receiver, replies, state_file)
+    });
 
-            let start_time = Instant::now();
-            let session_duration = Duration::from_secs(duration_minutes * 60);
-            let mut elapsed_time = Duration::new(0, 0);
+    // Input handling thread, left detached so that main() can return as soon
+    // as the timer stops even if it's still blocked reading a line. main()
+    // keeps its own sender so that stdin reaching EOF doesn't stop the timer.
+    let input_sender = sender.clone();
+    let input_notes = notes.clone();
+    match tui {
+        Some(tui) => {
+            let interrupted = interrupted.clone();
+            thread::spawn(move || {
+                tui::read_keys(
+                    input_sender,
+                    key_bindings,
+                    amounts,
+                    tui,
+                    interrupted,
+                    quit_at_risk,
+                )
+            });
+        }
+        None if keypresses => {
+            thread::spawn(move || {
+                read_keypresses(
+                    input_sender,
+                    key_bindings,
+                    amounts,
+                    display,
+                    help,
+                    input_notes,
+                    quit_at_risk,
+                )
+            });
+        }
+        None => {
+            thread::spawn(move || {
+                read_commands(
+                    input_sender,
+                    key_bindings,
+                    amounts,
+                    display,
+                    help,
+                    input_notes,
+                    quit_at_risk,
+                )
+            });
+        }
+    }
 
-            println!(
-                "\n--- {} Session {} Started ---",
-                session_type_name,
-                session_count + 1
-            );
-            Command::new("paplay")
-                .arg("/usr/share/sounds/freedesktop/stereo/complete.oga")
-                .spawn()
-                .unwrap();
-            print!("\x07");
-            println!("Press 'p' to pause, 's' to skip, 'q' to quit.");
-
-            while elapsed_time < session_duration {
-                let remaining = session_duration - elapsed_time;
-                let minutes = remaining.as_secs() / 60;
-                let seconds = remaining.as_secs() % 60;
-
-                print!("\rTime remaining: {:02}:{:02}", minutes, seconds);
-                stdout().flush().unwrap();
-
-                match receiver.try_recv() {
-                    Ok(TimerCommand::Pause) => {
-                        println!("\nTimer Paused. Press 'r' to resume.");
-                        current_state = TimerState::Paused;
-                        break;
-                    }
-                    Ok(TimerCommand::Skip) => {
-                        println!("\nSkipping current session.");
-                        break;
-                    }
-                    Ok(TimerCommand::Quit) => {
-                        running_clone.store(false, Ordering::SeqCst);
-                        break;
-                    }
-                    Ok(TimerCommand::Resume) => {
-                        // This should not happen if state is Paused, but good to handle
-                    }
-                    Err(mpsc::TryRecvError::Empty) => {}
-                    Err(mpsc::TryRecvError::Disconnected) => {
-                        running_clone.store(false, Ordering::SeqCst);
-                        break;
-                    }
-                }
+    // The panic message itself has already been printed by the panic hook
+    let joined = timer_thread.join();
+    // The timer's history log went with it, and its last rows are finished
+    // off before anything exits
+    if let Some(writer) = history_writer {
+        let _ = writer.join();
+    }
+    terminal::restore();
+    if flashing {
+        flash::reset();
+    }
+    let display = if full_screen {
+        ratatui::restore();
+        Display::new(false, false)
+    } else {
+        display
+    };
+    let Ok(counts) = joined else {
+        eprintln!("error: the timer stopped unexpectedly");
+        process::exit(1);
+    };
+    // --once ends quietly, the goodbye is enough
+    if !once {
+        say!(display, "{}", console::summary(&counts, cycles, icons));
+        if let Some(line) = goal_summary(&goal.progress(), clock) {
+            say!(display, "{}", line);
+        }
+    }
+    let notes = notes.all();
+    if !notes.is_empty() {
+        say!(display, "Notes:");
+        for note in notes {
+            say!(display, "  {}: {}", note.session, note.text);
+        }
+    }
+    if interrupted.load(Ordering::SeqCst) {
+        say!(display, "Pomodoro timer interrupted.");
+        process::exit(EXIT_INTERRUPTED);
+    }
+    say!(display, "Pomodoro timer finished. Goodbye!");
 
-                if let TimerState::Paused = current_state {
-                    break;
-                }
+    if once && counts.completed_pomodoros == 0 {
+        process::exit(EXIT_SESSION_ABANDONED);
+    }
+}
+
+// What run_timer() needs for the replies it reports itself, since the
+// timer's listeners never hear about them
+struct Replies<'a> {
+    display: Display,
+    keys: &'a KeyBindings,
+    show_elapsed: &'a AtomicBool,
+    clock: ClockStyle,
+    // Status reports go into the event stream as well
+    json: bool,
+    // The settings part of the help block, see settings_help()
+    help: &'a Mutex<String>,
+    // Shared with the sound, the notifications and the countdown
+    muted: &'a AtomicBool,
+    goal: &'a Goal,
+    // Where the task asked for before each work session goes, None when
+    // it isn't asked for
+    tasks: Option<&'a Notes>,
+}
+
+// "Work 25m, break 5m, long break 15m after every 4 pomodoros. Sound is on."
+fn settings_help(plan: &PomodoroConfig, muted: bool) -> String {
+    format!(
+        "Work {}, break {}, long break {} after every {}. Sound is {}.",
+        format_duration(plan.work()),
+        format_duration(plan.break_()),
+        format_duration(plan.long_break()),
+        count(plan.sessions_until_long_break(), "pomodoro", "pomodoros"),
+        if muted { "muted" } else { "on" }
+    )
+}
+
+// "5/8 pomodoros today — 3 to go, ~1h25m at current settings", counting
+// the breaks in between but not long breaks
+fn report_goal(timer: &PomodoroTimer, replies: &Replies) {
+    let progress = replies.goal.progress();
+    let done = progress.done;
+    let Some(target) = progress.target else {
+        say!(
+            replies.display,
+            "{} completed today. Set a daily goal with --goal N or `goal` in the config file.",
+            count(done, "pomodoro", "pomodoros")
+        );
+        return;
+    };
+    if done >= target {
+        let at = progress.reached.map_or(String::new(), |time| {
+            format!(" at {}", replies.clock.format(time))
+        });
+        say!(
+            replies.display,
+            "{}/{} today \u{2014} goal reached{}.",
+            done,
+            count(target, "pomodoro", "pomodoros"),
+            at
+        );
+        return;
+    }
+    let to_go = target - done;
+    let plan = timer.config();
+    let times = |duration: Duration, times: u64| {
+        duration.saturating_mul(u32::try_from(times).unwrap_or(u32::MAX))
+    };
+    let mut left = times(plan.work(), to_go) + times(plan.break_(), to_go - 1);
+    // The running pomodoro is already part of the way there
+    if timer.state() == TimerState::Work && matches!(timer.phase(), Phase::Running | Phase::Paused)
+    {
+        left = left.saturating_sub(timer.elapsed());
+    }
+    say!(
+        replies.display,
+        "{}/{} today \u{2014} {} to go, ~{} at current settings.",
+        done,
+        count(target, "pomodoro", "pomodoros"),
+        to_go,
+        format_hours_minutes(left)
+    );
+}
 
-                thread::sleep(Duration::from_secs(1));
-                elapsed_time = Instant::now().duration_since(start_time);
+// The goal's line after the totals at the end, None without a goal
+fn goal_summary(progress: &goal::Progress, clock: ClockStyle) -> Option<String> {
+    let target = progress.target?;
+    Some(match progress.reached {
+        Some(time) => format!(
+            "Daily goal of {} reached at {}.",
+            count(target, "pomodoro", "pomodoros"),
+            clock.format(time)
+        ),
+        None if progress.done >= target => {
+            format!(
+                "Daily goal of {} already reached.",
+                count(target, "pomodoro", "pomodoros")
+            )
+        }
+        None => format!(
+            "Daily goal: {} of {}, {} to go.",
+            progress.done,
+            count(target, "pomodoro", "pomodoros"),
+            target - progress.done
+        ),
+    })
+}
+
+// Where the timer is and the totals so far, e.g.
+// "Work Session 2 is running, 12:34 remaining, ends at 14:05."
+fn report_status(timer: &PomodoroTimer, replies: &Replies) {
+    let status = Status::of(timer);
+    if replies.json {
+        status.emit("status");
+    }
+    let Some(details) = &status.details else {
+        return;
+    };
+    let session = details.kind.label(status.session);
+    let remaining = format_clock(Duration::from_secs(status.remaining_secs));
+    let now = match (details.phase, details.ends_at) {
+        _ if details.kind == TimerState::Stopped => "The timer is stopped.".to_string(),
+        (Phase::Running, Some(ends_at)) => format!(
+            "{} is running, {} remaining, ends at {}.",
+            session,
+            remaining,
+            replies.clock.format(ends_at.time())
+        ),
+        (Phase::Running, None) => format!("{} is running, {} remaining.", session, remaining),
+        (Phase::Paused, _) => format!("{} is paused, {} remaining.", session, remaining),
+        (Phase::Snoozed, _) => format!(
+            "{} is snoozed, starting in {}.",
+            session,
+            format_clock(details.snooze_remaining)
+        ),
+        (Phase::Ready | Phase::Waiting, _) => format!("{} is waiting to start.", session),
+        (Phase::Finished, _) => "The timer has finished.".to_string(),
+    };
+    say!(
+        replies.display,
+        "{}\n{} completed this run, {} min focused, {} min paused, {} min idle.",
+        now,
+        count(status.completed_pomodoros, "pomodoro", "pomodoros"),
+        details.focused_secs / 60,
+        details.paused_secs / 60,
+        details.idle_secs / 60
+    );
+}
+
+// Run `timer` until it finishes, returning the run's totals. The running
+// session is kept in `state_file` as it goes, which is removed once the
+// timer has finished.
+fn run_timer<C: Clock>(
+    clock: &C,
+    mut timer: PomodoroTimer,
+    receiver: mpsc::Receiver<TimerCommand>,
+    replies: Replies,
+    state_file: Option<StateFile>,
+) -> Counts {
+    let display = replies.display;
+    let mut frontend = TimerFrontend {
+        replies,
+        task_asked: false,
+        state_file,
+        warned: false,
+    };
+    driver::run(clock, &mut timer, &receiver, &mut frontend);
+    if let Some(state_file) = frontend.state_file
+        && let Err(err) = state_file.remove()
+    {
+        say!(display, "warning: {}", err);
+    }
+    say!(display, "Timer thread stopped.");
+    timer.counts()
+}
+
+// What the timer thread reports and keeps track of itself, as run_timer()
+// goes round
+struct TimerFrontend<'a> {
+    replies: Replies<'a>,
+    // The task was asked for the work session that's about to begin
+    task_asked: bool,
+    state_file: Option<StateFile>,
+    // Reported once, a state file that can't be written fails every time
+    warned: bool,
+}
+
+impl Frontend for TimerFrontend<'_> {
+    // The session waits for the task, which the input thread answers with a
+    // Start once it has been typed
+    fn hold(&mut self, timer: &PomodoroTimer) -> bool {
+        match self.replies.tasks {
+            Some(tasks)
+                if !self.task_asked
+                    && timer.state() == TimerState::Work
+                    && !timer.in_overtime() =>
+            {
+                self.task_asked = true;
+                ask_task(self.replies.display, tasks);
+                true
             }
+            _ => {
+                self.task_asked = false;
+                false
+            }
+        }
+    }
 
-            if let TimerState::Paused = current_state {
-                continue; // Loop again and wait for resume command
+    fn replied(
+        &mut self,
+        timer: &PomodoroTimer,
+        command: TimerCommand,
+        phase: Phase,
+        reply: Reply,
+    ) {
+        let replies = &self.replies;
+        let Replies {
+            display,
+            keys: timer_keys,
+            show_elapsed,
+            ..
+        } = *replies;
+        match reply {
+            Reply::NextLength(duration) => say!(
+                display,
+                "The next session will last {}.",
+                format_duration(duration)
+            ),
+            Reply::Extended(added) => say!(
+                display,
+                "Added {} to {}, {} remaining.",
+                format_duration(added),
+                timer.session().label(),
+                format_clock(timer.remaining())
+            ),
+            Reply::Nudged => say!(
+                display,
+                "{} now ends at {}, {} remaining.",
+                timer.session().label(),
+                replies.clock.format(end_time(timer.remaining())),
+                format_clock(timer.remaining())
+            ),
+            Reply::Overtime(duration) => say!(
+                display,
+                "Working {} more before the break.",
+                format_duration(duration)
+            ),
+            Reply::LengthSet(kind, length) => {
+                if let Ok(mut help) = replies.help.lock() {
+                    *help = settings_help(timer.config(), replies.muted.load(Ordering::SeqCst));
+                }
+                say!(
+                    display,
+                    "{} will last {} from the next one on.",
+                    match kind {
+                        TimerState::Work => "Work sessions",
+                        TimerState::Break => "Breaks",
+                        _ => "Long breaks",
+                    },
+                    format_duration(length)
+                )
+            }
+            Reply::SaveConfig => save_settings(timer.config(), display),
+            // Shown from the next tick
+            Reply::ToggleElapsed => {
+                show_elapsed.fetch_xor(true, Ordering::SeqCst);
+            }
+            Reply::ToggleMute => {
+                let muted = !replies.muted.fetch_xor(true, Ordering::SeqCst);
+                if let Ok(mut help) = replies.help.lock() {
+                    *help = settings_help(timer.config(), muted);
+                }
+                say!(
+                    display,
+                    "Sound {}.",
+                    if muted { "muted" } else { "unmuted" }
+                );
             }
+            // Only a session that was running can be brought back
+            Reply::Ended(end)
+                if end.outcome == Outcome::Skipped && timer.phase() != Phase::Finished =>
+            {
+                say!(
+                    display,
+                    "Press '{}' within {} to undo the skip.",
+                    timer_keys.key(Action::Undo),
+                    format_duration(UNDO_WINDOW)
+                )
+            }
+            Reply::Strict => say!(
+                display,
+                "strict mode: the pomodoro must complete or be abandoned"
+            ),
+            Reply::StrictToggled(true) => say!(
+                display,
+                "Strict mode on, work sessions can only be completed or abandoned."
+            ),
+            Reply::StrictToggled(false) => say!(display, "Strict mode off."),
+            Reply::Status => report_status(timer, replies),
+            Reply::Goal => report_goal(timer, replies),
+            Reply::Ignored => match phase {
+                _ if command == TimerCommand::Restart => {
+                    say!(display, "Only a running session can be restarted.")
+                }
+                _ if command == TimerCommand::LongBreak => {
+                    say!(display, "Already on a long break.")
+                }
+                _ if matches!(command, TimerCommand::Snooze(_)) => say!(
+                    display,
+                    "Only the next session can be snoozed, before it starts or within {} of \
+                     starting on its own.",
+                    format_duration(SNOOZE_WINDOW)
+                ),
+                _ if matches!(command, TimerCommand::Extend(_)) => {
+                    say!(display, "Only a running session can be extended.")
+                }
+                _ if matches!(
+                    command,
+                    TimerCommand::AddMinute | TimerCommand::RemoveMinute
+                ) =>
+                {
+                    say!(
+                        display,
+                        "Only a running session can be made longer or shorter."
+                    )
+                }
+                _ if matches!(command, TimerCommand::Overtime(_)) => say!(
+                    display,
+                    "Overtime can only be added before the break is over."
+                ),
+                _ if command == TimerCommand::Undo => say!(
+                    display,
+                    "There's no skip to undo, a skip can only be undone within {} and \
+                     before anything else is done.",
+                    format_duration(UNDO_WINDOW)
+                ),
+                _ if command == TimerCommand::SkipBreak => {
+                    say!(display, "Only a break can be skipped to start working.")
+                }
+                _ if command == TimerCommand::TakeBreak => say!(
+                    display,
+                    "A break can only be taken in place of a session that's waiting to start."
+                ),
+                _ if timer.state() == TimerState::Stopped => say!(
+                    display,
+                    "Timer stopped, press Enter to start work, '{}' to take a break or '{}' \
+                     to quit.",
+                    timer_keys.key(Action::TakeBreak),
+                    timer_keys.key(Action::Quit)
+                ),
+                Phase::Waiting => say!(
+                    display,
+                    "Not started yet, press Enter to start or '{}' to quit.",
+                    timer_keys.key(Action::Quit)
+                ),
+                Phase::Snoozed => say!(
+                    display,
+                    "Snoozing, press Enter to start {} now or '{}' to quit.",
+                    timer.session().label(),
+                    timer_keys.key(Action::Quit)
+                ),
+                Phase::Paused if command == TimerCommand::Pause => say!(
+                    display,
+                    "Already paused. {}",
+                    timer_keys.hint(&[Action::Resume])
+                ),
+                Phase::Paused => {
+                    say!(display, "Paused. {}", timer_keys.hint(&[Action::Resume]))
+                }
+                _ => say!(
+                    display,
+                    "Already running. {}",
+                    timer_keys.hint(&[Action::Pause])
+                ),
+            },
+            _ => {}
+        }
+    }
 
-            if running_clone.load(Ordering::SeqCst) && elapsed_time >= session_duration {
-                play_beep();
-                println!("\n--- {} Session Finished! ---", session_type_name);
+    fn stepped(&mut self, timer: &PomodoroTimer, phase: Phase, now: Instant) {
+        let display = self.replies.display;
+        let timer_keys = self.replies.keys;
+        // In manual mode, after a skipped work session or once stopped, the
+        // next session waits for the user. A pending break can be skipped to
+        // go straight back to work.
+        if timer.phase() == Phase::Waiting && phase != Phase::Waiting {
+            let session = timer.session();
+            match session.state {
+                TimerState::Stopped => {}
+                TimerState::Work => {
+                    say!(display, "\nPress Enter to start {}.", session.label())
+                }
+                TimerState::Break | TimerState::LongBreak => say!(
+                    display,
+                    "\nPress Enter to start {}, or '{}' to skip it and start working.",
+                    session.label(),
+                    timer_keys.key(Action::Skip)
+                ),
             }
+        }
 
-            // Switch states or stop if quit
-            if running_clone.load(Ordering::SeqCst) {
-                match current_state {
-                    TimerState::Work => {
-                        current_state = TimerState::Break;
-                        session_count += 1;
-                    }
-                    TimerState::Break => {
-                        current_state = TimerState::Work;
-                        session_count += 1;
-                    }
-                    _ => {} // Should not happen here due to continue
+        if let Some(state_file) = &mut self.state_file
+            && let Err(err) = state_file.update(timer, now)
+            && !self.warned
+        {
+            self.warned = true;
+            say!(display, "warning: {}", err);
+        }
+    }
+}
+
+// "work 50", "break 10" or "long_break 20", which change how long sessions
+// of that kind last from the next one on. None for anything else.
+fn length_command(input: &str) -> Option<Result<TimerCommand, String>> {
+    let (word, argument) = input
+        .split_once(char::is_whitespace)
+        .map_or((input, ""), |(word, rest)| (word, rest.trim()));
+    let command: fn(Duration) -> TimerCommand = match word {
+        "work" => TimerCommand::SetWork,
+        "break" => TimerCommand::SetBreak,
+        "long_break" => TimerCommand::SetLongBreak,
+        _ => return None,
+    };
+    Some(
+        parse_duration(argument)
+            .map(command)
+            .map_err(|err| format!("Usage: {} DURATION, e.g. '{} 50' ({})", word, word, err)),
+    )
+}
+
+// Ask for a note and read it with `read_line`, with the countdown held so
+// it doesn't draw over what's being typed. None for an empty line, or when
+// there's no session for it yet.
+fn read_note(
+    display: Display,
+    notes: &Notes,
+    read_line: impl FnOnce() -> Option<String>,
+) -> Option<String> {
+    let Some(session) = notes.target() else {
+        say!(display, "There's no session to add a note to yet.");
+        return None;
+    };
+    display.hold_countdown(true);
+    say!(
+        display,
+        "Note for {}, Enter to save it or leave it empty to cancel:",
+        session
+    );
+    let line = read_line();
+    display.hold_countdown(false);
+    line.filter(|line| !line.is_empty())
+}
+
+// "Task for this pomodoro:", with the last task offered for an empty line
+fn ask_task(display: Display, tasks: &Notes) {
+    tasks.ask_task();
+    match tasks.task() {
+        Some(task) => say!(
+            display,
+            "\nTask for this pomodoro (Enter to keep '{}'):",
+            task
+        ),
+        None => say!(display, "\nTask for this pomodoro:"),
+    }
+}
+
+fn save_note(display: Display, notes: &Notes, text: &str) {
+    match notes.add(text) {
+        Some(session) => say!(display, "Noted for {}.", session),
+        None => say!(display, "There's no session to add a note to yet."),
+    }
+}
+
+fn read_commands(
+    sender: mpsc::Sender<TimerCommand>,
+    key_bindings: KeyBindings,
+    amounts: Amounts,
+    display: Display,
+    help: Arc<Mutex<String>>,
+    notes: Notes,
+    quit_at_risk: Arc<AtomicBool>,
+) {
+    // The quit or strict key was just pressed and the question asked, with
+    // the command it's about
+    let mut confirming: Option<TimerCommand> = None;
+    while let Some(line) = read_input_line() {
+        display.line_entered();
+        let input = line.as_str();
+        // The line the timer thread asked for, which lets the work session
+        // begin
+        if notes.asking_task() {
+            notes.answer_task(input);
+            if sender.send(TimerCommand::Start).is_err() {
+                return;
+            }
+            continue;
+        }
+        // Anything but yes or no is taken as a command, and pressing the
+        // same key again goes ahead
+        let asked = confirming.take();
+        match asked.zip(confirm::answer(input)) {
+            Some((command, true)) => {
+                if sender.send(command).is_err() || command == TimerCommand::Quit {
+                    return;
                 }
+                continue;
             }
+            Some((command, false)) => {
+                say!(display, "{}", confirm::declined(command));
+                continue;
+            }
+            None => {}
         }
-        println!("Timer thread stopped.");
-    });
+        // Commands like "d 15" carry an argument after the key
+        let (key, argument) = input
+            .split_once(char::is_whitespace)
+            .map_or((input, ""), |(key, rest)| (key, rest.trim()));
+        let command = match key_bindings.lookup(key) {
+            // Just pressing Enter starts the next session
+            _ if input.is_empty() => TimerCommand::Start,
+            Some(Action::OverrideNext) => match parse_duration(argument) {
+                Ok(duration) => TimerCommand::OverrideNext(duration),
+                Err(err) => {
+                    say!(
+                        display,
+                        "Usage: {} DURATION, e.g. '{} 15' ({})",
+                        key_bindings.key(Action::OverrideNext),
+                        key_bindings.key(Action::OverrideNext),
+                        err
+                    );
+                    continue;
+                }
+            },
+            // "e" on its own adds the usual amount, "e 10" ten minutes, and
+            // the same goes for snoozing with "z" and "z 3"
+            Some(Action::Extend) if argument.is_empty() => TimerCommand::Extend(amounts.extend),
+            Some(Action::Snooze) if argument.is_empty() => TimerCommand::Snooze(amounts.snooze),
+            Some(action @ (Action::Extend | Action::Snooze)) => match parse_duration(argument) {
+                Ok(duration) if action == Action::Extend => TimerCommand::Extend(duration),
+                Ok(duration) => TimerCommand::Snooze(duration),
+                Err(err) => {
+                    say!(
+                        display,
+                        "Usage: {} [DURATION], e.g. '{} 10' ({})",
+                        key_bindings.key(action),
+                        key_bindings.key(action),
+                        err
+                    );
+                    continue;
+                }
+            },
+            Some(Action::Help) => {
+                print_help(display, &key_bindings, &help);
+                continue;
+            }
+            // "n" on its own takes the note from the next line, "n text"
+            // straight away
+            Some(Action::Note) => {
+                let text = match argument {
+                    "" => read_note(display, &notes, read_input_line),
+                    text => Some(text.to_string()),
+                };
+                if let Some(text) = text {
+                    save_note(display, &notes, &text);
+                }
+                continue;
+            }
+            Some(Action::Quit)
+                if asked != Some(TimerCommand::Quit) && quit_at_risk.load(Ordering::SeqCst) =>
+            {
+                confirming = Some(TimerCommand::Quit);
+                say!(display, "{}", confirm::question(TimerCommand::Quit));
+                continue;
+            }
+            Some(Action::Strict) if asked != Some(TimerCommand::ToggleStrict) => {
+                confirming = Some(TimerCommand::ToggleStrict);
+                say!(display, "{}", confirm::question(TimerCommand::ToggleStrict));
+                continue;
+            }
+            Some(action) => match action.command() {
+                Some(command) => command,
+                None => continue,
+            },
+            None => match length_command(input) {
+                Some(Ok(command)) => command,
+                Some(Err(usage)) => {
+                    say!(display, "{}", usage);
+                    continue;
+                }
+                None => {
+                    say!(display, "Unknown command. {}", key_bindings.hint_all());
+                    continue;
+                }
+            },
+        };
+
+        // Sending only fails once the timer is done, whether it stopped
+        // because of a command or on its own, e.g. at the end of --cycles
+        if sender.send(command).is_err() || command == TimerCommand::Quit {
+            return;
+        }
+    }
+
+    // stdin was closed, e.g. `< /dev/null` or the end of a pipe. The timer
+    // keeps going on its own since main() still holds a sender.
+    say!(
+        display,
+        "Input closed, commands are no longer available. Press Ctrl+C to stop the timer."
+    );
+}
 
-    // Input handling thread
-    for line in stdin().lines() {
-        let input = line.unwrap().trim().to_lowercase();
-        match input.as_str() {
-            "p" => {
-                sender.send(TimerCommand::Pause).unwrap();
+// Like read_commands, for a terminal in cbreak mode where each key takes
+// effect as soon as it's pressed. A key binding longer than one character
+// is collected until it's complete, and the key for the next session's
+// length is followed by the length and Enter, as in "d15". Commands that
+// don't have a key, like "work 50", are typed after COMMAND_KEY.
+fn read_keypresses(
+    sender: mpsc::Sender<TimerCommand>,
+    key_bindings: KeyBindings,
+    amounts: Amounts,
+    display: Display,
+    help: Arc<Mutex<String>>,
+    notes: Notes,
+    quit_at_risk: Arc<AtomicBool>,
+) {
+    let mut input = stdin().lock();
+    let mut typed = String::new();
+    // The length typed so far after the OverrideNext key
+    let mut length: Option<String> = None;
+    // The command typed so far after COMMAND_KEY
+    let mut line: Option<String> = None;
+    // Arrow and function keys arrive as escape sequences, which are dropped
+    let mut after_escape = false;
+    let mut in_sequence = false;
+    // As in read_commands()
+    let mut confirming: Option<TimerCommand> = None;
+
+    while let Some(key) = terminal::read_key(&mut input) {
+        if in_sequence {
+            in_sequence = !('\x40'..='\x7e').contains(&key);
+            continue;
+        }
+        if after_escape {
+            after_escape = false;
+            if key == '[' || key == 'O' {
+                in_sequence = true;
+                continue;
             }
-            "r" => {
-                // If paused, try to resume
-                if sender.send(TimerCommand::Resume).is_ok() {
-                    // This command is primarily handled by the main timer loop's state
-                    // transition logic, but sending it allows the timer to 'unblock'
-                    // from a paused state.
+        }
+
+        // The task the timer thread asked for, typed as a whole line with
+        // the terminal's own echo and editing from the first key on
+        if notes.asking_task() && (key == '\n' || key == '\r' || !key.is_control()) {
+            typed.clear();
+            let task = match key {
+                '\n' | '\r' => String::new(),
+                key => {
+                    print!("{}", key);
+                    let _ = stdout().flush();
+                    let rest = terminal::line_mode(|| read_line_from(&mut input));
+                    format!("{}{}", key, rest.unwrap_or_default())
                 }
+            };
+            notes.answer_task(task.trim());
+            if sender.send(TimerCommand::Start).is_err() {
+                return;
             }
-            "s" => {
-                sender.send(TimerCommand::Skip).unwrap();
+            continue;
+        }
+
+        let command = match key {
+            '\n' | '\r' => match (length.take(), line.take()) {
+                // Just pressing Enter starts the next session
+                (None, None) => {
+                    typed.clear();
+                    TimerCommand::Start
+                }
+                (None, Some(line)) => match length_command(line.trim()) {
+                    Some(Ok(command)) => command,
+                    Some(Err(usage)) => {
+                        say!(display, "{}", usage);
+                        continue;
+                    }
+                    None => {
+                        say!(
+                            display,
+                            "Unknown command '{}', expected something like 'work 50', \
+                             'break 10' or 'long_break 20'.",
+                            line.trim()
+                        );
+                        continue;
+                    }
+                },
+                (Some(length), _) => match parse_duration(length.trim()) {
+                    Ok(duration) => TimerCommand::OverrideNext(duration),
+                    Err(err) => {
+                        say!(
+                            display,
+                            "Usage: {} DURATION, e.g. '{}15' and Enter ({})",
+                            key_bindings.key(Action::OverrideNext),
+                            key_bindings.key(Action::OverrideNext),
+                            err
+                        );
+                        continue;
+                    }
+                },
+            },
+            // Backspace
+            '\x7f' | '\x08' => {
+                match length.as_mut().or(line.as_mut()) {
+                    Some(text) => text.pop(),
+                    None => typed.pop(),
+                };
+                continue;
             }
-            "q" => {
-                sender.send(TimerCommand::Quit).unwrap();
-                running.store(false, Ordering::SeqCst);
-                break;
+            // Escape also gives up on a length or command being typed
+            '\x1b' => {
+                after_escape = true;
+                typed.clear();
+                length = None;
+                line = None;
+                continue;
             }
-            _ => {
-                println!(
-                    "Unknown command. Use 'p' to pause, 'r' to resume, 's' to skip, 'q' to quit."
-                );
+            key if key.is_control() => continue,
+            key => {
+                if let Some(text) = length.as_mut().or(line.as_mut()) {
+                    text.push(key);
+                    continue;
+                }
+                // As in read_commands()
+                let asked = confirming.take();
+                match asked.zip(confirm::answer(key.encode_utf8(&mut [0; 4]))) {
+                    Some((command, true)) => {
+                        if sender.send(command).is_err() || command == TimerCommand::Quit {
+                            return;
+                        }
+                        continue;
+                    }
+                    Some((command, false)) => {
+                        say!(display, "{}", confirm::declined(command));
+                        continue;
+                    }
+                    None => {}
+                }
+                if key == COMMAND_KEY
+                    && typed.is_empty()
+                    && key_bindings.lookup(&COMMAND_KEY.to_string()).is_none()
+                {
+                    line = Some(String::new());
+                    say!(
+                        display,
+                        "Type a command and press Enter, e.g. work 50 or break 10."
+                    );
+                    continue;
+                }
+                typed.push(key);
+                match key_bindings.lookup(&typed) {
+                    Some(Action::OverrideNext) => {
+                        typed.clear();
+                        length = Some(String::new());
+                        say!(
+                            display,
+                            "Type the next session's length and press Enter, e.g. 15 or 90s."
+                        );
+                        continue;
+                    }
+                    Some(Action::Extend) => {
+                        typed.clear();
+                        TimerCommand::Extend(amounts.extend)
+                    }
+                    Some(Action::Snooze) => {
+                        typed.clear();
+                        TimerCommand::Snooze(amounts.snooze)
+                    }
+                    Some(Action::Help) => {
+                        typed.clear();
+                        print_help(display, &key_bindings, &help);
+                        continue;
+                    }
+                    // Typed as a whole line, with the terminal's own echo
+                    // and editing
+                    Some(Action::Note) => {
+                        typed.clear();
+                        let text = terminal::line_mode(|| {
+                            read_note(display, &notes, || read_line_from(&mut input))
+                        });
+                        if let Some(text) = text {
+                            save_note(display, &notes, &text);
+                        }
+                        continue;
+                    }
+                    Some(Action::Quit)
+                        if asked != Some(TimerCommand::Quit)
+                            && quit_at_risk.load(Ordering::SeqCst) =>
+                    {
+                        typed.clear();
+                        confirming = Some(TimerCommand::Quit);
+                        say!(display, "{}", confirm::question(TimerCommand::Quit));
+                        continue;
+                    }
+                    Some(Action::Strict) if asked != Some(TimerCommand::ToggleStrict) => {
+                        typed.clear();
+                        confirming = Some(TimerCommand::ToggleStrict);
+                        say!(display, "{}", confirm::question(TimerCommand::ToggleStrict));
+                        continue;
+                    }
+                    Some(action) => {
+                        typed.clear();
+                        match action.command() {
+                            Some(command) => command,
+                            None => continue,
+                        }
+                    }
+                    None if key_bindings.is_prefix(&typed) => continue,
+                    None => {
+                        typed.clear();
+                        say!(display, "Unknown command. {}", key_bindings.hint_all());
+                        continue;
+                    }
+                }
             }
+        };
+
+        if sender.send(command).is_err() || command == TimerCommand::Quit {
+            return;
         }
     }
 
-    timer_thread.join().unwrap();
-    println!("Pomodoro timer finished. Goodbye!");
+    say!(
+        display,
+        "Input closed, commands are no longer available. Press Ctrl+C to stop the timer."
+    );
 }
@@ -0,0 +1,139 @@
+use std::sync::{Arc, Mutex};
+
+use pomodoro_timer::timer::{TimerEvent, TimerState};
+
+// A note typed with the note key, along with the session it's about
+#[derive(Clone, Debug)]
+pub struct Note {
+    // The session's label, e.g. "Work Session 2"
+    pub session: String,
+    pub text: String,
+    // Which of the run's sessions, counting every one started, since
+    // labels repeat after a stop
+    started: u64,
+}
+
+// The notes typed so far. Shared between the input thread, which adds them,
+// and the timer listener, which follows the sessions so each note goes with
+// the right one: the work session that's running, or during a break or
+// while waiting, the one that just finished. Also holds the task asked for
+// with `ask_task`, which the timer thread asks for and the input thread
+// reads.
+#[derive(Clone, Default)]
+pub struct Notes {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    // Sessions started so far
+    started: u64,
+    // The session the next note goes with, as its place among those
+    // started and its label. None before the first one starts.
+    target: Option<(u64, String)>,
+    // Each session's notes, in the order they were first taken
+    notes: Vec<Note>,
+    // What the running or next work session is for, kept for the ones after
+    // it until another is given
+    task: Option<String>,
+    // The next line typed is the task
+    asking: bool,
+}
+
+impl Notes {
+    // Attach `text` to the current session, returning that session's label.
+    // Several notes for one session add up. None when no session has
+    // started yet.
+    pub fn add(&self, text: &str) -> Option<String> {
+        let mut inner = self.inner.lock().ok()?;
+        let (started, session) = inner.target.clone()?;
+        match inner.notes.last_mut() {
+            Some(note) if note.started == started => {
+                note.text.push_str("; ");
+                note.text.push_str(text);
+            }
+            _ => inner.notes.push(Note {
+                session: session.clone(),
+                text: text.to_string(),
+                started,
+            }),
+        }
+        Some(session)
+    }
+
+    // The session the next note goes with
+    pub fn target(&self) -> Option<String> {
+        let inner = self.inner.lock().ok()?;
+        inner.target.as_ref().map(|(_, session)| session.clone())
+    }
+
+    // Whether notes go with the `started`th session of the run, see
+    // started()
+    pub fn taking(&self, started: u64) -> bool {
+        self.inner.lock().is_ok_and(|inner| {
+            inner
+                .target
+                .as_ref()
+                .is_some_and(|(target, _)| *target == started)
+        })
+    }
+
+    // Have the next line typed taken as the task
+    pub fn ask_task(&self) {
+        if let Ok(mut inner) = self.inner.lock() {
+            inner.asking = true;
+        }
+    }
+
+    pub fn asking_task(&self) -> bool {
+        self.inner.lock().is_ok_and(|inner| inner.asking)
+    }
+
+    // The line typed when asked for the task. An empty one keeps the last
+    // task.
+    pub fn answer_task(&self, text: &str) {
+        if let Ok(mut inner) = self.inner.lock() {
+            inner.asking = false;
+            if !text.is_empty() {
+                inner.task = Some(text.to_string());
+            }
+        }
+    }
+
+    pub fn task(&self) -> Option<String> {
+        self.inner.lock().ok()?.task.clone()
+    }
+
+    // The notes for the `started`th session of the run, see started()
+    pub fn note_for(&self, started: u64) -> Option<String> {
+        let inner = self.inner.lock().ok()?;
+        let note = inner.notes.iter().find(|note| note.started == started)?;
+        Some(note.text.clone())
+    }
+
+    // How many sessions have started so far, the running one included
+    pub fn started(&self) -> u64 {
+        self.inner.lock().map_or(0, |inner| inner.started)
+    }
+
+    pub fn all(&self) -> Vec<Note> {
+        self.inner
+            .lock()
+            .map(|inner| inner.notes.clone())
+            .unwrap_or_default()
+    }
+
+    // The timer listener that keeps track of the session notes go with
+    pub fn on_event(&mut self, event: &TimerEvent) {
+        let TimerEvent::SessionStarted { kind, number, .. } = *event else {
+            return;
+        };
+        if let Ok(mut inner) = self.inner.lock() {
+            inner.started += 1;
+            // A break only takes notes when there's no work session before it
+            if kind == TimerState::Work || inner.target.is_none() {
+                inner.target = Some((inner.started, kind.label(number)));
+            }
+        }
+    }
+}
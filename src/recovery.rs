@@ -0,0 +1,240 @@
+// The state file, which keeps the running session so that after a run ends
+// without warning, e.g. when the computer loses power, the next one can take
+// it up again. It's saved whenever the session changes and every
+// SAVE_INTERVAL besides, and removed when the timer quits as it should.
+//
+// It's one JSON object:
+//   version           VERSION, which goes up whenever older timers couldn't
+//                     make sense of the file any more. A session saved in
+//                     any other version isn't offered.
+//   pid               the timer that saved it
+//   saved_at          when, as RFC 3339
+//   kind, session     the session, as in the --log-events file
+//   started_at        when it started, leaving out the time the timer was
+//                     away for
+//   length_secs, elapsed_secs, paused_secs
+//   cycle_pomodoros, cycle_breaks
+//                     the counts the numbering goes by
+//   counts            the run's totals: completed_pomodoros, breaks_taken,
+//                     skipped_sessions, abandoned_sessions,
+//                     restarted_sessions, focused_secs, paused_secs,
+//                     idle_secs, interrupted_secs, snoozed_secs
+
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local, SecondsFormat, TimeDelta};
+use rustix::{io::Errno, process::Pid};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::PomodoroError,
+    timer::{Checkpoint, Counts, Phase, PomodoroTimer, Session, TimerState},
+};
+
+pub const VERSION: u32 = 1;
+
+// How often the running session is saved while nothing else changes
+pub const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+// A session saved longer ago than this isn't offered any more
+pub const MAX_AGE: Duration = Duration::from_secs(12 * 60 * 60);
+
+pub struct StateFile {
+    path: PathBuf,
+    // What was saved last, and when
+    saved: Option<(Saved, Instant)>,
+}
+
+// What's worth saving straight away when it changes, unlike the time the
+// session has run
+type Saved = (Phase, Option<Session>);
+
+#[derive(Serialize, Deserialize)]
+struct State {
+    version: u32,
+    pid: u32,
+    saved_at: String,
+    kind: String,
+    session: u64,
+    started_at: String,
+    length_secs: u64,
+    elapsed_secs: u64,
+    paused_secs: u64,
+    cycle_pomodoros: u64,
+    cycle_breaks: u64,
+    counts: SavedCounts,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedCounts {
+    completed_pomodoros: u64,
+    breaks_taken: u64,
+    skipped_sessions: u64,
+    abandoned_sessions: u64,
+    restarted_sessions: u64,
+    focused_secs: u64,
+    paused_secs: u64,
+    idle_secs: u64,
+    interrupted_secs: u64,
+    snoozed_secs: u64,
+}
+
+// Read first, so a file of another version doesn't have to fit State
+#[derive(Deserialize)]
+struct Version {
+    version: u32,
+}
+
+impl StateFile {
+    pub fn new(path: PathBuf) -> Self {
+        StateFile { path, saved: None }
+    }
+
+    // Save the timer's running session, or remove the file between
+    // sessions, when that changed since it was last done or SAVE_INTERVAL
+    // has gone by. The file is replaced in one go, so it's never found half
+    // written.
+    pub fn update(&mut self, timer: &PomodoroTimer, now: Instant) -> Result<(), PomodoroError> {
+        let checkpoint = timer.checkpoint();
+        let saved = (
+            timer.phase(),
+            checkpoint.map(|checkpoint| checkpoint.session),
+        );
+        if let Some((last, at)) = self.saved
+            && last == saved
+            && now.saturating_duration_since(at) < SAVE_INTERVAL
+        {
+            return Ok(());
+        }
+        self.saved = Some((saved, now));
+        let Some(checkpoint) = checkpoint else {
+            return self.remove();
+        };
+
+        let error = |source| PomodoroError::Io {
+            action: "write",
+            path: self.path.clone(),
+            source,
+        };
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(error)?;
+        }
+        let Ok(mut contents) = serde_json::to_string(&state(&checkpoint)) else {
+            return Ok(());
+        };
+        contents.push('\n');
+        let partial = self.path.with_extension("json.partial");
+        fs::write(&partial, contents).map_err(error)?;
+        fs::rename(&partial, &self.path).map_err(error)
+    }
+
+    // For when the timer quits as it should, leaving nothing to take up
+    pub fn remove(&self) -> Result<(), PomodoroError> {
+        match fs::remove_file(&self.path) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(PomodoroError::Io {
+                action: "remove",
+                path: self.path.clone(),
+                source: err,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+// The session in the state file at `path`, unless there's none to take up:
+// a timer still running saved it, it's older than MAX_AGE, or the file can't
+// be read as this version of it
+pub fn left_behind(path: &Path) -> Option<Checkpoint> {
+    let contents = fs::read_to_string(path).ok()?;
+    let version: Version = serde_json::from_str(&contents).ok()?;
+    if version.version != VERSION {
+        return None;
+    }
+    let state: State = serde_json::from_str(&contents).ok()?;
+    let saved_at = DateTime::parse_from_rfc3339(&state.saved_at)
+        .ok()?
+        .with_timezone(&Local);
+    let age = (Local::now() - saved_at).to_std().unwrap_or_default();
+    // A timer still running saves it again every SAVE_INTERVAL, and its
+    // process id could only have been taken by another since if it's gone
+    if age > MAX_AGE || (age < 2 * SAVE_INTERVAL && running(state.pid)) {
+        return None;
+    }
+    let kind = [TimerState::Work, TimerState::Break, TimerState::LongBreak]
+        .into_iter()
+        .find(|kind| kind.id() == state.kind)?;
+    let secs = Duration::from_secs;
+    let counts = &state.counts;
+    Some(Checkpoint {
+        session: Session {
+            state: kind,
+            number: state.session,
+            length: secs(state.length_secs),
+        },
+        elapsed: secs(state.elapsed_secs),
+        paused: secs(state.paused_secs),
+        counts: Counts {
+            completed_pomodoros: counts.completed_pomodoros,
+            breaks_taken: counts.breaks_taken,
+            skipped_sessions: counts.skipped_sessions,
+            abandoned_sessions: counts.abandoned_sessions,
+            restarted_sessions: counts.restarted_sessions,
+            focused: secs(counts.focused_secs),
+            paused: secs(counts.paused_secs),
+            idle: secs(counts.idle_secs),
+            interrupted: secs(counts.interrupted_secs),
+            snoozed: secs(counts.snoozed_secs),
+        },
+        cycle_pomodoros: state.cycle_pomodoros,
+        cycle_breaks: state.cycle_breaks,
+    })
+}
+
+fn state(checkpoint: &Checkpoint) -> State {
+    let now = Local::now();
+    let ran = TimeDelta::from_std(checkpoint.elapsed + checkpoint.paused).unwrap_or_default();
+    let timestamp = |at: DateTime<Local>| at.to_rfc3339_opts(SecondsFormat::Secs, false);
+    let counts = &checkpoint.counts;
+    State {
+        version: VERSION,
+        pid: process::id(),
+        saved_at: timestamp(now),
+        kind: checkpoint.session.state.id().to_string(),
+        session: checkpoint.session.number,
+        started_at: timestamp(now - ran),
+        length_secs: checkpoint.session.length.as_secs(),
+        elapsed_secs: checkpoint.elapsed.as_secs(),
+        paused_secs: checkpoint.paused.as_secs(),
+        cycle_pomodoros: checkpoint.cycle_pomodoros,
+        cycle_breaks: checkpoint.cycle_breaks,
+        counts: SavedCounts {
+            completed_pomodoros: counts.completed_pomodoros,
+            breaks_taken: counts.breaks_taken,
+            skipped_sessions: counts.skipped_sessions,
+            abandoned_sessions: counts.abandoned_sessions,
+            restarted_sessions: counts.restarted_sessions,
+            focused_secs: counts.focused.as_secs(),
+            paused_secs: counts.paused.as_secs(),
+            idle_secs: counts.idle.as_secs(),
+            interrupted_secs: counts.interrupted.as_secs(),
+            snoozed_secs: counts.snoozed.as_secs(),
+        },
+    }
+}
+
+// Whether a process with this id is running, even one of another user's
+fn running(pid: u32) -> bool {
+    let Some(pid) = i32::try_from(pid).ok().and_then(Pid::from_raw) else {
+        return false;
+    };
+    matches!(
+        rustix::process::test_kill_process(pid),
+        Ok(()) | Err(Errno::PERM)
+    )
+}
@@ -0,0 +1,693 @@
+use std::{fmt, path::PathBuf, time::Duration};
+
+use crate::{
+    cli::{StartArgs, StartWith},
+    color::Palette,
+    config::{self, Config, HistoryBackend, Profile, SleepPolicy, Urgency},
+    duration::{format_duration, parse_duration},
+    error::PomodoroError,
+    format::{ClockStyle, CountdownFormat, DEFAULT_FORMAT, Messages},
+    icons::IconStyle,
+    timer::{
+        DEFAULT_BREAK, DEFAULT_EXTEND, DEFAULT_LONG_BREAK, DEFAULT_NAG_INTERVAL,
+        DEFAULT_SESSIONS_UNTIL_LONG_BREAK, DEFAULT_SLEEP_THRESHOLD, DEFAULT_SNOOZE, DEFAULT_WORK,
+        PomodoroConfig,
+    },
+};
+
+const DEFAULT_SOUND_COMMAND: &str = "paplay {file}";
+const DEFAULT_SOUND_FILE: &str = "/usr/share/sounds/freedesktop/stereo/complete.oga";
+
+// The effective settings the timer runs with
+pub struct Settings {
+    pub work: Duration,
+    pub break_: Duration,
+    pub long_break: Duration,
+    pub sessions_until_long_break: u64,
+    // Work sessions to complete each day, None for no goal
+    pub goal: Option<u64>,
+    // Ask for a task before each work session
+    pub ask_task: bool,
+    // Work sessions can't be paused, skipped, restarted or extended
+    pub strict: bool,
+    // Where ended sessions are kept
+    pub history: HistoryBackend,
+    pub sound_command: String,
+    pub sound_file: PathBuf,
+    pub start_sound_file: PathBuf,
+    pub sound_on_start: bool,
+    pub sound_on_end: bool,
+    pub muted: bool,
+    pub auto_start: bool,
+    pub auto_start_breaks: bool,
+    pub auto_start_work: bool,
+    pub format: String,
+    // None keeps the built-in message
+    pub message_work_start: Option<String>,
+    pub message_work_end: Option<String>,
+    pub message_break_start: Option<String>,
+    pub message_break_end: Option<String>,
+    pub clock: ClockStyle,
+    pub sleep_threshold: Duration,
+    // Added to the running session by the extend key
+    pub extend: Duration,
+    // How long the snooze key puts off the next session
+    pub snooze: Duration,
+    pub on_sleep: SleepPolicy,
+    pub colors: Palette,
+    pub title: bool,
+    // Desktop notifications when a session finishes
+    pub notify: bool,
+    pub notify_urgency: Urgency,
+    // Repeat the end-of-session alert while the next session waits
+    pub nag: bool,
+    pub nag_interval: Duration,
+    // Count up from the start of the session instead of down to its end
+    pub show_elapsed: bool,
+    pub icons: IconStyle,
+    // Where each value came from, keyed by its config file name
+    pub sources: Vec<(&'static str, Source)>,
+}
+
+// Where a setting's value came from, reported by `pomodoro config`
+#[derive(Clone)]
+pub enum Source {
+    Default,
+    ConfigFile,
+    Profile(String),
+    Environment(&'static str),
+    Flag,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::ConfigFile => write!(f, "config file"),
+            Source::Profile(name) => write!(f, "profile '{}'", name),
+            Source::Environment(name) => write!(f, "environment variable {}", name),
+            Source::Flag => write!(f, "command-line flag"),
+        }
+    }
+}
+
+impl Settings {
+    // Each setting's config file name, current value and source
+    pub fn describe(&self) -> Vec<(&'static str, String, Source)> {
+        let values = [
+            ("work_minutes", format_duration(self.work)),
+            ("break_minutes", format_duration(self.break_)),
+            ("long_break_minutes", format_duration(self.long_break)),
+            (
+                "sessions_until_long_break",
+                self.sessions_until_long_break.to_string(),
+            ),
+            (
+                "goal",
+                self.goal
+                    .map_or("none".to_string(), |goal| goal.to_string()),
+            ),
+            ("ask_task", self.ask_task.to_string()),
+            ("strict", self.strict.to_string()),
+            ("history", self.history.to_string()),
+            ("sound_command", self.sound_command.clone()),
+            ("sound_file", self.sound_file.display().to_string()),
+            (
+                "start_sound_file",
+                self.start_sound_file.display().to_string(),
+            ),
+            ("sound_on_start", self.sound_on_start.to_string()),
+            ("sound_on_end", self.sound_on_end.to_string()),
+            ("mute", self.muted.to_string()),
+            ("auto_start", self.auto_start.to_string()),
+            ("auto_start_breaks", self.auto_start_breaks.to_string()),
+            ("auto_start_work", self.auto_start_work.to_string()),
+            ("format", self.format.clone()),
+            (
+                "message_work_start",
+                describe_message(&self.message_work_start),
+            ),
+            ("message_work_end", describe_message(&self.message_work_end)),
+            (
+                "message_break_start",
+                describe_message(&self.message_break_start),
+            ),
+            (
+                "message_break_end",
+                describe_message(&self.message_break_end),
+            ),
+            ("clock", self.clock.to_string()),
+            (
+                "sleep_threshold_minutes",
+                format_duration(self.sleep_threshold),
+            ),
+            ("on_sleep", self.on_sleep.to_string()),
+            ("extend_minutes", format_duration(self.extend)),
+            ("snooze_minutes", format_duration(self.snooze)),
+            ("title", self.title.to_string()),
+            ("notify", self.notify.to_string()),
+            ("notify_urgency", self.notify_urgency.to_string()),
+            ("nag", self.nag.to_string()),
+            (
+                "nag_interval_seconds",
+                self.nag_interval.as_secs().to_string(),
+            ),
+            ("show_elapsed", self.show_elapsed.to_string()),
+            ("icons", self.icons.to_string()),
+            ("colors.work", self.colors.work.to_string()),
+            ("colors.break", self.colors.break_.to_string()),
+            ("colors.paused", self.colors.paused.to_string()),
+        ];
+        values
+            .into_iter()
+            .map(|(key, value)| (key, value, self.source(key)))
+            .collect()
+    }
+
+    // The timer's configuration, with the run options from the command line
+    pub fn timer_config(&self, args: &StartArgs) -> Result<PomodoroConfig, PomodoroError> {
+        PomodoroConfig::builder()
+            .work(self.work)
+            .break_(self.break_)
+            .long_break(self.long_break)
+            .sessions_until_long_break(self.sessions_until_long_break)
+            .auto_start_breaks(self.auto_start_breaks)
+            .auto_start_work(self.auto_start_work)
+            .cycles(args.cycles)
+            .final_break(!args.no_final_break)
+            .once(args.once)
+            .start_with_break(matches!(args.start_with, StartWith::Break))
+            .until(args.until)
+            .sleep_threshold(self.sleep_threshold)
+            .on_sleep(self.on_sleep)
+            .nag(self.nag.then_some(self.nag_interval))
+            .strict(self.strict)
+            .build()
+    }
+
+    // The session messages, in the run's clock style
+    pub fn messages(&self, plan: &PomodoroConfig) -> Result<Messages, PomodoroError> {
+        let parse = |message: &Option<String>| {
+            message
+                .as_deref()
+                .map(|message| {
+                    CountdownFormat::parse(message).map(|format| format.with_clock(self.clock))
+                })
+                .transpose()
+        };
+        Ok(Messages {
+            work_start: parse(&self.message_work_start)?,
+            work_end: parse(&self.message_work_end)?,
+            break_start: parse(&self.message_break_start)?,
+            break_end: parse(&self.message_break_end)?,
+            total_sessions: plan.cycles().unwrap_or(plan.sessions_until_long_break()),
+        })
+    }
+
+    fn source(&self, key: &str) -> Source {
+        self.sources
+            .iter()
+            .find(|(name, _)| *name == key)
+            .map(|(_, source)| source.clone())
+            .unwrap_or(Source::Default)
+    }
+}
+
+// How `pomodoro config` shows a message that may be unset or empty
+fn describe_message(message: &Option<String>) -> String {
+    match message.as_deref() {
+        None => "(built in)".to_string(),
+        Some("") => "(none)".to_string(),
+        Some(message) => message.to_string(),
+    }
+}
+
+// Take the first value that is set, most important source first
+fn first<T>(candidates: impl IntoIterator<Item = (Option<T>, Source)>, default: T) -> (T, Source) {
+    candidates
+        .into_iter()
+        .find_map(|(value, source)| value.map(|value| (value, source)))
+        .unwrap_or((default, Source::Default))
+}
+
+// Combine every source of settings, later ones winning: built-in defaults <
+// config file (and the chosen profile) < environment < flags. Interactive
+// prompts are applied by the caller on top of this.
+pub fn resolve(
+    args: &StartArgs,
+    config: &Config,
+    profile: Option<&Profile>,
+    env: impl Fn(&str) -> Option<String>,
+) -> Settings {
+    let from_env = |name: &'static str| {
+        let value = env(name)?;
+        parse_duration(&value)
+            .inspect_err(|err| eprintln!("warning: ignoring {}: {}", name, err))
+            .ok()
+    };
+    let minutes = |value: Option<f64>| value.map(config::minutes_to_duration);
+    let from_profile = |value: fn(&Profile) -> Option<f64>| {
+        (
+            minutes(profile.and_then(value)),
+            Source::Profile(
+                profile
+                    .map(|profile| profile.name.clone())
+                    .unwrap_or_default(),
+            ),
+        )
+    };
+    let mut sources = Vec::new();
+
+    let (work, source) = first(
+        [
+            (args.work, Source::Flag),
+            (
+                from_env("POMODORO_WORK_MINUTES"),
+                Source::Environment("POMODORO_WORK_MINUTES"),
+            ),
+            from_profile(|profile| profile.work),
+            (minutes(config.work_minutes), Source::ConfigFile),
+        ],
+        DEFAULT_WORK,
+    );
+    sources.push(("work_minutes", source));
+
+    let (break_, source) = first(
+        [
+            (args.break_, Source::Flag),
+            (
+                from_env("POMODORO_BREAK_MINUTES"),
+                Source::Environment("POMODORO_BREAK_MINUTES"),
+            ),
+            from_profile(|profile| profile.break_),
+            (minutes(config.break_minutes), Source::ConfigFile),
+        ],
+        DEFAULT_BREAK,
+    );
+    sources.push(("break_minutes", source));
+
+    let (long_break, source) = first(
+        [
+            (args.long_break, Source::Flag),
+            from_profile(|profile| profile.long_break),
+            (minutes(config.long_break_minutes), Source::ConfigFile),
+        ],
+        DEFAULT_LONG_BREAK,
+    );
+    sources.push(("long_break_minutes", source));
+
+    let (sessions_until_long_break, source) = first(
+        [
+            (args.sessions_until_long_break, Source::Flag),
+            (config.sessions_until_long_break, Source::ConfigFile),
+        ],
+        DEFAULT_SESSIONS_UNTIL_LONG_BREAK,
+    );
+    sources.push(("sessions_until_long_break", source));
+
+    let (goal, source) = first(
+        [
+            (args.goal.map(Some), Source::Flag),
+            (config.goal.map(Some), Source::ConfigFile),
+        ],
+        None,
+    );
+    sources.push(("goal", source));
+
+    let (ask_task, source) = first([(config.ask_task, Source::ConfigFile)], false);
+    sources.push(("ask_task", source));
+
+    let (strict, source) = first(
+        [
+            (args.strict.then_some(true), Source::Flag),
+            (config.strict, Source::ConfigFile),
+        ],
+        false,
+    );
+    sources.push(("strict", source));
+
+    let (history, source) = first(
+        [(config.history, Source::ConfigFile)],
+        HistoryBackend::default(),
+    );
+    sources.push(("history", source));
+
+    let (sound_command, source) = first(
+        [
+            (
+                env("POMODORO_SOUND_CMD").filter(|command| !command.trim().is_empty()),
+                Source::Environment("POMODORO_SOUND_CMD"),
+            ),
+            (config.sound_command.clone(), Source::ConfigFile),
+        ],
+        DEFAULT_SOUND_COMMAND.to_string(),
+    );
+    sources.push(("sound_command", source));
+
+    let (sound_file, source) = first(
+        [
+            (args.sound_file.clone(), Source::Flag),
+            (config.sound_file.clone(), Source::ConfigFile),
+        ],
+        PathBuf::from(DEFAULT_SOUND_FILE),
+    );
+    sources.push(("sound_file", source));
+
+    // The start sound is the same as the end one unless set separately
+    let (start_sound_file, source) = first(
+        [(config.start_sound_file.clone(), Source::ConfigFile)],
+        sound_file.clone(),
+    );
+    sources.push(("start_sound_file", source));
+
+    // By default only the end of a session makes a sound
+    let (sound_on_start, source) = first([(config.sound_on_start, Source::ConfigFile)], false);
+    sources.push(("sound_on_start", source));
+
+    let (sound_on_end, source) = first([(config.sound_on_end, Source::ConfigFile)], true);
+    sources.push(("sound_on_end", source));
+
+    let (muted, source) = first(
+        [
+            (args.mute.then_some(true), Source::Flag),
+            (config.mute, Source::ConfigFile),
+        ],
+        false,
+    );
+    sources.push(("mute", source));
+
+    let flag_auto_start = if args.manual_start {
+        Some(false)
+    } else {
+        args.auto_start.then_some(true)
+    };
+    let (auto_start, source) = first(
+        [
+            (flag_auto_start, Source::Flag),
+            (config.auto_start, Source::ConfigFile),
+        ],
+        true,
+    );
+    sources.push(("auto_start", source));
+
+    // Breaks and work sessions can each be set apart from auto_start, but
+    // the flags decide for both
+    let (auto_start_breaks, source) = first(
+        [
+            (flag_auto_start, Source::Flag),
+            (config.auto_start_breaks, Source::ConfigFile),
+            (config.auto_start, Source::ConfigFile),
+        ],
+        true,
+    );
+    sources.push(("auto_start_breaks", source));
+
+    let (auto_start_work, source) = first(
+        [
+            (flag_auto_start, Source::Flag),
+            (config.auto_start_work, Source::ConfigFile),
+            (config.auto_start, Source::ConfigFile),
+        ],
+        true,
+    );
+    sources.push(("auto_start_work", source));
+
+    let (format, source) = first(
+        [
+            (args.format.clone(), Source::Flag),
+            (config.format.clone(), Source::ConfigFile),
+        ],
+        DEFAULT_FORMAT.to_string(),
+    );
+    sources.push(("format", source));
+
+    // Messages only come from the config file, and are otherwise built in
+    for (key, message) in [
+        ("message_work_start", &config.message_work_start),
+        ("message_work_end", &config.message_work_end),
+        ("message_break_start", &config.message_break_start),
+        ("message_break_end", &config.message_break_end),
+    ] {
+        if message.is_some() {
+            sources.push((key, Source::ConfigFile));
+        }
+    }
+
+    let (clock, source) = first(
+        [
+            (args.clock, Source::Flag),
+            (config.clock, Source::ConfigFile),
+        ],
+        ClockStyle::default(),
+    );
+    sources.push(("clock", source));
+
+    let (sleep_threshold, source) = first(
+        [(minutes(config.sleep_threshold_minutes), Source::ConfigFile)],
+        DEFAULT_SLEEP_THRESHOLD,
+    );
+    sources.push(("sleep_threshold_minutes", source));
+
+    let (extend, source) = first(
+        [(minutes(config.extend_minutes), Source::ConfigFile)],
+        DEFAULT_EXTEND,
+    );
+    sources.push(("extend_minutes", source));
+
+    let (snooze, source) = first(
+        [(minutes(config.snooze_minutes), Source::ConfigFile)],
+        DEFAULT_SNOOZE,
+    );
+    sources.push(("snooze_minutes", source));
+
+    let (on_sleep, source) = first(
+        [(config.on_sleep, Source::ConfigFile)],
+        SleepPolicy::default(),
+    );
+    sources.push(("on_sleep", source));
+
+    let (title, source) = first(
+        [
+            (args.title.then_some(true), Source::Flag),
+            (config.title, Source::ConfigFile),
+        ],
+        false,
+    );
+    sources.push(("title", source));
+
+    let (notify, source) = first(
+        [
+            (args.no_notify.then_some(false), Source::Flag),
+            (config.notify, Source::ConfigFile),
+        ],
+        true,
+    );
+    sources.push(("notify", source));
+
+    let (notify_urgency, source) = first(
+        [(config.notify_urgency, Source::ConfigFile)],
+        Urgency::default(),
+    );
+    sources.push(("notify_urgency", source));
+
+    let (nag, source) = first(
+        [
+            (args.nag.then_some(true), Source::Flag),
+            (config.nag, Source::ConfigFile),
+        ],
+        false,
+    );
+    sources.push(("nag", source));
+
+    let (nag_interval, source) = first(
+        [(
+            config.nag_interval_seconds.map(Duration::from_secs),
+            Source::ConfigFile,
+        )],
+        DEFAULT_NAG_INTERVAL,
+    );
+    sources.push(("nag_interval_seconds", source));
+
+    let (show_elapsed, source) = first([(config.show_elapsed, Source::ConfigFile)], false);
+    sources.push(("show_elapsed", source));
+
+    let (icons, source) = first(
+        [
+            (args.icons, Source::Flag),
+            (config.icons, Source::ConfigFile),
+        ],
+        IconStyle::default(),
+    );
+    sources.push(("icons", source));
+
+    let default_colors = Palette::default();
+    let (work_color, source) = first(
+        [(config.colors.work, Source::ConfigFile)],
+        default_colors.work,
+    );
+    sources.push(("colors.work", source));
+
+    let (break_color, source) = first(
+        [(config.colors.break_, Source::ConfigFile)],
+        default_colors.break_,
+    );
+    sources.push(("colors.break", source));
+
+    let (paused_color, source) = first(
+        [(config.colors.paused, Source::ConfigFile)],
+        default_colors.paused,
+    );
+    sources.push(("colors.paused", source));
+
+    Settings {
+        work,
+        break_,
+        long_break,
+        sessions_until_long_break,
+        goal,
+        ask_task,
+        strict,
+        history,
+        sound_command,
+        sound_file,
+        start_sound_file,
+        sound_on_start,
+        sound_on_end,
+        muted,
+        auto_start,
+        auto_start_breaks,
+        auto_start_work,
+        format,
+        message_work_start: config.message_work_start.clone(),
+        message_work_end: config.message_work_end.clone(),
+        message_break_start: config.message_break_start.clone(),
+        message_break_end: config.message_break_end.clone(),
+        clock,
+        sleep_threshold,
+        extend,
+        snooze,
+        on_sleep,
+        colors: Palette {
+            work: work_color,
+            break_: break_color,
+            paused: paused_color,
+        },
+        title,
+        notify,
+        notify_urgency,
+        nag,
+        nag_interval,
+        show_elapsed,
+        icons,
+        sources,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::cli::Cli;
+
+    fn args(flags: &[&str]) -> StartArgs {
+        let args = ["pomodoro_timer"].iter().chain(flags);
+        Cli::try_parse_from(args).unwrap().start
+    }
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    // Each source sets the work length to a different number of minutes,
+    // and the ones given decide which wins
+    fn work(flag: bool, env: bool, profile: bool, config: bool) -> (Duration, String) {
+        let args = if flag {
+            args(&["--work", "1"])
+        } else {
+            args(&[])
+        };
+        let config = Config {
+            work_minutes: config.then_some(4.0),
+            ..Config::default()
+        };
+        let profile = Profile {
+            name: "deep".to_string(),
+            work: profile.then_some(3.0),
+            ..Profile::default()
+        };
+        let settings = resolve(&args, &config, Some(&profile), |name| {
+            (env && name == "POMODORO_WORK_MINUTES").then(|| "2".to_string())
+        });
+        (settings.work, settings.source("work_minutes").to_string())
+    }
+
+    #[test]
+    fn flag_beats_everything() {
+        assert_eq!(
+            work(true, true, true, true),
+            (minutes(1), "command-line flag".to_string())
+        );
+    }
+
+    #[test]
+    fn environment_beats_profile_and_config() {
+        assert_eq!(
+            work(false, true, true, true),
+            (
+                minutes(2),
+                "environment variable POMODORO_WORK_MINUTES".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn profile_beats_config() {
+        assert_eq!(
+            work(false, false, true, true),
+            (minutes(3), "profile 'deep'".to_string())
+        );
+    }
+
+    #[test]
+    fn config_beats_default() {
+        assert_eq!(
+            work(false, false, false, true),
+            (minutes(4), "config file".to_string())
+        );
+    }
+
+    #[test]
+    fn default_when_nothing_is_set() {
+        assert_eq!(
+            work(false, false, false, false),
+            (DEFAULT_WORK, "default".to_string())
+        );
+    }
+
+    #[test]
+    fn invalid_environment_value_is_ignored() {
+        let config = Config {
+            work_minutes: Some(4.0),
+            ..Config::default()
+        };
+        let settings = resolve(&args(&[]), &config, None, |name| {
+            (name == "POMODORO_WORK_MINUTES").then(|| "soon".to_string())
+        });
+        assert_eq!(settings.work, minutes(4));
+    }
+
+    #[test]
+    fn sound_command_from_environment() {
+        let config = Config {
+            sound_command: Some("aplay {file}".to_string()),
+            ..Config::default()
+        };
+        let settings = resolve(&args(&[]), &config, None, |name| {
+            (name == "POMODORO_SOUND_CMD").then(|| "mpv {file}".to_string())
+        });
+        assert_eq!(settings.sound_command, "mpv {file}");
+        let settings = resolve(&args(&[]), &config, None, |_| None);
+        assert_eq!(settings.sound_command, "aplay {file}");
+    }
+}
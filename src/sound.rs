@@ -0,0 +1,220 @@
+use std::{
+    cell::Cell,
+    io::{ErrorKind, Write, stdout},
+    path::Path,
+    process::{Child, Command},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{error::PomodoroError, settings::Settings, timer::TimerEvent};
+
+// Placeholder in a sound_command template replaced by the sound file path
+const FILE_PLACEHOLDER: &str = "{file}";
+
+// How soon a player that has finished is waited on
+const REAP_INTERVAL: Duration = Duration::from_millis(100);
+
+// Every sound the timer makes goes through here, so muting, the choice of
+// events and the fallback to the terminal bell are all decided in one place
+pub struct Notifier {
+    // None when that event makes no sound at all
+    on_start: Option<Alert>,
+    on_end: Option<Alert>,
+    // Set while the sound is muted, by --mute or the mute key
+    muted: Arc<AtomicBool>,
+}
+
+// The alert for one event: the external player if there is one that works,
+// otherwise the terminal bell if it's allowed
+struct Alert {
+    player: Option<SoundCommand>,
+    bell: bool,
+}
+
+impl Notifier {
+    // Check the players up front so a bad path is reported at startup rather
+    // than mid-session. The bell is left out when stdout isn't for humans,
+    // e.g. in --json mode. The players are set up even when `muted` starts
+    // out set, since the sound can be turned back on.
+    pub fn new(settings: &Settings, bell: bool, muted: Arc<AtomicBool>) -> Self {
+        let alert = |enabled: bool, file: &Path| {
+            if !enabled {
+                return None;
+            }
+            let player = SoundCommand::from_template(&settings.sound_command, file)
+                .inspect_err(|err| eprintln!("warning: {}, only the terminal bell will sound", err))
+                .ok();
+            Some(Alert { player, bell })
+        };
+        Notifier {
+            on_start: alert(settings.sound_on_start, &settings.start_sound_file),
+            on_end: alert(settings.sound_on_end, &settings.sound_file),
+            muted,
+        }
+    }
+
+    // The timer listener that makes the sounds
+    pub fn on_event(&self, event: &TimerEvent) {
+        match event {
+            TimerEvent::SessionStarted { .. } => self.session_started(),
+            // Reminders repeat the end of the session
+            TimerEvent::SessionCompleted { .. } | TimerEvent::Reminder { .. } => {
+                self.session_finished()
+            }
+            TimerEvent::Acknowledged => self.silence(),
+            _ => {}
+        }
+    }
+
+    pub fn session_started(&self) {
+        if let Some(alert) = &self.on_start
+            && !self.muted.load(Ordering::Relaxed)
+        {
+            alert.sound();
+        }
+    }
+
+    pub fn session_finished(&self) {
+        if let Some(alert) = &self.on_end
+            && !self.muted.load(Ordering::Relaxed)
+        {
+            alert.sound();
+        }
+    }
+
+    // Cut short any sound still playing
+    pub fn silence(&self) {
+        for alert in [&self.on_start, &self.on_end].into_iter().flatten() {
+            if let Some(player) = &alert.player {
+                player.stop();
+            }
+        }
+    }
+}
+
+impl Alert {
+    fn sound(&self) {
+        let played = self.player.as_ref().is_some_and(SoundCommand::play);
+        if !played && self.bell {
+            print!("\x07"); // ASCII bell character
+            let _ = stdout().flush();
+        }
+    }
+}
+
+// An external player command built once at startup from the configured
+// template, e.g. "paplay {file}" or "mpv --no-video {file}"
+pub struct SoundCommand {
+    program: String,
+    args: Vec<String>,
+    // Set once a failure has been reported so it isn't repeated every session
+    warned: Cell<bool>,
+    // The player last started, until it has finished and been waited on
+    playing: Arc<Mutex<Option<Child>>>,
+}
+
+impl SoundCommand {
+    // Split the template on whitespace and substitute the file path, which is
+    // passed as a single argument even when it contains spaces. Templates
+    // without a placeholder are run as they are.
+    pub fn from_template(template: &str, file: &Path) -> Result<Self, PomodoroError> {
+        let mut words = template.split_whitespace();
+        let program = words
+            .next()
+            .ok_or_else(|| PomodoroError::SoundBackend("sound command is empty".to_string()))?
+            .to_string();
+        let mut uses_file = false;
+        let args = words
+            .map(|word| {
+                if word.contains(FILE_PLACEHOLDER) {
+                    uses_file = true;
+                    word.replace(FILE_PLACEHOLDER, &file.to_string_lossy())
+                } else {
+                    word.to_string()
+                }
+            })
+            .collect();
+
+        if uses_file && !file.is_file() {
+            return Err(PomodoroError::SoundBackend(format!(
+                "sound file {} does not exist",
+                file.display()
+            )));
+        }
+        Ok(SoundCommand {
+            program,
+            args,
+            warned: Cell::new(false),
+            playing: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    // Start the player in the background, unless the last one is still
+    // playing, so repeated alerts never overlap. A player that can't be run
+    // is reported once, and false is returned so the caller can fall back.
+    fn play(&self) -> bool {
+        let Ok(mut playing) = self.playing.lock() else {
+            return false;
+        };
+        if playing.is_some() {
+            return true;
+        }
+        match Command::new(&self.program).args(&self.args).spawn() {
+            Ok(child) => {
+                *playing = Some(child);
+                reap(self.playing.clone());
+                return true;
+            }
+            Err(err) if !self.warned.replace(true) => {
+                if err.kind() == ErrorKind::NotFound {
+                    eprintln!("warning: could not play sound: {} not found", self.program);
+                } else {
+                    eprintln!("warning: could not play sound: {}: {}", self.program, err);
+                }
+            }
+            Err(_) => {}
+        }
+        false
+    }
+
+    // Stop the player if it's still going
+    fn stop(&self) {
+        if let Some(mut child) = self
+            .playing
+            .lock()
+            .ok()
+            .and_then(|mut playing| playing.take())
+        {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+// Wait on the player in `playing` on a thread of its own as soon as it
+// finishes, so it doesn't linger as a zombie until the next alert. It's
+// only looked at now and then, rather than waited on, so stop() can still
+// get at it to kill it.
+fn reap(playing: Arc<Mutex<Option<Child>>>) {
+    thread::spawn(move || {
+        loop {
+            thread::sleep(REAP_INTERVAL);
+            let Ok(mut playing) = playing.lock() else {
+                return;
+            };
+            match playing.as_mut().map(Child::try_wait) {
+                Some(Ok(None)) => {}
+                // Finished, or already taken by stop()
+                _ => {
+                    *playing = None;
+                    return;
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,213 @@
+use std::{cmp::Reverse, collections::BTreeSet, time::Duration};
+
+use chrono::{Datelike, Days, NaiveDate};
+use clap::ValueEnum;
+use serde::{Serialize, Serializer};
+
+use crate::history::{self, DateRange, Record};
+
+// How many of the most used tasks `stats` lists
+pub const TOP_TASKS: usize = 5;
+
+// The stretch of days `stats --period` sums up. Weeks start on Monday.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    Today,
+    Week,
+    Month,
+    All,
+}
+
+impl Period {
+    // The days it covers as of `today`
+    pub fn range(self, today: NaiveDate) -> DateRange {
+        let from = match self {
+            Period::Today => today,
+            Period::Week => today - Days::new(today.weekday().num_days_from_monday().into()),
+            Period::Month => today.with_day(1).unwrap_or(today),
+            Period::All => return DateRange::default(),
+        };
+        DateRange {
+            from: Some(from),
+            to: Some(today),
+        }
+    }
+
+    // As the summary heads its line with
+    pub fn name(self) -> &'static str {
+        match self {
+            Period::Today => "Today",
+            Period::Week => "This week",
+            Period::Month => "This month",
+            Period::All => "All time",
+        }
+    }
+}
+
+// What `stats` reports, worked out from the sessions in the history.
+// Sessions count towards the day they started on, even if they ran past
+// midnight.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub sessions: usize,
+    pub totals: Vec<Totals>,
+    // Days with at least one completed pomodoro
+    pub active_days: usize,
+    // Pomodoros per active day
+    pub average_pomodoros: Option<f64>,
+    pub longest_streak: Option<Streak>,
+    // The tasks worked on most, by their completed pomodoros, most first
+    pub top_tasks: Vec<TaskCount>,
+    pub outcomes: Vec<OutcomeCount>,
+}
+
+// Pomodoros and focused time over some of the days
+#[derive(Debug, Serialize)]
+pub struct Totals {
+    // None for the days picked with --from and --to
+    pub period: Option<Period>,
+    #[serde(serialize_with = "date")]
+    pub from: Option<NaiveDate>,
+    #[serde(serialize_with = "date")]
+    pub to: Option<NaiveDate>,
+    pub pomodoros: u64,
+    #[serde(rename = "focused_secs", serialize_with = "seconds")]
+    pub focused: Duration,
+}
+
+// The most days in a row with a completed pomodoro on each
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Streak {
+    pub days: u64,
+    #[serde(serialize_with = "day")]
+    pub from: NaiveDate,
+    #[serde(serialize_with = "day")]
+    pub to: NaiveDate,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TaskCount {
+    pub task: String,
+    pub pomodoros: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OutcomeCount {
+    // As Outcome::id() names it
+    pub outcome: &'static str,
+    pub sessions: usize,
+}
+
+impl Report {
+    // `records` are all the sessions reported on, those in `range`, and
+    // `periods` the totals to give as of `today`, each over the records that
+    // fall in it. None totals up the whole range.
+    pub fn new(
+        records: &[Record],
+        range: DateRange,
+        periods: &[Option<Period>],
+        today: NaiveDate,
+    ) -> Self {
+        let totals = periods
+            .iter()
+            .map(|period| {
+                let range = period.map_or(range, |period| period.range(today));
+                let records: Vec<Record> = records
+                    .iter()
+                    .filter(|record| range.contains(record.start.date_naive()))
+                    .cloned()
+                    .collect();
+                Totals {
+                    period: *period,
+                    from: range.from,
+                    to: range.to,
+                    pomodoros: history::pomodoros(&records),
+                    focused: history::focused(&records),
+                }
+            })
+            .collect();
+
+        let completed: Vec<&Record> = records
+            .iter()
+            .filter(|record| record.is_pomodoro())
+            .collect();
+        let days: BTreeSet<NaiveDate> = completed
+            .iter()
+            .map(|record| record.start.date_naive())
+            .collect();
+        let average_pomodoros =
+            (!days.is_empty()).then(|| completed.len() as f64 / days.len() as f64);
+
+        let mut tasks: Vec<TaskCount> = Vec::new();
+        for task in completed.iter().filter_map(|record| record.task.as_deref()) {
+            match tasks.iter_mut().find(|count| count.task == task) {
+                Some(count) => count.pomodoros += 1,
+                None => tasks.push(TaskCount {
+                    task: task.to_string(),
+                    pomodoros: 1,
+                }),
+            }
+        }
+        // Ties keep the order the tasks were first worked on in
+        tasks.sort_by_key(|count| Reverse(count.pomodoros));
+        tasks.truncate(TOP_TASKS);
+
+        Report {
+            sessions: records.len(),
+            totals,
+            active_days: days.len(),
+            average_pomodoros,
+            longest_streak: longest_streak(&days),
+            top_tasks: tasks,
+            outcomes: history::outcomes(records)
+                .into_iter()
+                .map(|(outcome, sessions)| OutcomeCount {
+                    outcome: outcome.id(),
+                    sessions,
+                })
+                .collect(),
+        }
+    }
+}
+
+// The longest run of consecutive days, the earliest when there's a tie
+fn longest_streak(days: &BTreeSet<NaiveDate>) -> Option<Streak> {
+    let mut longest: Option<Streak> = None;
+    let mut current: Option<Streak> = None;
+    for &day in days {
+        current = match current {
+            Some(streak) if streak.to.succ_opt() == Some(day) => Some(Streak {
+                days: streak.days + 1,
+                to: day,
+                ..streak
+            }),
+            _ => Some(Streak {
+                days: 1,
+                from: day,
+                to: day,
+            }),
+        };
+        if let Some(streak) = current
+            && longest.is_none_or(|longest| streak.days > longest.days)
+        {
+            longest = Some(streak);
+        }
+    }
+    longest
+}
+
+fn date<S: Serializer>(date: &Option<NaiveDate>, serializer: S) -> Result<S::Ok, S::Error> {
+    match date {
+        Some(date) => serializer.serialize_str(&date.to_string()),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn day<S: Serializer>(date: &NaiveDate, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&date.to_string())
+}
+
+fn seconds<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_secs())
+}
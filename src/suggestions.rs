@@ -0,0 +1,146 @@
+use std::{
+    fs,
+    hash::{BuildHasher, RandomState},
+    io::ErrorKind,
+};
+
+use pomodoro_timer::{
+    config,
+    timer::{TimerEvent, TimerState},
+};
+
+use crate::display::Display;
+
+const BREAK_SUGGESTIONS: &[&str] = &[
+    "stand up and stretch",
+    "refill your water",
+    "look at something 20 feet away for 20 seconds",
+    "roll your shoulders and neck",
+    "take a few slow, deep breaths",
+    "walk around the room",
+    "tidy up your desk",
+];
+
+const LONG_BREAK_SUGGESTIONS: &[&str] = &[
+    "go for a short walk outside",
+    "have a snack",
+    "make a cup of tea",
+    "do a few minutes of stretching",
+    "step away from screens entirely",
+    "call or message a friend",
+];
+
+// Files in the config directory, one suggestion per line, that replace the
+// built-in lists
+const BREAK_FILE: &str = "suggestions.txt";
+const LONG_BREAK_FILE: &str = "long_break_suggestions.txt";
+
+// The timer listener that suggests something to do at the start of each
+// break. Turned off with --no-suggestions.
+pub struct Suggestions {
+    display: Display,
+    breaks: Pool,
+    long_breaks: Pool,
+}
+
+impl Suggestions {
+    pub fn new(display: Display) -> Self {
+        Suggestions {
+            display,
+            breaks: Pool::new(load(BREAK_FILE, BREAK_SUGGESTIONS)),
+            long_breaks: Pool::new(load(LONG_BREAK_FILE, LONG_BREAK_SUGGESTIONS)),
+        }
+    }
+
+    pub fn on_event(&mut self, event: &TimerEvent) {
+        let pool = match *event {
+            TimerEvent::SessionStarted {
+                kind: TimerState::Break,
+                ..
+            } => &mut self.breaks,
+            TimerEvent::SessionStarted {
+                kind: TimerState::LongBreak,
+                ..
+            } => &mut self.long_breaks,
+            _ => return,
+        };
+        if let Some(suggestion) = pool.draw() {
+            say!(self.display, "Suggestion: {}.", suggestion);
+        }
+    }
+}
+
+// The suggestions in `file` in the config directory, or `built_in` when
+// there is no such file or nothing in it
+fn load(file: &str, built_in: &[&str]) -> Vec<String> {
+    let built_in = || built_in.iter().map(|line| line.to_string()).collect();
+    let Some(path) = config::config_path().and_then(|path| Some(path.parent()?.join(file))) else {
+        return built_in();
+    };
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => return built_in(),
+        Err(err) => {
+            eprintln!("warning: could not read {}: {}", path.display(), err);
+            return built_in();
+        }
+    };
+    let suggestions: Vec<String> = contents
+        .lines()
+        .map(|line| line.trim().trim_end_matches('.').to_string())
+        .filter(|line| !line.is_empty())
+        .collect();
+    if suggestions.is_empty() {
+        built_in()
+    } else {
+        suggestions
+    }
+}
+
+// Suggestions in a random order, none repeated until every one has been
+// drawn
+struct Pool {
+    suggestions: Vec<String>,
+    // Indexes into `suggestions` still to be drawn, the next one last
+    left: Vec<usize>,
+    last: Option<usize>,
+    random: RandomState,
+    rounds: u64,
+}
+
+impl Pool {
+    fn new(suggestions: Vec<String>) -> Self {
+        Pool {
+            suggestions,
+            left: Vec::new(),
+            last: None,
+            random: RandomState::new(),
+            rounds: 0,
+        }
+    }
+
+    fn draw(&mut self) -> Option<&str> {
+        if self.left.is_empty() {
+            self.shuffle();
+            // A new round doesn't start with the suggestion that ended the
+            // last one
+            let end = self.left.len().saturating_sub(1);
+            if end > 0 && self.left.last().copied() == self.last {
+                self.left.swap(0, end);
+            }
+        }
+        let index = self.left.pop()?;
+        self.last = Some(index);
+        Some(&self.suggestions[index])
+    }
+
+    // Fisher-Yates, with a fresh permutation for every round
+    fn shuffle(&mut self) {
+        self.left = (0..self.suggestions.len()).collect();
+        for i in (1..self.left.len()).rev() {
+            let j = self.random.hash_one((self.rounds, i)) as usize % (i + 1);
+            self.left.swap(i, j);
+        }
+        self.rounds += 1;
+    }
+}
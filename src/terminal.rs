@@ -0,0 +1,86 @@
+use std::{
+    io::{IsTerminal, Read, StdinLock, stdin},
+    panic,
+    sync::Mutex,
+};
+
+use rustix::termios::{self, LocalModes, OptionalActions, SpecialCodeIndex, Termios};
+
+// The terminal's settings from before enter_cbreak(), for restore()
+static ORIGINAL: Mutex<Option<Termios>> = Mutex::new(None);
+
+// Deliver each key to stdin as soon as it's pressed, without waiting for
+// Enter and without echoing it. Signals are left alone so Ctrl+C and Ctrl+Z
+// behave as usual, and so is the output, so lines still end where they
+// should. Returns false, changing nothing, when stdin isn't a terminal.
+pub fn enter_cbreak() -> bool {
+    let stdin = stdin();
+    if !stdin.is_terminal() {
+        return false;
+    }
+    let Ok(original) = termios::tcgetattr(&stdin) else {
+        return false;
+    };
+    let mut cbreak = original.clone();
+    cbreak.local_modes -= LocalModes::ICANON | LocalModes::ECHO;
+    cbreak.special_codes[SpecialCodeIndex::VMIN] = 1;
+    cbreak.special_codes[SpecialCodeIndex::VTIME] = 0;
+    if termios::tcsetattr(&stdin, OptionalActions::Now, &cbreak).is_err() {
+        return false;
+    }
+    if let Ok(mut saved) = ORIGINAL.lock() {
+        *saved = Some(original);
+    }
+
+    // A panic would otherwise leave the shell without echo
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore();
+        hook(info);
+    }));
+    true
+}
+
+// Put back the terminal's settings from before enter_cbreak(), if it was
+// called. Safe to call more than once.
+pub fn restore() {
+    if let Ok(saved) = ORIGINAL.lock()
+        && let Some(original) = saved.as_ref()
+    {
+        let _ = termios::tcsetattr(stdin(), OptionalActions::Now, original);
+    }
+}
+
+// Run `read` with the terminal as it was before enter_cbreak(), so a whole
+// line can be typed with the usual echo and editing, and go back to cbreak
+// mode afterwards
+pub fn line_mode<T>(read: impl FnOnce() -> T) -> T {
+    let original = ORIGINAL.lock().ok().and_then(|saved| saved.clone());
+    let (Some(original), Ok(cbreak)) = (original, termios::tcgetattr(stdin())) else {
+        return read();
+    };
+    let _ = termios::tcsetattr(stdin(), OptionalActions::Now, &original);
+    let result = read();
+    let _ = termios::tcsetattr(stdin(), OptionalActions::Now, &cbreak);
+    result
+}
+
+// The next key typed, or None once stdin is closed. Bytes that aren't valid
+// UTF-8 come out as U+FFFD.
+pub fn read_key(input: &mut StdinLock) -> Option<char> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes[..1]).ok()?;
+    let length = match bytes[0] {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => 1,
+    };
+    input.read_exact(&mut bytes[1..length]).ok()?;
+    Some(
+        std::str::from_utf8(&bytes[..length])
+            .ok()
+            .and_then(|key| key.chars().next())
+            .unwrap_or(char::REPLACEMENT_CHARACTER),
+    )
+}
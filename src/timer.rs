@@ -0,0 +1,2553 @@
+use std::{fmt, time::Duration};
+
+use chrono::{NaiveDateTime, NaiveTime};
+use serde::Serialize;
+
+use crate::{
+    config::SleepPolicy,
+    duration::{duration_until, format_duration},
+    error::PomodoroError,
+};
+
+// Being away this much longer than expected means the process was stopped,
+// e.g. with Ctrl+Z, or the computer was asleep
+pub const SUSPEND_TOLERANCE: Duration = Duration::from_secs(3);
+
+// The kind of session the timer is in or about to start
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerState {
+    Work,
+    Break,
+    LongBreak,
+    // Stopped with the stop command, waiting to start a new cycle
+    Stopped,
+}
+
+impl TimerState {
+    // As shown by {state} in the countdown line
+    pub fn name(self) -> &'static str {
+        match self {
+            TimerState::Work => "Work",
+            TimerState::Break => "Break",
+            TimerState::LongBreak => "Long Break",
+            TimerState::Stopped => "Stopped",
+        }
+    }
+
+    // The state's name in the --json event stream
+    pub fn id(self) -> &'static str {
+        match self {
+            TimerState::Work => "work",
+            TimerState::Break => "break",
+            TimerState::LongBreak => "long_break",
+            TimerState::Stopped => "stopped",
+        }
+    }
+
+    // "Work Session 3", "Break 2", "Long Break 1"
+    pub fn label(self, number: u64) -> String {
+        match self {
+            TimerState::Work => format!("Work Session {}", number),
+            TimerState::Stopped => "Stopped".to_string(),
+            state => format!("{} {}", state.name(), number),
+        }
+    }
+}
+
+// Something the user asked the timer to do
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerCommand {
+    Pause,
+    Resume,
+    Skip,
+    Start,
+    OverrideNext(Duration),
+    // Make the running session this much longer
+    Extend(Duration),
+    // Start the running session over from the beginning
+    Restart,
+    // Cut the current session short and take a long break now
+    LongBreak,
+    // Take a short break in place of the session waiting to start, e.g.
+    // after a stop
+    TakeBreak,
+    // Skip the running or waiting break and start working straight away
+    SkipBreak,
+    // Take back the last skip, within UNDO_WINDOW of it
+    Undo,
+    // Make the running session NUDGE longer, or shorter
+    AddMinute,
+    RemoveMinute,
+    // Put off the next session for this long, or for this much longer if
+    // it's already snoozed
+    Snooze(Duration),
+    // Make sessions of each kind this long from the next one on, leaving
+    // the running one as it is
+    SetWork(Duration),
+    SetBreak(Duration),
+    SetLongBreak(Duration),
+    // Keep working this much longer on the work session that just
+    // finished, before its break
+    Overtime(Duration),
+    SaveConfig,
+    ToggleElapsed,
+    ToggleMute,
+    Status,
+    // Report progress towards the daily goal
+    Goal,
+    // Turn strict mode on or off
+    ToggleStrict,
+    Stop,
+    Quit,
+}
+
+// Where the timer is within the current session, named in snake case in a
+// status report's JSON
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    // The next session starts as soon as begin() is called
+    Ready,
+    // The next session waits for a Start command, as after Stop or in
+    // manual mode
+    Waiting,
+    Running,
+    Paused,
+    // The next session starts by itself once the snooze is over
+    Snoozed,
+    // Quit, or every requested cycle is done
+    Finished,
+}
+
+// How a session came to an end
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Completed,
+    Skipped,
+    // Stopped or quit part of the way through, or cut short for a long
+    // break
+    Abandoned,
+    // Started over, the time it ran for thrown away
+    Restarted,
+}
+
+impl Outcome {
+    pub const ALL: [Outcome; 4] = [
+        Outcome::Completed,
+        Outcome::Skipped,
+        Outcome::Abandoned,
+        Outcome::Restarted,
+    ];
+
+    // As written to the history file
+    pub fn id(self) -> &'static str {
+        match self {
+            Outcome::Completed => "completed",
+            Outcome::Skipped => "skipped",
+            Outcome::Abandoned => "abandoned",
+            Outcome::Restarted => "restarted",
+        }
+    }
+}
+
+// The classic Pomodoro rhythm, used for anything not configured
+pub const DEFAULT_WORK: Duration = Duration::from_secs(25 * 60);
+pub const DEFAULT_BREAK: Duration = Duration::from_secs(5 * 60);
+pub const DEFAULT_LONG_BREAK: Duration = Duration::from_secs(15 * 60);
+pub const DEFAULT_SESSIONS_UNTIL_LONG_BREAK: u64 = 4;
+pub const DEFAULT_SLEEP_THRESHOLD: Duration = Duration::from_secs(60);
+pub const DEFAULT_NAG_INTERVAL: Duration = Duration::from_secs(30);
+// What the extend key adds to the running session
+pub const DEFAULT_EXTEND: Duration = Duration::from_secs(5 * 60);
+// How long the snooze key puts off the next session
+pub const DEFAULT_SNOOZE: Duration = Duration::from_secs(5 * 60);
+
+// A session that started on its own can still be snoozed this long after
+// it started, for when the alarm went off unattended
+pub const SNOOZE_WINDOW: Duration = Duration::from_secs(60);
+
+// What AddMinute adds to the running session and RemoveMinute takes off
+pub const NUDGE: Duration = Duration::from_secs(60);
+
+// A skipped session can be brought back this long after the skip
+pub const UNDO_WINDOW: Duration = Duration::from_secs(10);
+
+// Reminders that a finished session is waiting stop after this many, so an
+// unattended timer doesn't go on all night
+pub const NAG_REPEATS: u32 = 10;
+
+// The rules the timer runs by, fixed for the whole run. Built and checked
+// with PomodoroConfig::builder().
+#[derive(Clone, Debug)]
+pub struct PomodoroConfig {
+    work: Duration,
+    break_: Duration,
+    long_break: Duration,
+    sessions_until_long_break: u64,
+    // Start each break, and each work session, without waiting for Enter
+    auto_start_breaks: bool,
+    auto_start_work: bool,
+    // Stop after this many completed work sessions
+    cycles: Option<u64>,
+    // Take the break after the last of `cycles`
+    final_break: bool,
+    // Run a single work session and nothing else
+    once: bool,
+    // Begin with a break rather than work
+    start_with_break: bool,
+    // The first work session ends at this time of day
+    until: Option<NaiveTime>,
+    // Time away from a session at least this long is handled by `on_sleep`,
+    // anything shorter doesn't count
+    sleep_threshold: Duration,
+    on_sleep: SleepPolicy,
+    // Remind this often that a finished session is waiting for the next
+    // one to be started
+    nag: Option<Duration>,
+    // Work sessions can't be paused, skipped, restarted or extended, only
+    // completed or abandoned
+    strict: bool,
+}
+
+impl PomodoroConfig {
+    pub fn builder() -> PomodoroConfigBuilder {
+        PomodoroConfigBuilder::default()
+    }
+
+    pub fn work(&self) -> Duration {
+        self.work
+    }
+
+    pub fn break_(&self) -> Duration {
+        self.break_
+    }
+
+    pub fn long_break(&self) -> Duration {
+        self.long_break
+    }
+
+    pub fn sessions_until_long_break(&self) -> u64 {
+        self.sessions_until_long_break
+    }
+
+    pub fn auto_start_breaks(&self) -> bool {
+        self.auto_start_breaks
+    }
+
+    pub fn auto_start_work(&self) -> bool {
+        self.auto_start_work
+    }
+
+    pub fn cycles(&self) -> Option<u64> {
+        self.cycles
+    }
+
+    pub fn final_break(&self) -> bool {
+        self.final_break
+    }
+
+    pub fn once(&self) -> bool {
+        self.once
+    }
+
+    pub fn nag(&self) -> Option<Duration> {
+        self.nag
+    }
+
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+}
+
+// Sets up a PomodoroConfig, anything left out keeps its default
+#[derive(Clone, Debug)]
+pub struct PomodoroConfigBuilder {
+    config: PomodoroConfig,
+}
+
+impl Default for PomodoroConfigBuilder {
+    fn default() -> Self {
+        PomodoroConfigBuilder {
+            config: PomodoroConfig {
+                work: DEFAULT_WORK,
+                break_: DEFAULT_BREAK,
+                long_break: DEFAULT_LONG_BREAK,
+                sessions_until_long_break: DEFAULT_SESSIONS_UNTIL_LONG_BREAK,
+                auto_start_breaks: true,
+                auto_start_work: true,
+                cycles: None,
+                final_break: true,
+                once: false,
+                start_with_break: false,
+                until: None,
+                sleep_threshold: DEFAULT_SLEEP_THRESHOLD,
+                on_sleep: SleepPolicy::default(),
+                nag: None,
+                strict: false,
+            },
+        }
+    }
+}
+
+impl PomodoroConfigBuilder {
+    pub fn work(mut self, work: Duration) -> Self {
+        self.config.work = work;
+        self
+    }
+
+    pub fn break_(mut self, break_: Duration) -> Self {
+        self.config.break_ = break_;
+        self
+    }
+
+    pub fn long_break(mut self, long_break: Duration) -> Self {
+        self.config.long_break = long_break;
+        self
+    }
+
+    pub fn sessions_until_long_break(mut self, sessions: u64) -> Self {
+        self.config.sessions_until_long_break = sessions;
+        self
+    }
+
+    // The same for breaks and work sessions
+    pub fn auto_start(self, auto_start: bool) -> Self {
+        self.auto_start_breaks(auto_start)
+            .auto_start_work(auto_start)
+    }
+
+    pub fn auto_start_breaks(mut self, auto_start: bool) -> Self {
+        self.config.auto_start_breaks = auto_start;
+        self
+    }
+
+    pub fn auto_start_work(mut self, auto_start: bool) -> Self {
+        self.config.auto_start_work = auto_start;
+        self
+    }
+
+    pub fn cycles(mut self, cycles: Option<u64>) -> Self {
+        self.config.cycles = cycles;
+        self
+    }
+
+    pub fn final_break(mut self, final_break: bool) -> Self {
+        self.config.final_break = final_break;
+        self
+    }
+
+    pub fn once(mut self, once: bool) -> Self {
+        self.config.once = once;
+        self
+    }
+
+    pub fn start_with_break(mut self, start_with_break: bool) -> Self {
+        self.config.start_with_break = start_with_break;
+        self
+    }
+
+    pub fn until(mut self, until: Option<NaiveTime>) -> Self {
+        self.config.until = until;
+        self
+    }
+
+    pub fn sleep_threshold(mut self, sleep_threshold: Duration) -> Self {
+        self.config.sleep_threshold = sleep_threshold;
+        self
+    }
+
+    pub fn on_sleep(mut self, on_sleep: SleepPolicy) -> Self {
+        self.config.on_sleep = on_sleep;
+        self
+    }
+
+    pub fn nag(mut self, interval: Option<Duration>) -> Self {
+        self.config.nag = interval;
+        self
+    }
+
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.config.strict = strict;
+        self
+    }
+
+    // Check that the settings make a timer that can actually run
+    pub fn build(self) -> Result<PomodoroConfig, PomodoroError> {
+        let config = self.config;
+        for (name, duration) in [
+            ("work", config.work),
+            ("break", config.break_),
+            ("long break", config.long_break),
+            ("sleep threshold", config.sleep_threshold),
+        ]
+        .into_iter()
+        .chain(config.nag.map(|interval| ("nag interval", interval)))
+        {
+            if duration < Duration::from_secs(1) {
+                return Err(PomodoroError::InvalidConfig(format!(
+                    "{} duration must be at least one second, got {}",
+                    name,
+                    format_duration(duration)
+                )));
+            }
+        }
+        if config.sessions_until_long_break == 0 {
+            return Err(PomodoroError::InvalidConfig(
+                "sessions until long break must be at least 1".to_string(),
+            ));
+        }
+        match config.cycles {
+            Some(0) => {
+                return Err(PomodoroError::InvalidConfig(
+                    "cycles must be at least 1".to_string(),
+                ));
+            }
+            Some(_) if config.once => {
+                return Err(PomodoroError::InvalidConfig(
+                    "a single session and a number of cycles can't be combined".to_string(),
+                ));
+            }
+            _ => {}
+        }
+        Ok(config)
+    }
+}
+
+// Running totals for the whole run
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Counts {
+    pub completed_pomodoros: u64,
+    pub breaks_taken: u64,
+    pub skipped_sessions: u64,
+    // Sessions stopped, quit or cut short part of the way through
+    pub abandoned_sessions: u64,
+    pub restarted_sessions: u64,
+    // Time spent in work sessions, including ones cut short
+    pub focused: Duration,
+    // Time spent paused, in any kind of session
+    pub paused: Duration,
+    // Time spent waiting for a session to be started
+    pub idle: Duration,
+    // Time thrown away by restarting sessions, which isn't focused time
+    pub interrupted: Duration,
+    // Time spent snoozing before sessions
+    pub snoozed: Duration,
+}
+
+// The session that is running, or the next one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Session {
+    pub state: TimerState,
+    // Work sessions and breaks are numbered separately since the last stop
+    // or long break taken early
+    pub number: u64,
+    pub length: Duration,
+}
+
+impl Session {
+    pub fn label(&self) -> String {
+        self.state.label(self.number)
+    }
+}
+
+// Enough of the running session to take it up again in a later run, when
+// the one it was running in ended without warning
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub session: Session,
+    pub elapsed: Duration,
+    // Time the session has spent paused
+    pub paused: Duration,
+    // The run's totals, leaving out the session itself
+    pub counts: Counts,
+    // What the numbering and the long break go by, see PomodoroTimer
+    pub cycle_pomodoros: u64,
+    pub cycle_breaks: u64,
+}
+
+// A session that has just ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SessionEnd {
+    pub session: Session,
+    pub outcome: Outcome,
+    pub elapsed: Duration,
+    // Added to it with Extend, already part of the session's length
+    pub extended: Duration,
+    // Net seconds added with AddMinute and RemoveMinute, negative when more
+    // was taken off, also part of the length already
+    pub nudged: i64,
+    // How many times it was paused, and for how long in all
+    pub pauses: u32,
+    pub paused: Duration,
+    // Taken up from the state file a timer that didn't get to quit left
+    pub resumed: bool,
+}
+
+// Something the timer did, passed to every listener in the order it
+// happened. Commands that change nothing don't produce events, their
+// feedback comes from the Reply instead.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerEvent {
+    // `counts` are the totals so far, before this session. `resumed` is
+    // set for a session taken up where a timer that didn't get to quit left
+    // it, see PomodoroTimer::resume().
+    SessionStarted {
+        kind: TimerState,
+        number: u64,
+        duration: Duration,
+        counts: Counts,
+        resumed: bool,
+    },
+    // Once when a session starts and then for every whole second that
+    // passes, until the last one
+    Tick {
+        remaining: Duration,
+    },
+    // `away` is set when the timer paused itself after a gap, e.g. the
+    // computer sleeping, so the user can decide whether the time counts
+    Paused {
+        away: Option<Duration>,
+    },
+    // For every whole second spent paused, with how long that's been so far
+    // and what was left of the session at its last tick
+    PausedTick {
+        paused: Duration,
+        remaining: Duration,
+    },
+    // `counted` is set when resuming counted that gap towards the session
+    Resumed {
+        counted: Option<Duration>,
+    },
+    // The timer was away for a while and dealt with it as `policy` says.
+    // Ask is reported as Paused instead.
+    Suspended {
+        away: Duration,
+        policy: SleepPolicy,
+    },
+    // Sent for every session that ends, however it ends, before the event
+    // that reports it in full
+    SessionEnded {
+        end: SessionEnd,
+    },
+    // `next` is the session that follows, None when the run is over
+    SessionCompleted {
+        kind: TimerState,
+        number: u64,
+        next: Option<Session>,
+    },
+    SessionSkipped {
+        kind: TimerState,
+        number: u64,
+    },
+    // The running session was abandoned after the timer was away, and the
+    // next one follows as it would after a skip
+    SessionAbandoned {
+        kind: TimerState,
+        number: u64,
+    },
+    // The running session started over, with the time it had run for
+    // counted as interrupted. It's running again, even if it was paused.
+    Restarted {
+        kind: TimerState,
+        number: u64,
+        duration: Duration,
+    },
+    // The skip of the session was undone and it carries on where it was
+    // skipped, running again even if it was paused, with `counts` as they
+    // were before the skip
+    SkipUndone {
+        kind: TimerState,
+        number: u64,
+        duration: Duration,
+        remaining: Duration,
+        counts: Counts,
+    },
+    // The running session was cut short for a long break, with the work
+    // it had done counted as interrupted
+    Interrupted {
+        kind: TimerState,
+        number: u64,
+    },
+    // `next` is put off until the snooze is over, `remaining` from now.
+    // Sent when the snooze starts and whenever it's made longer.
+    Snoozed {
+        next: Session,
+        remaining: Duration,
+    },
+    // For every whole second of a snooze, with what's left of it
+    SnoozeTick {
+        remaining: Duration,
+    },
+    // The running session was made longer by `added`, and now lasts
+    // `duration` in all
+    Extended {
+        added: Duration,
+        duration: Duration,
+    },
+    // The running session was made a minute longer or shorter, and now
+    // lasts `duration` with `remaining` left as of its last tick
+    Nudged {
+        duration: Duration,
+        remaining: Duration,
+    },
+    // What follows the running session if it runs its course, None when
+    // the run ends with it. Sent as the session starts, before its first
+    // tick, and again whenever that changes.
+    Upcoming {
+        next: Option<Session>,
+    },
+    // A finished session is still waiting for the next one to be started,
+    // reminding for the `repeat`th time
+    Reminder {
+        repeat: u32,
+    },
+    // A command came in while reminding, which stops the reminders
+    Acknowledged,
+    // Strict mode was turned on or off
+    StrictToggled {
+        strict: bool,
+    },
+    // Stopped until the user starts a new cycle, with the totals so far
+    Stopped {
+        counts: Counts,
+    },
+    // The run is over, whether quit or after the last of --cycles
+    Finished,
+}
+
+// Called with every event, see PomodoroTimer::subscribe()
+pub type Listener = Box<dyn FnMut(&TimerEvent) + Send>;
+
+// What a command did, so the frontend can report it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reply {
+    // A waiting session or a stopped timer was started
+    Started,
+    Paused,
+    Resumed,
+    // Resumed after a gap, counting that time towards the session
+    ResumedCounting(Duration),
+    // A break that was waiting to start was skipped
+    SkippedBreak,
+    NextLength(Duration),
+    // The finished work session goes on for this much longer
+    Overtime(Duration),
+    // The running session was made this much longer
+    Extended(Duration),
+    // The running session started over
+    Restarted,
+    // The running session was made a minute longer or shorter
+    Nudged,
+    // The last skip was taken back
+    Undone,
+    // Strict mode doesn't let the work session be divided that way
+    Strict,
+    // Strict mode is now on, or off
+    StrictToggled(bool),
+    // A long break starts straight away
+    LongBreak,
+    // The next session was put off, or put off for this much longer
+    Snoozed(Duration),
+    // Sessions of this kind last this long from the next one on
+    LengthSet(TimerState, Duration),
+    // The frontend should write the settings to the config file
+    SaveConfig,
+    // The frontend should switch between showing the time remaining and
+    // the time elapsed
+    ToggleElapsed,
+    // The frontend should mute the sound, or turn it back on
+    ToggleMute,
+    // The frontend should report the timer's status
+    Status,
+    // The frontend should report progress towards the daily goal
+    Goal,
+    // The timer was stopped while no session was running
+    Stopped,
+    // Quit while no session was running
+    Quit,
+    // The running session was skipped, or abandoned by stopping or quitting
+    Ended(SessionEnd),
+    // The command doesn't apply in the current phase
+    Ignored,
+}
+
+// The Pomodoro state machine. It doesn't measure time itself: the caller
+// reports how much time has passed with advance(), which keeps it free of
+// threads and clocks. What happens is reported to the listeners added with
+// subscribe().
+pub struct PomodoroTimer {
+    config: PomodoroConfig,
+    state: TimerState,
+    phase: Phase,
+    length: Duration,
+    elapsed: Duration,
+    // How much of `length` was added with Extend
+    extended: Duration,
+    // Net seconds of it added with AddMinute and RemoveMinute
+    nudged: i64,
+    next_override: Option<Duration>,
+    until: Option<NaiveTime>,
+    // Time the timer was away for, offered to the user while paused
+    pending_gap: Option<Duration>,
+    counts: Counts,
+    // The same counts since the last stop, which decide the numbering and
+    // when the long break comes
+    cycle_pomodoros: u64,
+    cycle_breaks: u64,
+    // Ticks sent for the running session, one per whole second of it
+    ticks: u64,
+    // How long the timer has been paused this time
+    paused_for: Duration,
+    // The running session's pauses so far, and the time spent in them
+    session_pauses: u32,
+    session_paused: Duration,
+    // The break that's next or running follows a completed work session,
+    // which can still be given overtime
+    extendable: bool,
+    // Working overtime, with the break to go back to afterwards
+    overtime: Option<TimerState>,
+    // The next session is put off until the snooze is over, with how long
+    // the snooze lasts in all and how much of it has gone by
+    snooze: Duration,
+    snoozed_for: Duration,
+    // The running session started on its own after the one before it
+    // finished, so it can still be snoozed for a while
+    snoozable: bool,
+    // The length of a session taken back by snoozing, which it keeps
+    snoozed_length: Option<Duration>,
+    // Reminding that the next session is waiting, with how long it has
+    // waited and how many reminders have gone out
+    nagging: bool,
+    waited: Duration,
+    reminders: u32,
+    // Starts out as the config says, and can be turned on and off
+    strict: bool,
+    // The next or running session is taken up from a checkpoint, until it
+    // ends
+    resumed: bool,
+    // The session last skipped, until UNDO_WINDOW has gone by or something
+    // else is done
+    skipped: Option<Skipped>,
+    listeners: Vec<Listener>,
+}
+
+// What the timer was like just before a skip, to go back to if it's undone
+#[derive(Clone, Copy, Debug)]
+struct Skipped {
+    state: TimerState,
+    length: Duration,
+    elapsed: Duration,
+    extended: Duration,
+    nudged: i64,
+    session_pauses: u32,
+    session_paused: Duration,
+    next_override: Option<Duration>,
+    counts: Counts,
+    cycle_pomodoros: u64,
+    cycle_breaks: u64,
+    ticks: u64,
+    extendable: bool,
+    overtime: Option<TimerState>,
+    snoozable: bool,
+    snoozed_length: Option<Duration>,
+    resumed: bool,
+    // Time since the skip
+    age: Duration,
+}
+
+// Everything that decides what the timer does next, leaving out the
+// listeners, for the property tests to show the state a shrunk run of
+// steps left the timer in
+impl fmt::Debug for PomodoroTimer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PomodoroTimer")
+            .field("state", &self.state)
+            .field("phase", &self.phase)
+            .field("length", &self.length)
+            .field("elapsed", &self.elapsed)
+            .field("extended", &self.extended)
+            .field("nudged", &self.nudged)
+            .field("next_override", &self.next_override)
+            .field("pending_gap", &self.pending_gap)
+            .field("counts", &self.counts)
+            .field("cycle_pomodoros", &self.cycle_pomodoros)
+            .field("cycle_breaks", &self.cycle_breaks)
+            .field("overtime", &self.overtime)
+            .field("snooze", &self.snooze)
+            .field("snoozed_for", &self.snoozed_for)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PomodoroTimer {
+    pub fn new(config: PomodoroConfig) -> Self {
+        let state = if config.start_with_break {
+            TimerState::Break
+        } else {
+            TimerState::Work
+        };
+        let until = config.until;
+        let strict = config.strict;
+        let mut timer = PomodoroTimer {
+            config,
+            state,
+            phase: Phase::Ready,
+            length: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            extended: Duration::ZERO,
+            nudged: 0,
+            next_override: None,
+            until,
+            pending_gap: None,
+            counts: Counts::default(),
+            cycle_pomodoros: 0,
+            cycle_breaks: 0,
+            ticks: 0,
+            paused_for: Duration::ZERO,
+            session_pauses: 0,
+            session_paused: Duration::ZERO,
+            extendable: false,
+            overtime: None,
+            snooze: Duration::ZERO,
+            snoozed_for: Duration::ZERO,
+            snoozable: false,
+            snoozed_length: None,
+            nagging: false,
+            waited: Duration::ZERO,
+            reminders: 0,
+            strict,
+            resumed: false,
+            skipped: None,
+            listeners: Vec::new(),
+        };
+        timer.length = timer.default_length();
+        timer
+    }
+
+    // Call `listener` with every event from now on
+    pub fn subscribe(&mut self, listener: impl FnMut(&TimerEvent) + Send + 'static) {
+        self.listeners.push(Box::new(listener));
+    }
+
+    pub fn config(&self) -> &PomodoroConfig {
+        &self.config
+    }
+
+    pub fn state(&self) -> TimerState {
+        self.state
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    // The work session is overtime added after a pomodoro
+    pub fn in_overtime(&self) -> bool {
+        self.overtime.is_some()
+    }
+
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    pub fn counts(&self) -> Counts {
+        self.counts
+    }
+
+    pub fn session(&self) -> Session {
+        let number = match self.state {
+            // Overtime carries on the pomodoro that was just counted
+            TimerState::Work if self.overtime.is_some() => self.cycle_pomodoros,
+            TimerState::Work => self.cycle_pomodoros + 1,
+            TimerState::Break | TimerState::LongBreak => self.cycle_breaks + 1,
+            TimerState::Stopped => 0,
+        };
+        Session {
+            state: self.state,
+            number,
+            length: self.length,
+        }
+    }
+
+    // The session that follows the current one if it runs its course, None
+    // when the run ends with it or the timer is stopped. Worked out the same
+    // way as when the session ends, so this is what will happen unless
+    // something changes in the meantime.
+    pub fn next(&self) -> Option<Session> {
+        let (state, finished) = self.successor(true);
+        if finished || state == TimerState::Stopped {
+            return None;
+        }
+        let number = match state {
+            TimerState::Work => self.cycle_pomodoros + 1,
+            _ => self.cycle_breaks + 1,
+        };
+        // Set while a session runs, the override is for the one after it
+        let length = match self.phase {
+            Phase::Running | Phase::Paused => self.next_override,
+            _ => None,
+        };
+        Some(Session {
+            state,
+            number,
+            length: length.unwrap_or(self.length_of(state)),
+        })
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    // The running or paused session as it is, None between sessions and
+    // while working overtime, which is nothing to come back to
+    pub fn checkpoint(&self) -> Option<Checkpoint> {
+        if !matches!(self.phase, Phase::Running | Phase::Paused) || self.overtime.is_some() {
+            return None;
+        }
+        Some(Checkpoint {
+            session: self.session(),
+            elapsed: self.elapsed,
+            paused: self.session_paused,
+            counts: self.counts,
+            cycle_pomodoros: self.cycle_pomodoros,
+            cycle_breaks: self.cycle_breaks,
+        })
+    }
+
+    // Carry on from `checkpoint` instead of the first session, before the
+    // first begin(). The session starts again with the time it had left,
+    // and the time it had run counts towards the run's totals.
+    pub fn resume(&mut self, checkpoint: Checkpoint) {
+        let remaining = checkpoint.session.length.saturating_sub(checkpoint.elapsed);
+        if self.phase != Phase::Ready
+            || checkpoint.session.state == TimerState::Stopped
+            || remaining.is_zero()
+        {
+            return;
+        }
+        self.state = checkpoint.session.state;
+        self.counts = checkpoint.counts;
+        if self.state == TimerState::Work {
+            self.counts.focused += checkpoint.elapsed;
+        }
+        self.cycle_pomodoros = checkpoint.cycle_pomodoros;
+        self.cycle_breaks = checkpoint.cycle_breaks;
+        self.next_override = Some(remaining);
+        self.until = None;
+        self.resumed = true;
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.length.saturating_sub(self.elapsed)
+    }
+
+    // What's left of the snooze, zero unless snoozed
+    pub fn snooze_remaining(&self) -> Duration {
+        match self.phase {
+            Phase::Snoozed => self.snooze.saturating_sub(self.snoozed_for),
+            _ => Duration::ZERO,
+        }
+    }
+
+    // How much more time advance() needs for the next tick or the end of the
+    // session, whichever comes first. While paused, the next paused tick,
+    // and while snoozed the next snooze tick or the end of the snooze.
+    pub fn until_next_tick(&self) -> Duration {
+        if self.phase == Phase::Paused {
+            return Duration::from_secs(self.paused_for.as_secs() + 1) - self.paused_for;
+        }
+        if self.phase == Phase::Snoozed {
+            return Duration::from_secs(self.snoozed_for.as_secs() + 1).min(self.snooze)
+                - self.snoozed_for;
+        }
+        Duration::from_secs(self.ticks)
+            .saturating_sub(self.elapsed)
+            .min(self.remaining())
+    }
+
+    // How much more time advance() needs for the next reminder that a
+    // finished session is waiting, None when there's no reminder to come
+    pub fn until_reminder(&self) -> Option<Duration> {
+        let interval = self.config.nag.filter(|_| self.nagging)?;
+        Some((interval * (self.reminders + 1)).saturating_sub(self.waited))
+    }
+
+    // Start the next session. --until only shapes the first work session,
+    // and a length set with the override command wins over everything but
+    // overtime, which is as long as was asked for and leaves the override
+    // to the break after it. A session taken back by snoozing keeps the
+    // length it started with.
+    pub fn begin(&mut self, now: NaiveDateTime) {
+        if self.phase != Phase::Ready {
+            return;
+        }
+        let length = match (self.state, self.until) {
+            _ if self.overtime.is_some() => self.length,
+            (TimerState::Work, Some(end)) => {
+                self.until = None;
+                duration_until(now, end)
+            }
+            _ => self.default_length(),
+        };
+        let snoozed_length = self.snoozed_length.take();
+        self.length = match (self.overtime, snoozed_length) {
+            (Some(_), _) => length,
+            (None, Some(snoozed_length)) => snoozed_length,
+            (None, None) => self.next_override.take().unwrap_or(length),
+        };
+        self.elapsed = Duration::ZERO;
+        self.extended = Duration::ZERO;
+        self.nudged = 0;
+        self.session_pauses = 0;
+        self.session_paused = Duration::ZERO;
+        self.ticks = 0;
+        self.phase = Phase::Running;
+        self.emit(TimerEvent::SessionStarted {
+            kind: self.state,
+            number: self.session().number,
+            duration: self.length,
+            counts: self.counts,
+            resumed: self.resumed,
+        });
+        self.emit(TimerEvent::Upcoming { next: self.next() });
+        self.tick();
+    }
+
+    // Count `elapsed` towards the running session, ending it when its time
+    // is up. For a paused timer it only adds to the time spent paused, and
+    // for a waiting one to the time spent idle. A snoozed one gets closer
+    // to the end of the snooze, and is ready to begin once it's over.
+    pub fn advance(&mut self, elapsed: Duration) -> Option<SessionEnd> {
+        if let Some(skipped) = &mut self.skipped {
+            skipped.age += elapsed;
+            if skipped.age > UNDO_WINDOW {
+                self.skipped = None;
+            }
+        }
+        match self.phase {
+            Phase::Running => {}
+            Phase::Paused => {
+                self.counts.paused += elapsed;
+                self.session_paused += elapsed;
+                let seconds = self.paused_for.as_secs();
+                self.paused_for += elapsed;
+                // A long wait gets a single tick, there's nothing to catch
+                // up on
+                if self.paused_for.as_secs() > seconds {
+                    let ticked = Duration::from_secs(self.ticks.saturating_sub(1));
+                    self.emit(TimerEvent::PausedTick {
+                        paused: self.paused_for,
+                        remaining: self.length.saturating_sub(ticked),
+                    });
+                }
+                return None;
+            }
+            Phase::Waiting => {
+                self.counts.idle += elapsed;
+                self.remind(elapsed);
+                return None;
+            }
+            Phase::Snoozed => {
+                let before = self.snoozed_for;
+                self.snoozed_for = (before + elapsed).min(self.snooze);
+                self.counts.snoozed += self.snoozed_for - before;
+                if self.snoozed_for >= self.snooze {
+                    self.phase = Phase::Ready;
+                } else if self.snoozed_for.as_secs() > before.as_secs() {
+                    let ticked = Duration::from_secs(self.snoozed_for.as_secs());
+                    self.emit(TimerEvent::SnoozeTick {
+                        remaining: self.snooze - ticked,
+                    });
+                }
+                return None;
+            }
+            Phase::Ready | Phase::Finished => return None,
+        }
+        self.elapsed = (self.elapsed + elapsed).min(self.length);
+        if self.elapsed >= self.length {
+            return Some(self.end_session(Outcome::Completed));
+        }
+        // A long wait catches up one second at a time, so a countdown never
+        // skips a value
+        while Duration::from_secs(self.ticks) <= self.elapsed {
+            self.tick();
+        }
+        None
+    }
+
+    // Deal with time the caller couldn't account for, e.g. the process was
+    // stopped or the computer was asleep, which advance() shouldn't count.
+    // Long gaps follow the sleep policy, where asking means pausing so the
+    // user can decide: Start counts the gap, Resume leaves it out and Skip
+    // abandons the session.
+    pub fn away(&mut self, away: Duration) -> Option<SessionEnd> {
+        if self.phase != Phase::Running {
+            return None;
+        }
+        if away < self.config.sleep_threshold {
+            if away > SUSPEND_TOLERANCE {
+                self.emit(TimerEvent::Suspended {
+                    away,
+                    policy: SleepPolicy::Discard,
+                });
+            }
+            return None;
+        }
+
+        let policy = self.config.on_sleep;
+        if policy != SleepPolicy::Ask {
+            self.emit(TimerEvent::Suspended { away, policy });
+        }
+        match policy {
+            SleepPolicy::Count => self.advance(away),
+            SleepPolicy::Discard => None,
+            SleepPolicy::Abandon => Some(self.end_session(Outcome::Abandoned)),
+            SleepPolicy::Ask => {
+                self.phase = Phase::Paused;
+                self.session_pauses += 1;
+                self.paused_for = Duration::ZERO;
+                self.pending_gap = Some(away);
+                self.emit(TimerEvent::Paused { away: Some(away) });
+                None
+            }
+        }
+    }
+
+    pub fn handle(&mut self, command: TimerCommand) -> Reply {
+        use TimerCommand::*;
+
+        // Any command at all means someone is there
+        if self.nagging {
+            self.nagging = false;
+            self.emit(TimerEvent::Acknowledged);
+        }
+        // Anything but asking about the timer puts the skip out of reach
+        let skipped = self.skipped.take();
+        if matches!(
+            command,
+            SaveConfig | ToggleElapsed | ToggleMute | Status | Goal
+        ) {
+            self.skipped = skipped;
+        }
+
+        match (self.phase, command) {
+            (Phase::Finished, _) => Reply::Ignored,
+            (_, Undo) => match skipped {
+                Some(skipped) => self.undo(skipped),
+                None => Reply::Ignored,
+            },
+            // Abandoning the session when asked about the time away, which
+            // even strict mode allows
+            (Phase::Paused, Skip) if self.pending_gap.is_some() => {
+                Reply::Ended(self.end_session(Outcome::Abandoned))
+            }
+            // A pomodoro runs its course or is abandoned, though the overtime
+            // after one can be cut short
+            (
+                Phase::Running | Phase::Paused,
+                Pause | Skip | Restart | Extend(_) | AddMinute | RemoveMinute,
+            ) if self.strict && self.state == TimerState::Work && self.overtime.is_none() => {
+                Reply::Strict
+            }
+            (_, ToggleStrict) => {
+                self.strict = !self.strict;
+                self.emit(TimerEvent::StrictToggled {
+                    strict: self.strict,
+                });
+                Reply::StrictToggled(self.strict)
+            }
+            (phase, OverrideNext(length)) => {
+                self.next_override = Some(length);
+                if matches!(phase, Phase::Running | Phase::Paused) {
+                    self.emit(TimerEvent::Upcoming { next: self.next() });
+                }
+                Reply::NextLength(length)
+            }
+            // The break, even one already under way, waits until after
+            // the overtime, which starts straight away
+            (_, Overtime(length))
+                if self.extendable
+                    && matches!(self.state, TimerState::Break | TimerState::LongBreak) =>
+            {
+                self.extendable = false;
+                self.snoozable = false;
+                self.overtime = Some(self.state);
+                self.state = TimerState::Work;
+                self.length = length;
+                self.elapsed = Duration::ZERO;
+                self.pending_gap = None;
+                self.phase = Phase::Ready;
+                Reply::Overtime(length)
+            }
+            (Phase::Running | Phase::Paused, Extend(added)) => {
+                self.length += added;
+                self.extended += added;
+                self.emit(TimerEvent::Extended {
+                    added,
+                    duration: self.length,
+                });
+                Reply::Extended(added)
+            }
+            (Phase::Running | Phase::Paused, AddMinute) => {
+                self.length += NUDGE;
+                self.nudged += NUDGE.as_secs() as i64;
+                self.emit_nudged();
+                Reply::Nudged
+            }
+            // Taking off more than is left finishes the session
+            (Phase::Running | Phase::Paused, RemoveMinute) if self.remaining() <= NUDGE => {
+                self.nudged -= self.remaining().as_secs() as i64;
+                self.length = self.elapsed;
+                Reply::Ended(self.end_session(Outcome::Completed))
+            }
+            (Phase::Running | Phase::Paused, RemoveMinute) => {
+                self.length -= NUDGE;
+                self.nudged -= NUDGE.as_secs() as i64;
+                self.emit_nudged();
+                Reply::Nudged
+            }
+            (Phase::Running | Phase::Paused, Restart) => {
+                self.ended(Outcome::Restarted);
+                self.counts.interrupted += self.elapsed;
+                self.snoozable = false;
+                self.elapsed = Duration::ZERO;
+                self.session_pauses = 0;
+                self.session_paused = Duration::ZERO;
+                self.ticks = 0;
+                self.pending_gap = None;
+                self.phase = Phase::Running;
+                let session = self.session();
+                self.emit(TimerEvent::Restarted {
+                    kind: session.state,
+                    number: session.number,
+                    duration: self.length,
+                });
+                self.tick();
+                Reply::Restarted
+            }
+            (Phase::Running | Phase::Paused, LongBreak) if self.state == TimerState::LongBreak => {
+                Reply::Ignored
+            }
+            // The long break comes a full cycle after this one, and the
+            // sessions after it are numbered afresh as after a stop
+            (phase, LongBreak) => {
+                if matches!(phase, Phase::Running | Phase::Paused) {
+                    self.ended(Outcome::Abandoned);
+                    if self.state == TimerState::Work {
+                        self.counts.interrupted += self.elapsed;
+                    }
+                    let session = self.session();
+                    self.emit(TimerEvent::Interrupted {
+                        kind: session.state,
+                        number: session.number,
+                    });
+                }
+                self.state = TimerState::LongBreak;
+                self.extendable = false;
+                self.snoozable = false;
+                self.snoozed_length = None;
+                self.overtime = None;
+                self.pending_gap = None;
+                self.cycle_pomodoros = 0;
+                self.cycle_breaks = 0;
+                self.length = self.default_length();
+                self.elapsed = Duration::ZERO;
+                self.phase = Phase::Ready;
+                Reply::LongBreak
+            }
+            (Phase::Waiting, Snooze(length)) if self.state != TimerState::Stopped => {
+                self.start_snooze(length)
+            }
+            (Phase::Snoozed, Snooze(added)) => {
+                self.snooze += added;
+                self.emit(TimerEvent::Snoozed {
+                    next: self.session(),
+                    remaining: self.snooze - self.snoozed_for,
+                });
+                Reply::Snoozed(added)
+            }
+            // The session that just started on its own goes back to waiting
+            // for the snooze, and the little it ran for counts as snoozed
+            (Phase::Running | Phase::Paused, Snooze(length))
+                if self.snoozable && self.elapsed < SNOOZE_WINDOW =>
+            {
+                self.counts.snoozed += self.elapsed;
+                self.snoozed_length = Some(self.length);
+                self.elapsed = Duration::ZERO;
+                self.pending_gap = None;
+                self.start_snooze(length)
+            }
+            (_, SetWork(length)) => self.set_length(TimerState::Work, length),
+            (_, SetBreak(length)) => self.set_length(TimerState::Break, length),
+            (_, SetLongBreak(length)) => self.set_length(TimerState::LongBreak, length),
+            (_, SaveConfig) => Reply::SaveConfig,
+            (_, ToggleElapsed) => Reply::ToggleElapsed,
+            (_, ToggleMute) => Reply::ToggleMute,
+            (_, Status) => Reply::Status,
+            (_, Goal) => Reply::Goal,
+
+            (Phase::Running | Phase::Paused, Skip) => {
+                let skipped = self.snapshot();
+                let end = self.end_session(Outcome::Skipped);
+                self.skipped = Some(skipped);
+                Reply::Ended(end)
+            }
+            (Phase::Running | Phase::Paused, Stop) => {
+                let end = self.cut_short();
+                self.stop();
+                Reply::Ended(end)
+            }
+            (Phase::Running | Phase::Paused, Quit) => {
+                let end = self.cut_short();
+                self.finish();
+                Reply::Ended(end)
+            }
+            (Phase::Running, Pause) => {
+                self.phase = Phase::Paused;
+                self.session_pauses += 1;
+                self.paused_for = Duration::ZERO;
+                self.emit(TimerEvent::Paused { away: None });
+                Reply::Paused
+            }
+            (Phase::Paused, Start) if self.pending_gap.is_some() => {
+                let gap = self.pending_gap.take().unwrap_or_default();
+                self.phase = Phase::Running;
+                self.emit(TimerEvent::Resumed { counted: Some(gap) });
+                match self.advance(gap) {
+                    Some(end) => Reply::Ended(end),
+                    None => Reply::ResumedCounting(gap),
+                }
+            }
+            (Phase::Paused, Resume) => {
+                self.pending_gap = None;
+                self.phase = Phase::Running;
+                self.emit(TimerEvent::Resumed { counted: None });
+                Reply::Resumed
+            }
+
+            (Phase::Ready | Phase::Waiting | Phase::Snoozed, Start) => {
+                if self.state == TimerState::Stopped {
+                    self.state = TimerState::Work;
+                    self.length = self.default_length();
+                }
+                // Started by hand, so there's nothing to take back
+                self.snoozable = false;
+                self.phase = Phase::Ready;
+                Reply::Started
+            }
+            (Phase::Ready | Phase::Waiting | Phase::Snoozed, TakeBreak) => {
+                self.extendable = false;
+                self.snoozable = false;
+                self.snoozed_length = None;
+                self.overtime = None;
+                self.state = TimerState::Break;
+                self.length = self.default_length();
+                self.phase = Phase::Ready;
+                Reply::Started
+            }
+            (Phase::Ready | Phase::Waiting | Phase::Snoozed, Skip | SkipBreak)
+                if matches!(self.state, TimerState::Break | TimerState::LongBreak) =>
+            {
+                self.skip_waiting_break()
+            }
+            // Work starts even when it would otherwise wait to be started
+            (Phase::Running | Phase::Paused, SkipBreak)
+                if matches!(self.state, TimerState::Break | TimerState::LongBreak) =>
+            {
+                let skipped = self.snapshot();
+                let end = self.end_session(Outcome::Skipped);
+                if self.phase == Phase::Waiting && self.state == TimerState::Work {
+                    self.phase = Phase::Ready;
+                }
+                self.skipped = Some(skipped);
+                Reply::Ended(end)
+            }
+            (Phase::Ready | Phase::Waiting | Phase::Snoozed, Stop)
+                if self.state != TimerState::Stopped =>
+            {
+                self.stop();
+                Reply::Stopped
+            }
+            (Phase::Ready | Phase::Waiting | Phase::Snoozed, Quit) => {
+                self.finish();
+                Reply::Quit
+            }
+
+            (
+                _,
+                Pause | Resume | Skip | Start | Stop | Extend(_) | AddMinute | RemoveMinute
+                | Restart | TakeBreak | SkipBreak | Snooze(_) | Overtime(_),
+            ) => Reply::Ignored,
+        }
+    }
+
+    // The drawn countdown moves by the nudge straight away, and the ticks
+    // carry on from where they were, as they count from the session start
+    fn emit_nudged(&mut self) {
+        let ticked = Duration::from_secs(self.ticks.saturating_sub(1));
+        self.emit(TimerEvent::Nudged {
+            duration: self.length,
+            remaining: self.length.saturating_sub(ticked),
+        });
+    }
+
+    // The running session as it is, for undoing a skip of it
+    fn snapshot(&self) -> Skipped {
+        Skipped {
+            state: self.state,
+            length: self.length,
+            elapsed: self.elapsed,
+            extended: self.extended,
+            nudged: self.nudged,
+            session_pauses: self.session_pauses,
+            session_paused: self.session_paused,
+            next_override: self.next_override,
+            counts: self.counts,
+            cycle_pomodoros: self.cycle_pomodoros,
+            cycle_breaks: self.cycle_breaks,
+            ticks: self.ticks,
+            extendable: self.extendable,
+            overtime: self.overtime,
+            snoozable: self.snoozable,
+            snoozed_length: self.snoozed_length,
+            resumed: self.resumed,
+            age: Duration::ZERO,
+        }
+    }
+
+    // Go back to the skipped session and carry on with it, as though it had
+    // never been skipped. Whatever started after it is dropped.
+    fn undo(&mut self, skipped: Skipped) -> Reply {
+        self.state = skipped.state;
+        self.length = skipped.length;
+        self.elapsed = skipped.elapsed;
+        self.extended = skipped.extended;
+        self.nudged = skipped.nudged;
+        self.session_pauses = skipped.session_pauses;
+        self.session_paused = skipped.session_paused;
+        self.next_override = skipped.next_override;
+        self.counts = skipped.counts;
+        self.cycle_pomodoros = skipped.cycle_pomodoros;
+        self.cycle_breaks = skipped.cycle_breaks;
+        self.ticks = skipped.ticks;
+        self.extendable = skipped.extendable;
+        self.overtime = skipped.overtime;
+        self.snoozable = skipped.snoozable;
+        self.snoozed_length = skipped.snoozed_length;
+        self.resumed = skipped.resumed;
+        self.pending_gap = None;
+        self.waited = Duration::ZERO;
+        self.reminders = 0;
+        self.phase = Phase::Running;
+        let session = self.session();
+        self.emit(TimerEvent::SkipUndone {
+            kind: session.state,
+            number: session.number,
+            duration: self.length,
+            remaining: self.remaining(),
+            counts: self.counts,
+        });
+        self.emit(TimerEvent::Upcoming { next: self.next() });
+        Reply::Undone
+    }
+
+    // Skip the break that's waiting to start, counting it as skipped, and
+    // go on to the work session after it, unless the break was the last
+    // session of the run
+    fn skip_waiting_break(&mut self) -> Reply {
+        let session = self.session();
+        self.counts.skipped_sessions += 1;
+        self.emit(TimerEvent::SessionSkipped {
+            kind: session.state,
+            number: session.number,
+        });
+        self.extendable = false;
+        self.snoozed_length = None;
+        let (next, finished) = self.successor(false);
+        if finished {
+            self.finish();
+            return Reply::SkippedBreak;
+        }
+        self.state = next;
+        self.length = self.default_length();
+        self.phase = Phase::Ready;
+        Reply::SkippedBreak
+    }
+
+    // Count `elapsed` towards the wait for the next session, reminding once
+    // the interval is up. A long wait gets a single reminder.
+    fn remind(&mut self, elapsed: Duration) {
+        let Some(interval) = self.config.nag.filter(|_| self.nagging) else {
+            return;
+        };
+        self.waited += elapsed;
+        let due = (self.waited.as_secs_f64() / interval.as_secs_f64()) as u32;
+        if due > self.reminders {
+            self.reminders = due.min(NAG_REPEATS);
+            self.nagging = self.reminders < NAG_REPEATS;
+            self.emit(TimerEvent::Reminder {
+                repeat: self.reminders,
+            });
+        }
+    }
+
+    // Sessions of `kind` last `length` from the next one on, which may be
+    // the one waiting to start. Overtime and a session taken back by
+    // snoozing keep the length they have.
+    fn set_length(&mut self, kind: TimerState, length: Duration) -> Reply {
+        match kind {
+            TimerState::Work => self.config.work = length,
+            TimerState::Break => self.config.break_ = length,
+            TimerState::LongBreak => self.config.long_break = length,
+            TimerState::Stopped => return Reply::Ignored,
+        }
+        match self.phase {
+            Phase::Running | Phase::Paused => self.emit(TimerEvent::Upcoming { next: self.next() }),
+            _ if self.state == kind && self.overtime.is_none() && self.snoozed_length.is_none() => {
+                self.length = length
+            }
+            _ => {}
+        }
+        Reply::LengthSet(kind, length)
+    }
+
+    // Put off the session that's next until `length` has gone by
+    fn start_snooze(&mut self, length: Duration) -> Reply {
+        self.phase = Phase::Snoozed;
+        self.snooze = length;
+        self.snoozed_for = Duration::ZERO;
+        self.emit(TimerEvent::Snoozed {
+            next: self.session(),
+            remaining: length,
+        });
+        Reply::Snoozed(length)
+    }
+
+    fn emit(&mut self, event: TimerEvent) {
+        for listener in &mut self.listeners {
+            listener(&event);
+        }
+    }
+
+    fn tick(&mut self) {
+        let remaining = self.length - Duration::from_secs(self.ticks);
+        self.ticks += 1;
+        self.emit(TimerEvent::Tick { remaining });
+    }
+
+    fn finish(&mut self) {
+        self.phase = Phase::Finished;
+        self.emit(TimerEvent::Finished);
+    }
+
+    fn default_length(&self) -> Duration {
+        self.length_of(self.state)
+    }
+
+    fn length_of(&self, state: TimerState) -> Duration {
+        match state {
+            TimerState::Work => self.config.work,
+            TimerState::Break => self.config.break_,
+            TimerState::LongBreak => self.config.long_break,
+            TimerState::Stopped => Duration::ZERO,
+        }
+    }
+
+    // Start over from the first work session, keeping the totals
+    fn stop(&mut self) {
+        self.state = TimerState::Stopped;
+        self.extendable = false;
+        self.snoozable = false;
+        self.snoozed_length = None;
+        self.overtime = None;
+        self.phase = Phase::Waiting;
+        self.length = Duration::ZERO;
+        self.elapsed = Duration::ZERO;
+        self.cycle_pomodoros = 0;
+        self.cycle_breaks = 0;
+        self.emit(TimerEvent::Stopped {
+            counts: self.counts,
+        });
+    }
+
+    // What follows the current session when it ends, completed or not, and
+    // whether the run is over instead. Overtime goes back to the break it
+    // put off, and a skipped pomodoro doesn't bring the long break closer.
+    fn successor(&self, completed: bool) -> (TimerState, bool) {
+        let config = &self.config;
+        match self.state {
+            TimerState::Work if self.overtime.is_some() => {
+                (self.overtime.unwrap_or(TimerState::Break), false)
+            }
+            TimerState::Work => {
+                let counted = u64::from(completed);
+                let long_break = completed
+                    && (self.cycle_pomodoros + counted)
+                        .is_multiple_of(config.sessions_until_long_break);
+                let finished = config.once
+                    || (config.cycles == Some(self.counts.completed_pomodoros + counted)
+                        && !config.final_break);
+                let state = if long_break {
+                    TimerState::LongBreak
+                } else {
+                    TimerState::Break
+                };
+                (state, finished)
+            }
+            TimerState::Break | TimerState::LongBreak => (
+                TimerState::Work,
+                config.cycles == Some(self.counts.completed_pomodoros),
+            ),
+            TimerState::Stopped => (TimerState::Stopped, false),
+        }
+    }
+
+    // Report how the running session ended and count it, before it's gone
+    fn ended(&mut self, outcome: Outcome) -> SessionEnd {
+        let end = SessionEnd {
+            session: self.session(),
+            outcome,
+            elapsed: self.elapsed,
+            extended: self.extended,
+            nudged: self.nudged,
+            pauses: self.session_pauses,
+            paused: self.session_paused,
+            resumed: self.resumed,
+        };
+        // Whatever comes next, even the same session restarted, is new
+        self.resumed = false;
+        match outcome {
+            Outcome::Abandoned => self.counts.abandoned_sessions += 1,
+            Outcome::Restarted => self.counts.restarted_sessions += 1,
+            // Counted once it's known whether they were overtime
+            Outcome::Completed | Outcome::Skipped => {}
+        }
+        self.emit(TimerEvent::SessionEnded { end });
+        end
+    }
+
+    // Abandon the session without moving on, leaving the caller to stop
+    // the timer or finish
+    fn cut_short(&mut self) -> SessionEnd {
+        let end = self.ended(Outcome::Abandoned);
+        self.pending_gap = None;
+        if self.state == TimerState::Work {
+            self.counts.focused += self.elapsed;
+        }
+        self.overtime = None;
+        self.extendable = false;
+        self.snoozable = false;
+        end
+    }
+
+    // Record how the session went and move on to the next one. A
+    // restarted session is left to the caller.
+    fn end_session(&mut self, outcome: Outcome) -> SessionEnd {
+        let end = self.ended(outcome);
+        self.pending_gap = None;
+        if self.state == TimerState::Work {
+            self.counts.focused += self.elapsed;
+        }
+        let completed = outcome == Outcome::Completed;
+        let (next, finished) = self.successor(completed);
+        let overtime = self.overtime.take();
+        // Only a completed work session can be given overtime, including
+        // overtime itself
+        self.extendable = completed && self.state == TimerState::Work;
+        self.snoozable = completed;
+
+        let (kind, number) = (end.session.state, end.session.number);
+        match outcome {
+            Outcome::Restarted => return end,
+            // Counted as abandoned when it ended
+            Outcome::Abandoned => {
+                self.emit(TimerEvent::SessionAbandoned { kind, number });
+            }
+            // Skipping overtime just goes on to the break
+            Outcome::Skipped if overtime.is_some() => {
+                self.emit(TimerEvent::SessionSkipped { kind, number });
+            }
+            Outcome::Skipped => {
+                self.counts.skipped_sessions += 1;
+                self.emit(TimerEvent::SessionSkipped { kind, number });
+            }
+            // Reported below, once it's known what comes next
+            Outcome::Completed => {}
+        }
+
+        let config = &self.config;
+        let mut waiting = false;
+        match self.state {
+            // The pomodoro was counted when it first finished
+            TimerState::Work if overtime.is_some() => {}
+            TimerState::Work => {
+                // Skipped work sessions don't bring the long break closer
+                if completed {
+                    self.counts.completed_pomodoros += 1;
+                    self.cycle_pomodoros += 1;
+                }
+                // An abandoned pomodoro doesn't lead straight into a break,
+                // the user chooses between a break or work
+                waiting |= !completed;
+            }
+            TimerState::Break | TimerState::LongBreak if completed => {
+                self.counts.breaks_taken += 1;
+                self.cycle_breaks += 1;
+            }
+            TimerState::Break | TimerState::LongBreak | TimerState::Stopped => {}
+        }
+        self.state = next;
+        // Whether the next session waits depends on what it is
+        waiting |= match self.state {
+            TimerState::Work => !config.auto_start_work,
+            _ => !config.auto_start_breaks,
+        };
+
+        self.length = self.default_length();
+        self.elapsed = Duration::ZERO;
+        if completed {
+            let next = (!finished).then(|| Session {
+                length: self.next_override.unwrap_or(self.length),
+                ..self.session()
+            });
+            self.emit(TimerEvent::SessionCompleted { kind, number, next });
+        }
+        if finished {
+            self.finish();
+        } else if waiting {
+            self.phase = Phase::Waiting;
+            // Only a session that ran its course leaves someone to remind
+            self.nagging = completed && self.config.nag.is_some();
+            self.waited = Duration::ZERO;
+            self.reminders = 0;
+        } else {
+            self.phase = Phase::Ready;
+        }
+        end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::{Arc, Mutex};
+
+    use chrono::NaiveDate;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    fn config() -> PomodoroConfigBuilder {
+        PomodoroConfig::builder()
+            .work(minutes(25))
+            .break_(minutes(5))
+            .long_break(minutes(15))
+    }
+
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, 14)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap()
+    }
+
+    // A timer whose first session has begun
+    fn started(config: PomodoroConfigBuilder) -> PomodoroTimer {
+        let mut timer = PomodoroTimer::new(config.build().unwrap());
+        timer.begin(now());
+        timer
+    }
+
+    // Every event the timer sends from now on
+    fn record(timer: &mut PomodoroTimer) -> Arc<Mutex<Vec<TimerEvent>>> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        timer.subscribe(move |event| sink.lock().unwrap().push(*event));
+        events
+    }
+
+    fn ticks(events: &Mutex<Vec<TimerEvent>>) -> Vec<u64> {
+        events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|event| match event {
+                TimerEvent::Tick { remaining } => Some(remaining.as_secs()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pausing_keeps_the_remaining_time() {
+        for paused in [secs(1), secs(59), minutes(10), minutes(3 * 60)] {
+            let mut timer = started(config());
+            timer.advance(secs(12 * 60 + 30));
+            let remaining = timer.remaining();
+            assert_eq!(remaining, secs(12 * 60 + 30));
+
+            assert_eq!(timer.handle(TimerCommand::Pause), Reply::Paused);
+            // Paused time comes in bits, as the loop wakes up for each tick
+            let mut left = paused;
+            while !left.is_zero() {
+                let step = left.min(Duration::from_millis(700));
+                assert_eq!(timer.advance(step), None);
+                left -= step;
+            }
+            assert_eq!(timer.phase(), Phase::Paused);
+            assert_eq!(timer.remaining(), remaining);
+            assert_eq!(timer.counts().paused, paused);
+
+            assert_eq!(timer.handle(TimerCommand::Resume), Reply::Resumed);
+            assert_eq!(timer.phase(), Phase::Running);
+            assert_eq!(timer.remaining(), remaining);
+
+            // What's left runs for exactly as long as it had to go
+            assert_eq!(timer.advance(remaining - secs(1)), None);
+            let end = timer.advance(secs(1)).unwrap();
+            assert_eq!(end.outcome, Outcome::Completed);
+            assert_eq!(end.elapsed, minutes(25));
+            assert_eq!(end.pauses, 1);
+            assert_eq!(end.paused, paused);
+        }
+    }
+
+    #[test]
+    fn pausing_twice_keeps_the_remaining_time() {
+        let mut timer = started(config());
+        timer.advance(minutes(5));
+        timer.handle(TimerCommand::Pause);
+        timer.advance(minutes(2));
+        timer.handle(TimerCommand::Resume);
+        timer.advance(minutes(5));
+        timer.handle(TimerCommand::Pause);
+        timer.advance(minutes(30));
+        timer.handle(TimerCommand::Resume);
+        assert_eq!(timer.remaining(), minutes(15));
+        assert_eq!(timer.counts().paused, minutes(32));
+    }
+
+    #[test]
+    fn every_second_is_ticked_once() {
+        let mut timer = PomodoroTimer::new(config().work(secs(60)).build().unwrap());
+        let events = record(&mut timer);
+        timer.begin(now());
+        // A slow tick handler brings the loop back late, by varying amounts,
+        // sometimes more than a second
+        let lags = [0, 300, 1700, 10, 2500, 999, 1000, 4100];
+        let mut lag = lags.iter().cycle();
+        loop {
+            let wait = timer.until_next_tick() + Duration::from_millis(*lag.next().unwrap());
+            if timer.advance(wait).is_some() {
+                break;
+            }
+        }
+        assert_eq!(ticks(&events), (1..=60).rev().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn ticks_on_time_are_whole_seconds_apart() {
+        let mut timer = PomodoroTimer::new(config().work(secs(10)).build().unwrap());
+        let events = record(&mut timer);
+        timer.begin(now());
+        assert_eq!(timer.until_next_tick(), secs(1));
+        timer.advance(Duration::from_millis(400));
+        assert_eq!(timer.until_next_tick(), Duration::from_millis(600));
+        timer.advance(Duration::from_millis(600));
+        assert_eq!(timer.until_next_tick(), secs(1));
+        assert_eq!(ticks(&events), [10, 9]);
+    }
+
+    #[test]
+    fn work_becomes_a_break_once_its_time_is_up() {
+        let mut timer = started(config());
+        assert_eq!(timer.state(), TimerState::Work);
+        assert_eq!(timer.advance(minutes(25) - secs(1)), None);
+        assert_eq!(timer.state(), TimerState::Work);
+        assert_eq!(timer.counts().completed_pomodoros, 0);
+
+        let end = timer.advance(secs(1)).unwrap();
+        assert_eq!(end.outcome, Outcome::Completed);
+        assert_eq!(end.session.state, TimerState::Work);
+        assert_eq!(timer.state(), TimerState::Break);
+        assert_eq!(timer.phase(), Phase::Ready);
+        assert_eq!(timer.counts().completed_pomodoros, 1);
+        assert_eq!(timer.counts().focused, minutes(25));
+
+        timer.begin(now());
+        assert_eq!(timer.session().length, minutes(5));
+        timer.advance(minutes(5));
+        assert_eq!(timer.state(), TimerState::Work);
+        assert_eq!(timer.counts().breaks_taken, 1);
+    }
+
+    #[test]
+    fn long_break_after_the_last_pomodoro_of_a_cycle() {
+        let mut timer = started(config().sessions_until_long_break(2));
+        let mut kinds = Vec::new();
+        for _ in 0..4 {
+            timer.advance(timer.remaining());
+            kinds.push(timer.state());
+            timer.begin(now());
+        }
+        assert_eq!(
+            kinds,
+            [
+                TimerState::Break,
+                TimerState::Work,
+                TimerState::LongBreak,
+                TimerState::Work
+            ]
+        );
+        assert_eq!(timer.counts().completed_pomodoros, 2);
+        // Only a stop or a long break taken early numbers them afresh
+        assert_eq!(timer.session().number, 3);
+    }
+
+    #[test]
+    fn events_come_in_a_fixed_order() {
+        let mut timer = PomodoroTimer::new(config().work(secs(3)).break_(secs(2)).build().unwrap());
+        let events = record(&mut timer);
+        timer.begin(now());
+        for _ in 0..3 {
+            timer.advance(secs(1));
+        }
+        timer.begin(now());
+        timer.handle(TimerCommand::Pause);
+        timer.handle(TimerCommand::Resume);
+        timer.handle(TimerCommand::Quit);
+
+        let work = Session {
+            state: TimerState::Work,
+            number: 1,
+            length: secs(3),
+        };
+        let break_ = Session {
+            state: TimerState::Break,
+            number: 1,
+            length: secs(2),
+        };
+        let after_work = Counts {
+            completed_pomodoros: 1,
+            focused: secs(3),
+            ..Counts::default()
+        };
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                TimerEvent::SessionStarted {
+                    kind: TimerState::Work,
+                    number: 1,
+                    duration: secs(3),
+                    counts: Counts::default(),
+                    resumed: false,
+                },
+                TimerEvent::Upcoming { next: Some(break_) },
+                TimerEvent::Tick { remaining: secs(3) },
+                TimerEvent::Tick { remaining: secs(2) },
+                TimerEvent::Tick { remaining: secs(1) },
+                TimerEvent::SessionEnded {
+                    end: SessionEnd {
+                        session: work,
+                        outcome: Outcome::Completed,
+                        elapsed: secs(3),
+                        extended: Duration::ZERO,
+                        nudged: 0,
+                        pauses: 0,
+                        paused: Duration::ZERO,
+                        resumed: false,
+                    },
+                },
+                TimerEvent::SessionCompleted {
+                    kind: TimerState::Work,
+                    number: 1,
+                    next: Some(break_),
+                },
+                TimerEvent::SessionStarted {
+                    kind: TimerState::Break,
+                    number: 1,
+                    duration: secs(2),
+                    counts: after_work,
+                    resumed: false,
+                },
+                TimerEvent::Upcoming {
+                    next: Some(Session { number: 2, ..work })
+                },
+                TimerEvent::Tick { remaining: secs(2) },
+                TimerEvent::Paused { away: None },
+                TimerEvent::Resumed { counted: None },
+                TimerEvent::SessionEnded {
+                    end: SessionEnd {
+                        session: break_,
+                        outcome: Outcome::Abandoned,
+                        elapsed: Duration::ZERO,
+                        extended: Duration::ZERO,
+                        nudged: 0,
+                        pauses: 1,
+                        paused: Duration::ZERO,
+                        resumed: false,
+                    },
+                },
+                TimerEvent::Finished,
+            ]
+        );
+    }
+
+    #[test]
+    fn listeners_get_each_event_in_the_order_they_subscribed() {
+        let mut timer = PomodoroTimer::new(config().work(secs(2)).build().unwrap());
+        let log = Arc::new(Mutex::new(Vec::new()));
+        for listener in 0..3 {
+            let log = log.clone();
+            timer.subscribe(move |event| log.lock().unwrap().push((listener, *event)));
+        }
+        timer.begin(now());
+        timer.advance(secs(1));
+        timer.handle(TimerCommand::Skip);
+
+        let log = log.lock().unwrap();
+        assert!(log.len() > 3);
+        for (i, (listener, event)) in log.iter().enumerate() {
+            assert_eq!(*listener, i % 3);
+            assert_eq!(*event, log[i - i % 3].1);
+        }
+    }
+
+    // The timer with `state` as its session in `phase`, got to the way a
+    // user would: sessions run a minute in, or waiting once the one before
+    // them finished, with the long break after the second pomodoro
+    fn in_state(state: TimerState, phase: Phase) -> PomodoroTimer {
+        use Phase::*;
+        use TimerState::*;
+
+        let mut timer = match (state, phase) {
+            (Work, Ready) | (_, Finished) => {
+                let config = config().sessions_until_long_break(2).auto_start(false);
+                PomodoroTimer::new(config.build().unwrap())
+            }
+            (Stopped, _) => in_state(Work, Running),
+            (_, Running) => in_state(state, Ready),
+            (_, Paused) => in_state(state, Running),
+            (_, Snoozed) => in_state(state, Waiting),
+            (Break, Waiting) => in_state(Work, Running),
+            (Break | LongBreak, Ready) => in_state(state, Waiting),
+            (Work, Waiting) => in_state(Break, Running),
+            (LongBreak, Waiting) => in_state(Work, Waiting),
+        };
+        match (state, phase) {
+            (Work, Ready) => {}
+            (_, Finished) => {
+                timer.handle(TimerCommand::Quit);
+            }
+            (Stopped, _) => {
+                timer.handle(TimerCommand::Stop);
+            }
+            (_, Running) => {
+                timer.begin(now());
+                timer.advance(minutes(1));
+            }
+            (_, Paused) => {
+                timer.handle(TimerCommand::Pause);
+            }
+            (_, Snoozed) => {
+                timer.handle(TimerCommand::Snooze(minutes(5)));
+            }
+            (Break, Waiting) => {
+                timer.advance(minutes(24));
+            }
+            (Break | LongBreak, Ready) => {
+                timer.handle(TimerCommand::Start);
+            }
+            (Work, Waiting) => {
+                timer.advance(minutes(4));
+            }
+            (LongBreak, Waiting) => {
+                timer.handle(TimerCommand::Start);
+                timer.begin(now());
+                timer.advance(minutes(25));
+            }
+        }
+        assert_eq!((timer.state(), timer.phase()), (state, phase));
+        timer
+    }
+
+    // Every command, which stops compiling when one is added so it can't be
+    // left out of the table
+    fn commands() -> [TimerCommand; 26] {
+        use TimerCommand::*;
+
+        let commands = [
+            Pause,
+            Resume,
+            Skip,
+            Start,
+            OverrideNext(minutes(10)),
+            Extend(minutes(5)),
+            Restart,
+            LongBreak,
+            TakeBreak,
+            SkipBreak,
+            Undo,
+            AddMinute,
+            RemoveMinute,
+            Snooze(minutes(5)),
+            SetWork(minutes(30)),
+            SetBreak(minutes(10)),
+            SetLongBreak(minutes(20)),
+            Overtime(minutes(5)),
+            SaveConfig,
+            ToggleElapsed,
+            ToggleMute,
+            Status,
+            Goal,
+            ToggleStrict,
+            Stop,
+            Quit,
+        ];
+        for command in &commands {
+            match command {
+                Pause | Resume | Skip | Start | OverrideNext(_) | Extend(_) | Restart
+                | LongBreak | TakeBreak | SkipBreak | Undo | AddMinute | RemoveMinute
+                | Snooze(_) | SetWork(_) | SetBreak(_) | SetLongBreak(_) | Overtime(_)
+                | SaveConfig | ToggleElapsed | ToggleMute | Status | Goal | ToggleStrict | Stop
+                | Quit => {}
+            }
+        }
+        commands
+    }
+
+    type Start = (TimerState, Phase);
+    type Move = (TimerCommand, TimerState, Phase);
+
+    // Every state the timer's session can be in, in every phase it can be
+    // in with it, and the state and phase each command leaves it in. Only
+    // the commands that change either are listed, the rest must leave both
+    // as they were.
+    fn transitions() -> Vec<(Start, Vec<Move>)> {
+        use Phase::*;
+        use TimerCommand::*;
+        use TimerState::{Break as Short, LongBreak as Long, Stopped, Work};
+
+        // A break that's waiting, or snoozed, to be started
+        let waiting_break = |kind: TimerState, phase: Phase| {
+            let mut moves = vec![
+                (Skip, Work, Ready),
+                (Start, kind, Ready),
+                (LongBreak, Long, Ready),
+                (TakeBreak, Short, Ready),
+                (SkipBreak, Work, Ready),
+                (Overtime(minutes(5)), Work, Ready),
+                (Stop, Stopped, Waiting),
+                (Quit, kind, Finished),
+            ];
+            if phase == Waiting {
+                moves.push((Snooze(minutes(5)), kind, Snoozed));
+            }
+            ((kind, phase), moves)
+        };
+        // A break that's running, or paused, with the work session after it
+        // waiting once it's skipped
+        let running_break = |kind: TimerState, phase: Phase| {
+            let mut moves = vec![
+                (Skip, Work, Waiting),
+                (SkipBreak, Work, Ready),
+                (Overtime(minutes(5)), Work, Ready),
+                (Stop, Stopped, Waiting),
+                (Quit, kind, Finished),
+            ];
+            moves.push(match phase {
+                Running => (Pause, kind, Paused),
+                _ => (Resume, kind, Running),
+            });
+            if phase == Paused {
+                moves.push((Restart, kind, Running));
+            }
+            if kind == Short {
+                moves.push((LongBreak, Long, Ready));
+            }
+            ((kind, phase), moves)
+        };
+        // A work session that's running, or paused
+        let running_work = |phase: Phase| {
+            let mut moves = vec![
+                (Skip, Short, Waiting),
+                (LongBreak, Long, Ready),
+                (Stop, Stopped, Waiting),
+                (Quit, Work, Finished),
+            ];
+            match phase {
+                Running => moves.push((Pause, Work, Paused)),
+                _ => moves.extend([(Resume, Work, Running), (Restart, Work, Running)]),
+            }
+            ((Work, phase), moves)
+        };
+        // A work session that's waiting, or snoozed, to be started
+        let waiting_work = |phase: Phase| {
+            let mut moves = vec![
+                (Start, Work, Ready),
+                (LongBreak, Long, Ready),
+                (TakeBreak, Short, Ready),
+                (Stop, Stopped, Waiting),
+                (Quit, Work, Finished),
+            ];
+            if phase == Waiting {
+                moves.push((Snooze(minutes(5)), Work, Snoozed));
+            }
+            ((Work, phase), moves)
+        };
+
+        vec![
+            (
+                (Work, Ready),
+                vec![
+                    (LongBreak, Long, Ready),
+                    (TakeBreak, Short, Ready),
+                    (Stop, Stopped, Waiting),
+                    (Quit, Work, Finished),
+                ],
+            ),
+            running_work(Running),
+            running_work(Paused),
+            waiting_work(Waiting),
+            waiting_work(Snoozed),
+            (
+                (Short, Ready),
+                vec![
+                    (Skip, Work, Ready),
+                    (LongBreak, Long, Ready),
+                    (SkipBreak, Work, Ready),
+                    (Overtime(minutes(5)), Work, Ready),
+                    (Stop, Stopped, Waiting),
+                    (Quit, Short, Finished),
+                ],
+            ),
+            running_break(Short, Running),
+            running_break(Short, Paused),
+            waiting_break(Short, Waiting),
+            waiting_break(Short, Snoozed),
+            (
+                (Long, Ready),
+                vec![
+                    (Skip, Work, Ready),
+                    (TakeBreak, Short, Ready),
+                    (SkipBreak, Work, Ready),
+                    (Overtime(minutes(5)), Work, Ready),
+                    (Stop, Stopped, Waiting),
+                    (Quit, Long, Finished),
+                ],
+            ),
+            running_break(Long, Running),
+            running_break(Long, Paused),
+            waiting_break(Long, Waiting),
+            waiting_break(Long, Snoozed),
+            (
+                (Stopped, Waiting),
+                vec![
+                    (Start, Work, Ready),
+                    (LongBreak, Long, Ready),
+                    (TakeBreak, Short, Ready),
+                    (Quit, Stopped, Finished),
+                ],
+            ),
+            ((Work, Finished), vec![]),
+        ]
+    }
+
+    #[test]
+    fn every_command_in_every_state() {
+        let table = transitions();
+        assert_eq!(table.len(), 17);
+        for ((state, phase), moves) in table {
+            for command in commands() {
+                let expected = moves
+                    .iter()
+                    .find(|(listed, _, _)| *listed == command)
+                    .map_or((state, phase), |&(_, state, phase)| (state, phase));
+                let mut timer = in_state(state, phase);
+                let reply = timer.handle(command);
+                assert_eq!(
+                    (timer.state(), timer.phase()),
+                    expected,
+                    "{:?} in {:?} {:?} replied {:?}, leaving {:?}",
+                    command,
+                    state,
+                    phase,
+                    reply,
+                    timer
+                );
+                if phase == Phase::Finished {
+                    assert_eq!(reply, Reply::Ignored);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn strict_work_sessions_refuse_to_be_divided() {
+        for phase in [Phase::Running, Phase::Paused] {
+            for command in [
+                TimerCommand::Pause,
+                TimerCommand::Skip,
+                TimerCommand::Restart,
+                TimerCommand::Extend(minutes(5)),
+                TimerCommand::AddMinute,
+                TimerCommand::RemoveMinute,
+            ] {
+                let mut timer = in_state(TimerState::Work, phase);
+                timer.handle(TimerCommand::ToggleStrict);
+                assert_eq!(timer.handle(command), Reply::Strict);
+                assert_eq!(timer.phase(), phase);
+                assert_eq!(timer.remaining(), minutes(24));
+            }
+        }
+    }
+
+    #[test]
+    fn sessions_abandoned_after_sleeping_count_as_abandoned() {
+        // Abandoned as the config says, or when asked, which strict mode
+        // allows
+        for (policy, strict) in [
+            (SleepPolicy::Abandon, false),
+            (SleepPolicy::Ask, false),
+            (SleepPolicy::Ask, true),
+        ] {
+            let mut timer = started(config().on_sleep(policy).strict(strict));
+            let events = record(&mut timer);
+            timer.advance(minutes(10));
+            let end = match timer.away(minutes(30)) {
+                Some(end) => end,
+                None => match timer.handle(TimerCommand::Skip) {
+                    Reply::Ended(end) => end,
+                    reply => panic!("{policy}: {reply:?}"),
+                },
+            };
+            assert_eq!(end.outcome, Outcome::Abandoned);
+            assert_eq!(end.elapsed, minutes(10));
+
+            let counts = timer.counts();
+            assert_eq!(counts.abandoned_sessions, 1);
+            assert_eq!(counts.skipped_sessions, 0);
+            assert_eq!(counts.completed_pomodoros, 0);
+            assert_eq!(counts.focused, minutes(10));
+            // On to the break, like after a skip, but for good
+            assert_eq!(
+                (timer.state(), timer.phase()),
+                (TimerState::Break, Phase::Waiting)
+            );
+            assert_eq!(timer.handle(TimerCommand::Undo), Reply::Ignored);
+
+            let events = events.lock().unwrap();
+            assert!(events.contains(&TimerEvent::SessionAbandoned {
+                kind: TimerState::Work,
+                number: 1,
+            }));
+            assert!(
+                !events
+                    .iter()
+                    .any(|event| matches!(event, TimerEvent::SessionSkipped { .. }))
+            );
+        }
+    }
+
+    #[test]
+    fn only_the_session_taken_up_is_marked_resumed() {
+        let mut timer = PomodoroTimer::new(config().build().unwrap());
+        let events = record(&mut timer);
+        timer.resume(Checkpoint {
+            session: Session {
+                state: TimerState::Work,
+                number: 3,
+                length: minutes(25),
+            },
+            elapsed: minutes(18),
+            paused: Duration::ZERO,
+            counts: Counts::default(),
+            cycle_pomodoros: 2,
+            cycle_breaks: 2,
+        });
+        timer.begin(now());
+        assert_eq!(timer.remaining(), minutes(7));
+        timer.advance(minutes(7));
+        timer.begin(now());
+
+        let resumed: Vec<_> = events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|event| match *event {
+                TimerEvent::SessionStarted { kind, resumed, .. } => Some((kind, resumed)),
+                TimerEvent::SessionEnded { end } => Some((end.session.state, end.resumed)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            resumed,
+            [
+                (TimerState::Work, true),
+                (TimerState::Work, true),
+                (TimerState::Break, false)
+            ]
+        );
+    }
+
+    // Property tests: the timer is played a random run of commands and of
+    // time passing, as the driver would, and shrunk to the shortest run that
+    // breaks a property
+    mod properties {
+        use proptest::prelude::*;
+
+        use super::*;
+
+        #[derive(Clone, Copy, Debug)]
+        enum Step {
+            Command(TimerCommand),
+            // Time passing as the driver counts it
+            Advance(Duration),
+            // Time the driver found the timer away for, e.g. asleep
+            Away(Duration),
+        }
+
+        fn command() -> impl Strategy<Value = TimerCommand> {
+            let length = (1u64..=30).prop_map(minutes);
+            prop_oneof![
+                Just(TimerCommand::Pause),
+                Just(TimerCommand::Resume),
+                Just(TimerCommand::Skip),
+                Just(TimerCommand::Start),
+                length.clone().prop_map(TimerCommand::OverrideNext),
+                length.clone().prop_map(TimerCommand::Extend),
+                Just(TimerCommand::Restart),
+                Just(TimerCommand::LongBreak),
+                Just(TimerCommand::TakeBreak),
+                Just(TimerCommand::SkipBreak),
+                Just(TimerCommand::Undo),
+                Just(TimerCommand::AddMinute),
+                Just(TimerCommand::RemoveMinute),
+                length.clone().prop_map(TimerCommand::Snooze),
+                length.clone().prop_map(TimerCommand::SetWork),
+                length.clone().prop_map(TimerCommand::SetBreak),
+                length.clone().prop_map(TimerCommand::SetLongBreak),
+                length.prop_map(TimerCommand::Overtime),
+                Just(TimerCommand::ToggleStrict),
+                Just(TimerCommand::Stop),
+            ]
+        }
+
+        fn step() -> impl Strategy<Value = Step> {
+            prop_oneof![
+                3 => command().prop_map(Step::Command),
+                3 => (1u64..=600).prop_map(|s| Step::Advance(secs(s))),
+                1 => (1u64..=3600).prop_map(|s| Step::Away(secs(s))),
+            ]
+        }
+
+        fn steps() -> impl Strategy<Value = Vec<Step>> {
+            prop::collection::vec(step(), 0..60)
+        }
+
+        // Short sessions, so a run gets through a few of them
+        fn timer() -> impl Strategy<Value = PomodoroTimer> {
+            let policy = prop_oneof![
+                Just(SleepPolicy::Ask),
+                Just(SleepPolicy::Count),
+                Just(SleepPolicy::Discard),
+                Just(SleepPolicy::Abandon),
+            ];
+            (any::<bool>(), any::<bool>(), policy).prop_map(|(auto_start, strict, policy)| {
+                let config = PomodoroConfig::builder()
+                    .work(minutes(5))
+                    .break_(minutes(1))
+                    .long_break(minutes(3))
+                    .sessions_until_long_break(2)
+                    .auto_start(auto_start)
+                    .strict(strict)
+                    .sleep_threshold(minutes(1))
+                    .on_sleep(policy);
+                PomodoroTimer::new(config.build().unwrap())
+            })
+        }
+
+        // Play `step` the way the driver would, returning the wall-clock time
+        // it took
+        fn play(timer: &mut PomodoroTimer, step: Step) -> Duration {
+            if timer.phase() == Phase::Ready {
+                timer.begin(now());
+            }
+            match step {
+                Step::Command(command) => {
+                    timer.handle(command);
+                    Duration::ZERO
+                }
+                Step::Advance(elapsed) => {
+                    timer.advance(elapsed);
+                    elapsed
+                }
+                Step::Away(away) => {
+                    if timer.phase() == Phase::Running {
+                        timer.away(away);
+                    }
+                    away
+                }
+            }
+        }
+
+        proptest! {
+            #[test]
+            fn focused_time_never_exceeds_wall_time(mut timer in timer(), steps in steps()) {
+                let mut wall = Duration::ZERO;
+                for step in steps {
+                    wall += play(&mut timer, step);
+                    prop_assert!(timer.counts().focused <= wall, "{:?}", timer);
+                }
+            }
+
+            #[test]
+            fn pause_and_resume_keep_the_remaining_time(
+                mut timer in timer(),
+                steps in steps(),
+                paused in (1u64..=3600).prop_map(secs),
+            ) {
+                for step in steps {
+                    play(&mut timer, step);
+                }
+                let remaining = timer.remaining();
+                // Strict mode refuses to pause a work session
+                if timer.phase() == Phase::Running
+                    && timer.handle(TimerCommand::Pause) == Reply::Paused
+                {
+                    timer.advance(paused);
+                    timer.handle(TimerCommand::Resume);
+                    prop_assert_eq!(timer.remaining(), remaining, "{:?}", timer);
+                }
+            }
+
+            #[test]
+            fn completed_pomodoros_never_go_down(mut timer in timer(), steps in steps()) {
+                let mut completed = 0;
+                for step in steps {
+                    play(&mut timer, step);
+                    let now = timer.counts().completed_pomodoros;
+                    prop_assert!(now >= completed, "{} after {}: {:?}", now, completed, timer);
+                    completed = now;
+                }
+            }
+
+            // The driver checks the phase straight after handling a command,
+            // so a run that's Finished by then doesn't wait for another tick
+            #[test]
+            fn quit_finishes_at_once(mut timer in timer(), steps in steps()) {
+                for step in steps {
+                    play(&mut timer, step);
+                }
+                timer.handle(TimerCommand::Quit);
+                prop_assert_eq!(timer.phase(), Phase::Finished, "{:?}", timer);
+            }
+        }
+    }
+}
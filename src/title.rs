@@ -0,0 +1,75 @@
+use std::{
+    io::{Write, stdout},
+    time::Duration,
+};
+
+use pomodoro_timer::{
+    format::format_clock,
+    icons::EMOJI,
+    timer::{TimerEvent, TimerState},
+};
+
+// Save the terminal's title on its title stack, for restore() to bring back
+const PUSH_TITLE: &str = "\x1b[22;0t";
+
+// Clear our title, for terminals without a title stack, then pop the saved one
+const POP_TITLE: &str = "\x1b]0;\x07\x1b[23;0t";
+
+// The timer listener behind --title, which keeps the remaining time in the
+// terminal's title so it can be seen from the tab bar
+pub struct TerminalTitle {
+    kind: TimerState,
+}
+
+impl TerminalTitle {
+    pub fn new() -> Self {
+        write(PUSH_TITLE);
+        TerminalTitle {
+            kind: TimerState::Work,
+        }
+    }
+
+    pub fn on_event(&mut self, event: &TimerEvent) {
+        match *event {
+            TimerEvent::SessionStarted { kind, duration, .. } => {
+                self.kind = kind;
+                self.set(duration);
+            }
+            TimerEvent::SkipUndone {
+                kind, remaining, ..
+            } => {
+                self.kind = kind;
+                self.set(remaining);
+            }
+            TimerEvent::Tick { remaining } => self.set(remaining),
+            TimerEvent::Paused { .. } => set_title("PAUSED"),
+            TimerEvent::Stopped { .. } => set_title("Stopped"),
+            TimerEvent::Finished => restore(),
+            _ => {}
+        }
+    }
+
+    // "🍅 12:34 Work", "Break 04:59"
+    fn set(&self, remaining: Duration) {
+        let title = match self.kind {
+            TimerState::Work => format!("{} {} Work", EMOJI.work, format_clock(remaining)),
+            kind => format!("{} {}", kind.name(), format_clock(remaining)),
+        };
+        set_title(&title);
+    }
+}
+
+// Put back the title the terminal had before TerminalTitle::new()
+pub fn restore() {
+    write(POP_TITLE);
+}
+
+// OSC 0 sets both the window and the tab title
+fn set_title(title: &str) {
+    write(&format!("\x1b]0;{}\x07", title));
+}
+
+fn write(escape: &str) {
+    print!("{}", escape);
+    let _ = stdout().flush();
+}
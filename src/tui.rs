@@ -0,0 +1,475 @@
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    time::Duration,
+};
+
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout},
+    style::{Style, Stylize},
+    widgets::{Gauge, Paragraph, Wrap},
+};
+
+use pomodoro_timer::{
+    config::SleepPolicy,
+    duration::format_duration,
+    format::{BIG_ROWS, big_text, format_clock, next_preview},
+    keys::{Action, Amounts, KeyBindings},
+    timer::{TimerCommand, TimerEvent, TimerState},
+};
+
+use crate::confirm;
+
+// Width of the progress gauge under the countdown
+const GAUGE_WIDTH: u16 = 40;
+
+// Where the timer is, as far as the screen is concerned
+#[derive(Clone, Copy, PartialEq)]
+enum Showing {
+    // Before the first session starts
+    Starting,
+    Running,
+    Paused,
+    // A session has ended and the next one hasn't started yet
+    Between,
+    // The next session starts once the snooze is over
+    Snoozed,
+    Stopped,
+}
+
+// The full-screen frontend behind --tui. Like Console it only follows the
+// timer's events, it just draws them differently.
+pub struct Tui {
+    terminal: DefaultTerminal,
+    keys: KeyBindings,
+    showing: Showing,
+    // The kind, number and length of the current session
+    session: (TimerState, u64, Duration),
+    remaining: Duration,
+    // How long the timer has been paused this time
+    paused_for: Duration,
+    // What's left of the snooze
+    snooze_left: Duration,
+    completed_pomodoros: u64,
+    // What comes after the current session, as next_preview() puts it
+    next: String,
+    // The last notable thing that happened, shown above the footer
+    status: String,
+    // Count up instead of down, switched by the toggle key
+    show_elapsed: Arc<AtomicBool>,
+    // Marked in the title while set, switched by the mute key
+    muted: Arc<AtomicBool>,
+    // Work sessions are marked [STRICT] while it's on
+    strict: bool,
+}
+
+impl Tui {
+    pub fn new(
+        terminal: DefaultTerminal,
+        keys: KeyBindings,
+        show_elapsed: Arc<AtomicBool>,
+        muted: Arc<AtomicBool>,
+        strict: bool,
+    ) -> Self {
+        Tui {
+            terminal,
+            keys,
+            showing: Showing::Starting,
+            session: (TimerState::Work, 0, Duration::ZERO),
+            remaining: Duration::ZERO,
+            paused_for: Duration::ZERO,
+            snooze_left: Duration::ZERO,
+            completed_pomodoros: 0,
+            next: String::new(),
+            status: String::new(),
+            show_elapsed,
+            muted,
+            strict,
+        }
+    }
+
+    pub fn on_event(&mut self, event: &TimerEvent) {
+        let (kind, number, _) = self.session;
+        match *event {
+            TimerEvent::SessionStarted {
+                kind,
+                number,
+                duration,
+                ..
+            } => {
+                self.session = (kind, number, duration);
+                self.remaining = duration;
+                self.showing = Showing::Running;
+            }
+            TimerEvent::Restarted { duration, .. } => {
+                self.remaining = duration;
+                self.showing = Showing::Running;
+                self.status = format!("{} restarted.", kind.label(number));
+            }
+            TimerEvent::SkipUndone {
+                kind,
+                number,
+                duration,
+                remaining,
+                counts,
+            } => {
+                self.session = (kind, number, duration);
+                self.remaining = remaining;
+                self.completed_pomodoros = counts.completed_pomodoros;
+                self.showing = Showing::Running;
+                self.status = format!("Skip undone, back to {}.", kind.label(number));
+            }
+            TimerEvent::Nudged {
+                duration,
+                remaining,
+            } => {
+                self.session.2 = duration;
+                self.remaining = remaining;
+                self.status = format!(
+                    "{} left in {}.",
+                    format_clock(remaining),
+                    kind.label(number)
+                );
+            }
+            TimerEvent::Extended { added, duration } => {
+                self.session.2 = duration;
+                self.remaining += added;
+                self.status = format!(
+                    "Added {} to {}.",
+                    format_duration(added),
+                    kind.label(number)
+                );
+            }
+            TimerEvent::Upcoming { next } => self.next = next_preview(kind, next),
+            TimerEvent::Tick { remaining } => self.remaining = remaining,
+            TimerEvent::Paused { away: None } => {
+                self.showing = Showing::Paused;
+                self.paused_for = Duration::ZERO;
+                self.status = "Timer paused.".to_string();
+            }
+            TimerEvent::Paused { away: Some(away) } => {
+                self.showing = Showing::Paused;
+                self.paused_for = Duration::ZERO;
+                self.status = format!(
+                    "Timer was suspended for {}. Press Enter to count that time, \
+                     '{}' to resume without it, or '{}' to abandon {}.",
+                    format_duration(away),
+                    self.keys.key(Action::Resume),
+                    self.keys.key(Action::Skip),
+                    kind.label(number)
+                );
+            }
+            TimerEvent::PausedTick { paused, remaining } => {
+                self.paused_for = paused;
+                self.remaining = remaining;
+            }
+            TimerEvent::Resumed { counted: None } => {
+                self.showing = Showing::Running;
+                self.status = "Timer resumed.".to_string();
+            }
+            TimerEvent::Resumed {
+                counted: Some(counted),
+            } => {
+                self.showing = Showing::Running;
+                self.status = format!(
+                    "Timer resumed, counting the {} away.",
+                    format_duration(counted)
+                );
+            }
+            TimerEvent::Suspended { away, policy } => {
+                let handling = match policy {
+                    SleepPolicy::Count => "counting it".to_string(),
+                    SleepPolicy::Abandon => format!("abandoning {}", kind.label(number)),
+                    SleepPolicy::Discard | SleepPolicy::Ask => "not counting it".to_string(),
+                };
+                self.status = format!(
+                    "Timer was suspended for {}, {}.",
+                    format_duration(away),
+                    handling
+                );
+            }
+            TimerEvent::SessionCompleted { kind, number, .. } => {
+                if kind == TimerState::Work {
+                    self.completed_pomodoros += 1;
+                }
+                self.remaining = Duration::ZERO;
+                self.showing = Showing::Between;
+                self.status = format!("{} finished!", kind.label(number));
+            }
+            TimerEvent::SessionSkipped { kind, number } => {
+                self.showing = Showing::Between;
+                self.status = format!("Skipped {}.", kind.label(number));
+            }
+            TimerEvent::SessionAbandoned { kind, number } => {
+                self.showing = Showing::Between;
+                self.status = format!("Abandoned {}.", kind.label(number));
+            }
+            TimerEvent::Interrupted { kind, number } => {
+                self.showing = Showing::Between;
+                self.status = format!("Cut {} short for a long break.", kind.label(number));
+            }
+            // Shows the session that's next, as it will be when it starts
+            TimerEvent::Snoozed { next, remaining } => {
+                self.session = (next.state, next.number, next.length);
+                self.remaining = next.length;
+                self.snooze_left = remaining;
+                self.showing = Showing::Snoozed;
+                self.status = format!(
+                    "Snoozing, {} starts in {}.",
+                    next.label(),
+                    format_duration(remaining)
+                );
+            }
+            TimerEvent::SnoozeTick { remaining } => self.snooze_left = remaining,
+            TimerEvent::Stopped { .. } => {
+                self.showing = Showing::Stopped;
+                self.status = "Timer stopped.".to_string();
+            }
+            TimerEvent::StrictToggled { strict } => {
+                self.strict = strict;
+                self.status = format!("Strict mode {}.", if strict { "on" } else { "off" });
+            }
+            // The alert is repeated by the notifier, the screen already
+            // says the session is done
+            TimerEvent::SessionEnded { .. }
+            | TimerEvent::Reminder { .. }
+            | TimerEvent::Acknowledged => return,
+            // main() restores the terminal once the timer thread is done
+            TimerEvent::Finished => return,
+        }
+        self.draw();
+    }
+
+    pub fn draw(&mut self) {
+        let Tui {
+            terminal,
+            keys,
+            showing,
+            session,
+            remaining,
+            paused_for,
+            snooze_left,
+            completed_pomodoros,
+            next,
+            status,
+            show_elapsed,
+            muted,
+            strict,
+        } = self;
+        let (kind, number, length) = *session;
+        let title = match showing {
+            Showing::Starting => "Starting...".to_string(),
+            Showing::Running => kind.label(number),
+            Showing::Paused => format!(
+                "{} (paused for {})",
+                kind.label(number),
+                format_clock(*paused_for)
+            ),
+            Showing::Between => "Press Enter to start the next session".to_string(),
+            Showing::Snoozed => format!(
+                "{} starts in {} (snoozed)",
+                kind.label(number),
+                format_clock(*snooze_left)
+            ),
+            Showing::Stopped => format!(
+                "Stopped, press Enter to start work or '{}' for a break",
+                keys.key(Action::TakeBreak)
+            ),
+        };
+        let title = match showing {
+            Showing::Running | Showing::Paused if *strict && kind == TimerState::Work => {
+                format!("{} [STRICT]", title)
+            }
+            _ => title,
+        };
+        let title = if muted.load(Ordering::Relaxed) {
+            format!("{} \u{1f507}", title)
+        } else {
+            title
+        };
+        let mut countdown = Style::new().bold();
+        if matches!(showing, Showing::Paused | Showing::Snoozed) {
+            countdown = countdown.dim();
+        }
+        let progress = if length.is_zero() {
+            0.0
+        } else {
+            (1.0 - remaining.as_secs_f64() / length.as_secs_f64()).clamp(0.0, 1.0)
+        };
+        // What's next only matters while a session is under way
+        let counts_line = match showing {
+            Showing::Running | Showing::Paused if !next.is_empty() => {
+                format!(
+                    "Completed pomodoros: {} \u{b7} {}",
+                    completed_pomodoros, next
+                )
+            }
+            _ => format!("Completed pomodoros: {}", completed_pomodoros),
+        };
+        let footer = format!(
+            "{} Enter starts the next session.",
+            keys.hint(&[
+                Action::Pause,
+                Action::Resume,
+                Action::Skip,
+                Action::Stop,
+                Action::Help,
+                Action::Quit,
+            ])
+        );
+
+        let clock = if show_elapsed.load(Ordering::Relaxed) {
+            format_clock(length - *remaining)
+        } else {
+            format_clock(*remaining)
+        };
+        let digits = big_text(&clock);
+
+        let _ = terminal.draw(|frame: &mut Frame| {
+            // Block digits when there's room for them, with the title, gauge,
+            // count, status, footer and the gaps between them
+            let area = frame.area();
+            let big = usize::from(area.width) > digits[0].chars().count()
+                && usize::from(area.height) >= BIG_ROWS + 12;
+            let (countdown_text, countdown_rows) = if big {
+                (digits.join("\n"), BIG_ROWS as u16)
+            } else {
+                (clock, 1)
+            };
+            let [_, middle, _, status_area, footer_area] = Layout::vertical([
+                Constraint::Fill(1),
+                Constraint::Length(countdown_rows + 6),
+                Constraint::Fill(1),
+                Constraint::Length(3),
+                Constraint::Length(2),
+            ])
+            .areas(area);
+            let [title_area, countdown_area, gauge_area, counts_area] = Layout::vertical([
+                Constraint::Length(1),
+                Constraint::Length(countdown_rows),
+                Constraint::Length(1),
+                Constraint::Length(1),
+            ])
+            .spacing(1)
+            .areas(middle);
+
+            frame.render_widget(Paragraph::new(title).centered(), title_area);
+            frame.render_widget(
+                Paragraph::new(countdown_text).style(countdown).centered(),
+                countdown_area,
+            );
+            frame.render_widget(
+                Gauge::default().ratio(progress).label(""),
+                gauge_area.centered_horizontally(Constraint::Length(GAUGE_WIDTH)),
+            );
+            frame.render_widget(Paragraph::new(counts_line).centered(), counts_area);
+            frame.render_widget(
+                Paragraph::new(status.as_str())
+                    .wrap(Wrap { trim: true })
+                    .centered(),
+                status_area,
+            );
+            frame.render_widget(
+                Paragraph::new(footer)
+                    .wrap(Wrap { trim: true })
+                    .dim()
+                    .centered(),
+                footer_area,
+            );
+        });
+    }
+}
+
+// Turn key presses into commands without waiting for Enter. Only single
+// character bindings can be typed this way, and setting the next session's
+// length needs the line-based mode. Ctrl+C doesn't raise SIGINT in raw mode,
+// so it's handled here and quits the same way, without asking first.
+pub fn read_keys(
+    sender: mpsc::Sender<TimerCommand>,
+    keys: KeyBindings,
+    amounts: Amounts,
+    tui: Arc<Mutex<Tui>>,
+    interrupted: Arc<AtomicBool>,
+    quit_at_risk: Arc<AtomicBool>,
+) {
+    let show = |status: String| {
+        if let Ok(mut tui) = tui.lock() {
+            tui.status = status;
+            tui.draw();
+        }
+    };
+    // The quit or strict key was just pressed and the question asked, with
+    // the command it's about
+    let mut confirming: Option<TimerCommand> = None;
+    while let Ok(event) = event::read() {
+        let key = match event {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            Event::Resize(..) => {
+                if let Ok(mut tui) = tui.lock() {
+                    tui.draw();
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        let asked = confirming.take();
+        let command = match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                interrupted.store(true, Ordering::SeqCst);
+                TimerCommand::Quit
+            }
+            KeyCode::Enter => TimerCommand::Start,
+            KeyCode::Char(c) => {
+                let key = c.encode_utf8(&mut [0; 4]).to_string();
+                // Anything but yes or no is taken as a command, and pressing
+                // the same key again goes ahead
+                let answer = asked.zip(confirm::answer(&key));
+                match (answer, keys.lookup(&key)) {
+                    (Some((command, true)), _) => command,
+                    (Some((command, false)), _) => {
+                        show(confirm::declined(command).to_string());
+                        continue;
+                    }
+                    // There's only room for the keys, in the status line
+                    (None, Some(Action::Help)) => {
+                        show(keys.hint_all());
+                        continue;
+                    }
+                    (None, Some(Action::Quit))
+                        if asked != Some(TimerCommand::Quit)
+                            && quit_at_risk.load(Ordering::SeqCst) =>
+                    {
+                        confirming = Some(TimerCommand::Quit);
+                        show(confirm::question(TimerCommand::Quit).to_string());
+                        continue;
+                    }
+                    (None, Some(Action::Strict)) if asked != Some(TimerCommand::ToggleStrict) => {
+                        confirming = Some(TimerCommand::ToggleStrict);
+                        show(confirm::question(TimerCommand::ToggleStrict).to_string());
+                        continue;
+                    }
+                    (None, Some(Action::Note)) => {
+                        show("Notes can't be typed in the full-screen view.".to_string());
+                        continue;
+                    }
+                    (None, Some(Action::Extend)) => TimerCommand::Extend(amounts.extend),
+                    (None, Some(Action::Snooze)) => TimerCommand::Snooze(amounts.snooze),
+                    (None, Some(action)) => match action.command() {
+                        Some(command) => command,
+                        None => continue,
+                    },
+                    (None, None) => continue,
+                }
+            }
+            _ => continue,
+        };
+        if sender.send(command).is_err() || command == TimerCommand::Quit {
+            return;
+        }
+    }
+}